@@ -1,73 +1,492 @@
-use crate::config::Config;
+use crate::config::{Config, Session, parse_resolution};
+use crate::ops::{encode, load_upright};
+use crate::persistence::{prune_scaled, save_output, save_state, scaled_path};
+use image::{ImageFormat, imageops::FilterType};
 use std::{
-    path::Path,
+    env, fs, io,
+    path::{Path, PathBuf},
     process::{Command, Stdio},
+    slice, thread,
+    time::SystemTime,
 };
 
-pub fn apply_wallpaper(path: &Path, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+/// Sets `path` as the wallpaper with the backend for the configured session,
+/// then runs the enabled color generators. `output` limits it to one monitor
+/// and is remembered for `--restore-all`. Fails before running anything when
+/// one of the programs isn't installed or the backend can't target `output`.
+pub fn apply_wallpaper(
+    path: &Path,
+    output: Option<&str>,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    require(&required_programs(config))?;
+    require_outputs(output, config)?;
+
+    generate_colors(path, config)?;
+    set_background(path, output, config)?;
+    run_reload_command(config);
+
+    save_state("current", &path.to_string_lossy());
+    if let Some(output) = output {
+        save_output(output, path);
+    }
+
+    Ok(())
+}
+
+/// Runs pywal and hellwal on `path` when they're enabled, leaving the
+/// background alone.
+pub fn generate_colors(path: &Path, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    require(&color_programs(config))?;
     let path_str = path.to_str().unwrap();
+
+    if config.pywal {
+        // Run wal
+        let mut args = expand_args(&config.commands.wal, path_str, None, config);
+        if config.pywal_light && !args.iter().any(|a| a == "-l") {
+            args.push("-l".into());
+        }
+        run(Command::new("wal").args(args))?;
+    }
+    if config.hellwal {
+        // Run hellwal
+        run(Command::new("hellwal").args(expand_args(
+            &config.commands.hellwal,
+            path_str,
+            None,
+            config,
+        )))?;
+    }
+    Ok(())
+}
+
+/// Hands `path` to the backend for the configured session, on `output` only
+/// when given.
+pub fn set_background(
+    path: &Path,
+    output: Option<&str>,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    require(&[backend_program(config)])?;
+    require_outputs(output, config)?;
+
+    // The backend may get copies scaled to the monitors, wal keeps the original
+    let images = if config.mpvpaper {
+        Vec::new()
+    } else {
+        pre_scale_sizes(config, output)
+            .into_iter()
+            .map(|(output, size)| Ok((output, pre_scale(path, size)?)))
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?
+    };
+    let images = if images.is_empty() {
+        vec![(output.map(String::from), path.to_path_buf())]
+    } else {
+        images
+    };
+
+    match config.session {
+        Session::Wayland => {
+            if config.mpvpaper {
+                run(Command::new("mpvpaper").args(expand_args(
+                    &config.commands.mpvpaper,
+                    path.to_str().unwrap(),
+                    output,
+                    config,
+                )))?;
+            } else {
+                for (output, image) in &images {
+                    let output = output.as_deref();
+                    let mut args = expand_args(
+                        &config.commands.swww,
+                        image.to_str().unwrap(),
+                        output,
+                        config,
+                    );
+                    if let Some(output) = output
+                        && !config.commands.swww.iter().any(|a| a.contains("{output}"))
+                    {
+                        args.extend(["--outputs".into(), output.into()]);
+                    }
+                    run(Command::new("swww").args(args))?;
+                }
+            }
+        }
+        Session::X11 => {
+            let images: Vec<&str> = images.iter().map(|(_, i)| i.to_str().unwrap()).collect();
+            run(Command::new("feh").args(feh_args(&images, output, config)))?;
+        }
+    }
+    Ok(())
+}
+
+// Runs `command` to the end with its output discarded, a non-zero exit is an
+// error naming the program
+fn run(command: &mut Command) -> Result<(), Box<dyn std::error::Error>> {
+    let status = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} failed with {}",
+            command.get_program().to_string_lossy(),
+            status
+        )
+        .into())
+    }
+}
+
+/// Whether the backend for the configured session can set the wallpaper of
+/// one monitor: swww always can, feh and mpvpaper only when their command
+/// has an `{output}` or `{monitor}` placeholder.
+pub fn targets_outputs(config: &Config) -> bool {
+    let args = match config.session {
+        Session::Wayland if config.mpvpaper => &config.commands.mpvpaper,
+        Session::Wayland => return true,
+        Session::X11 => &config.commands.feh,
+    };
+    args.iter()
+        .any(|a| a.contains("{output}") || a.contains("{monitor}"))
+}
+
+// Errors when `output` is given but the backend would paint every monitor
+fn require_outputs(
+    output: Option<&str>,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match output {
+        Some(output) if !targets_outputs(config) => Err(format!(
+            "{} can't set the wallpaper of {} alone, add {{output}} to its command",
+            backend_program(config),
+            output
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+// feh puts one image on each screen, in the order xrandr lists them, so a
+// bare {path} takes every image
+fn feh_args(images: &[&str], output: Option<&str>, config: &Config) -> Vec<String> {
+    let mut args = Vec::new();
+    for arg in &config.commands.feh {
+        if arg == "{path}" {
+            args.extend(images.iter().map(|image| image.to_string()));
+        } else {
+            args.extend(expand_args(slice::from_ref(arg), images[0], output, config));
+        }
+    }
+    args
+}
+
+// Replace placeholders in args
+fn expand_args(args: &[String], path: &str, output: Option<&str>, config: &Config) -> Vec<String> {
     let transition = if !config.transition_type.is_empty() {
         config.transition_type.as_str()
     } else {
         "fade"
     };
+    let transition_fps = config.transition_fps.to_string();
+    let transition_duration = config.transition_duration.to_string();
 
-    // Replace placeholders in args
-    let expand_args = |args: &[String]| -> Vec<String> {
-        args.iter()
-            .map(|arg| {
-                arg.replace("{path}", path_str)
-                    .replace("{transition}", transition)
-            })
-            .collect()
-    };
-    if config.pywal {
-        // Run wal
-        Command::new("wal")
-            .args(expand_args(&config.commands.wal))
+    args.iter()
+        .map(|arg| {
+            arg.replace("{path}", path)
+                .replace("{transition}", transition)
+                .replace("{transition_fps}", &transition_fps)
+                .replace("{transition_duration}", &transition_duration)
+                .replace("{output}", output.unwrap_or("*"))
+                .replace("{monitor}", output.unwrap_or("*"))
+                .replace("{backend}", &config.pywal_backend)
+        })
+        .collect()
+}
+
+/// Runs `reload_command`, waybar by default, so the bar picks up the new
+/// colors. A bar that isn't running is fine, so failures are ignored.
+pub fn run_reload_command(config: &Config) {
+    if let Some((program, args)) = config.reload_command.split_first() {
+        Command::new(program)
+            .args(args)
             .stdout(Stdio::null())
             .stderr(Stdio::null())
-            .status()?;
+            .status()
+            .ok();
+    }
+}
+
+// The programs apply_wallpaper runs for this config, waybar is optional
+fn required_programs(config: &Config) -> Vec<&'static str> {
+    let mut programs = color_programs(config);
+    programs.push(backend_program(config));
+    programs
+}
+
+// The color generators enabled in this config
+fn color_programs(config: &Config) -> Vec<&'static str> {
+    let mut programs = Vec::new();
+    if config.pywal {
+        programs.push("wal");
     }
     if config.hellwal {
-        // Run hellwal
-        Command::new("hellawal")
-            .args(expand_args(&config.commands.wal))
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()?;
+        programs.push("hellwal");
     }
+    programs
+}
 
+// The program that sets the background for the configured session
+fn backend_program(config: &Config) -> &'static str {
     match config.session {
-        crate::config::Session::Wayland => {
-            if config.mpvpaper {
-                Command::new("mpvpaper")
-                    .args(expand_args(&config.commands.mpvpaper))
-                    .stdout(Stdio::null())
-                    .stderr(Stdio::null())
-                    .status()?;
-            } else {
-                Command::new("swww")
-                    .args(expand_args(&config.commands.swww))
-                    .stdout(Stdio::null())
-                    .stderr(Stdio::null())
-                    .status()?;
+        Session::Wayland if config.mpvpaper => "mpvpaper",
+        Session::Wayland => "swww",
+        Session::X11 => "feh",
+    }
+}
+
+// Errors on the first of `programs` that isn't on PATH
+fn require(programs: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    match programs
+        .iter()
+        .find(|program| find_in_path(program).is_none())
+    {
+        Some(program) => Err(format!("{} is not installed or not on PATH", program).into()),
+        None => Ok(()),
+    }
+}
+
+/// Full path of `program` in the first `PATH` directory that has it.
+pub fn find_in_path(program: &str) -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+// Target sizes for pre_scale and the monitor each is for, None standing for
+// `output`. "auto" asks the session for the monitor sizes: without an output
+// every monitor gets its own copy unless they all share one size. Empty
+// leaves the image as it is, also when a size can't be read.
+fn pre_scale_sizes(config: &Config, output: Option<&str>) -> Vec<(Option<String>, (u32, u32))> {
+    match config.pre_scale.as_str() {
+        "" => Vec::new(),
+        "auto" => {
+            let sized: Option<Vec<(String, (u32, u32))>> = monitors(config)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|(name, _)| output.is_none_or(|output| name == output))
+                .map(|(name, size)| Some((name, size?)))
+                .collect();
+            let sized = sized.unwrap_or_default();
+            match sized.first() {
+                Some(&(_, size)) if sized.iter().all(|(_, s)| *s == size) => {
+                    vec![(output.map(String::from), size)]
+                }
+                _ => sized
+                    .into_iter()
+                    .map(|(name, size)| (Some(name), size))
+                    .collect(),
             }
         }
-        crate::config::Session::X11 => {
-            Command::new("feh")
-                .args(expand_args(&config.commands.feh))
-                .stdout(Stdio::null())
-                .stderr(Stdio::null())
-                .status()?;
-        }
+        size => parse_resolution(size)
+            .ok()
+            .map(|size| (output.map(String::from), size))
+            .into_iter()
+            .collect(),
+    }
+}
+
+// Cuts and scales `path` to fill `size` in a cached PNG, made once per file
+// and size. Videos and GIFs are passed through as they are.
+fn pre_scale(path: &Path, size: (u32, u32)) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if !ImageFormat::from_path(path).is_ok_and(|f| f != ImageFormat::Gif) {
+        return Ok(path.to_path_buf());
+    }
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+    let target = scaled_path(path, modified, size);
+    if target.is_file() {
+        // Counts as a fresh copy for prune_scaled
+        let _ = fs::File::options()
+            .write(true)
+            .open(&target)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        return Ok(target);
+    }
+
+    let image = load_upright(path)?.resize_to_fill(size.0, size.1, FilterType::Lanczos3);
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Written aside and moved in so the backend never reads half a file
+    encode(&image, &target, ImageFormat::Png)?.persist(&target)?;
+    prune_scaled();
+    Ok(target)
+}
+
+// Names of the monitors currently connected, None when they can't be queried
+pub fn connected_outputs(config: &Config) -> Option<Vec<String>> {
+    Some(
+        monitors(config)
+            .ok()?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect(),
+    )
+}
+
+// A monitor's name and its resolution when it could be read
+type Monitor = (String, Option<(u32, u32)>);
+
+// Connected monitors as swww or xrandr lists them
+fn monitors(config: &Config) -> io::Result<Vec<Monitor>> {
+    let (program, args): (&str, &[&str]) = match config.session {
+        Session::Wayland => ("swww", &["query"]),
+        Session::X11 => ("xrandr", &["--listmonitors"]),
+    };
+    let output = Command::new(program)
+        .args(args)
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{} failed with {}",
+            program, output.status
+        )));
     }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let monitors = match config.session {
+        // "eDP-1: 1920x1080, scale: 1, ...", newer swww prefixes ": "
+        Session::Wayland => stdout
+            .lines()
+            .filter_map(|line| {
+                let (name, rest) = line
+                    .trim_start_matches(": ")
+                    .split_once(':')
+                    .unwrap_or((line, ""));
+                let size = rest
+                    .split(',')
+                    .next()
+                    .and_then(|size| parse_resolution(size).ok());
+                let name = name.trim();
+                (!name.is_empty()).then(|| (name.to_string(), size))
+            })
+            .collect(),
+        // " 0: +*eDP-1 1920/344x1080/193+0+0  eDP-1", after a "Monitors: N" header
+        Session::X11 => stdout
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let size = fields.get(2).and_then(|geometry| {
+                    let (width, height) = geometry.split_once('x')?;
+                    let width = width.split('/').next()?.parse().ok()?;
+                    let height = height.split(['/', '+']).next()?.parse().ok()?;
+                    Some((width, height))
+                });
+                Some((fields.last()?.to_string(), size))
+            })
+            .collect(),
+    };
+    Ok(monitors)
+}
+
+// Opens the wallpaper's folder without waiting for the file manager to exit
+pub fn reveal_in_file_manager(path: &Path, config: &Config) -> io::Result<()> {
+    let path_str = path.to_string_lossy();
+    let dir_str = path.parent().unwrap_or(path).to_string_lossy();
+    let args: Vec<String> = config
+        .file_manager
+        .iter()
+        .map(|arg| arg.replace("{path}", &path_str).replace("{dir}", &dir_str))
+        .collect();
 
-    // Reload waybar
-    Command::new("pkill")
-        .args(["-USR2", "waybar"])
-        .status()
-        .ok();
+    let Some((program, args)) = args.split_first() else {
+        return Ok(());
+    };
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(e.kind(), format!("{} not found", program)),
+            _ => e,
+        })?;
+    thread::spawn(move || child.wait());
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(session: Session, mpvpaper: bool) -> Config {
+        Config {
+            session,
+            mpvpaper,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn only_backends_that_take_an_output_accept_one() {
+        assert!(targets_outputs(&config(Session::Wayland, false)));
+        assert!(targets_outputs(&config(Session::Wayland, true)));
+        assert!(!targets_outputs(&config(Session::X11, false)));
+        assert!(require_outputs(Some("HDMI-A-1"), &config(Session::X11, false)).is_err());
+        assert!(require_outputs(None, &config(Session::X11, false)).is_ok());
+
+        let mut feh = config(Session::X11, false);
+        feh.commands.feh.push("--output={output}".into());
+        assert!(targets_outputs(&feh));
+    }
+
+    #[test]
+    fn mpvpaper_gets_the_output_or_every_monitor() {
+        let config = config(Session::Wayland, true);
+        let args = |output| expand_args(&config.commands.mpvpaper, "/w.mp4", output, &config);
+        assert_eq!(args(Some("DP-2")).iter().rev().nth(1).unwrap(), "DP-2");
+        assert_eq!(args(None).iter().rev().nth(1).unwrap(), "*");
+    }
+
+    #[test]
+    fn hellwal_gets_its_own_arguments() {
+        let config = config(Session::Wayland, false);
+        assert_eq!(
+            expand_args(&config.commands.hellwal, "/w.png", None, &config),
+            ["-i", "/w.png"]
+        );
+    }
+
+    #[test]
+    fn feh_gets_every_scaled_image_in_screen_order() {
+        let config = config(Session::X11, false);
+        assert_eq!(
+            feh_args(&["/a.png", "/b.png"], None, &config),
+            ["--bg-scale", "/a.png", "/b.png"]
+        );
+    }
+
+    #[test]
+    fn a_fixed_pre_scale_size_is_for_the_given_output() {
+        let mut config = config(Session::Wayland, false);
+        assert!(pre_scale_sizes(&config, None).is_empty());
+        config.pre_scale = "2560x1440".into();
+        assert_eq!(
+            pre_scale_sizes(&config, Some("DP-1")),
+            [(Some("DP-1".to_string()), (2560, 1440))]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_failing_exit_code_is_an_error() {
+        assert!(run(&mut Command::new("true")).is_ok());
+        let error = run(&mut Command::new("false")).unwrap_err();
+        assert!(error.to_string().starts_with("false failed"));
+    }
+}
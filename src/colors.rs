@@ -0,0 +1,77 @@
+use image::DynamicImage;
+
+// Pixels considered per image, the input is downscaled to about this many
+const SAMPLE_SIZE: u32 = 64;
+const ITERATIONS: usize = 10;
+
+// Dominant colors of `image` by k-means over a downscaled copy, most common
+// first. Centroids start at luminance quantiles so results are deterministic.
+pub fn dominant_colors(image: &DynamicImage, k: usize) -> Vec<[u8; 3]> {
+    let pixels: Vec<[f32; 3]> = image
+        .thumbnail(SAMPLE_SIZE, SAMPLE_SIZE)
+        .to_rgb8()
+        .pixels()
+        .map(|p| [p[0] as f32, p[1] as f32, p[2] as f32])
+        .collect();
+    if pixels.is_empty() || k == 0 {
+        return Vec::new();
+    }
+
+    let mut by_luma = pixels.clone();
+    by_luma.sort_by(|a, b| luma(a).total_cmp(&luma(b)));
+    let mut centroids: Vec<[f32; 3]> = (0..k.min(pixels.len()))
+        .map(|i| by_luma[(2 * i + 1) * by_luma.len() / (2 * k)])
+        .collect();
+
+    let mut assignment = vec![0; pixels.len()];
+    for _ in 0..ITERATIONS {
+        for (pixel, slot) in pixels.iter().zip(assignment.iter_mut()) {
+            *slot = nearest(&centroids, pixel);
+        }
+
+        let mut sums = vec![[0.0f32; 3]; centroids.len()];
+        let mut counts = vec![0usize; centroids.len()];
+        for (pixel, &c) in pixels.iter().zip(&assignment) {
+            for (sum, value) in sums[c].iter_mut().zip(pixel) {
+                *sum += value;
+            }
+            counts[c] += 1;
+        }
+        // Empty clusters keep their previous centroid
+        for (c, centroid) in centroids.iter_mut().enumerate() {
+            if counts[c] > 0 {
+                *centroid = sums[c].map(|sum| sum / counts[c] as f32);
+            }
+        }
+    }
+
+    let mut counts = vec![0usize; centroids.len()];
+    for &c in &assignment {
+        counts[c] += 1;
+    }
+    let mut clusters: Vec<(usize, [f32; 3])> = counts
+        .into_iter()
+        .zip(centroids)
+        .filter(|(count, _)| *count > 0)
+        .collect();
+    clusters.sort_by_key(|(count, _)| std::cmp::Reverse(*count));
+
+    clusters
+        .into_iter()
+        .map(|(_, c)| c.map(|channel| channel.round() as u8))
+        .collect()
+}
+
+fn luma(p: &[f32; 3]) -> f32 {
+    0.299 * p[0] + 0.587 * p[1] + 0.114 * p[2]
+}
+
+fn nearest(centroids: &[[f32; 3]], pixel: &[f32; 3]) -> usize {
+    let distance = |c: &[f32; 3]| -> f32 { (0..3).map(|i| (c[i] - pixel[i]).powi(2)).sum() };
+    centroids
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
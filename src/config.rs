@@ -1,7 +1,11 @@
-use std::{env, fs, path::PathBuf};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
 use toml::Value;
 
 use crate::tui::Tab;
+use crate::wallpapers::build_exclude_sets;
 
 #[derive(Clone)]
 pub struct CustomKeybindings {
@@ -10,23 +14,111 @@ pub struct CustomKeybindings {
     pub multi_select: char,
     pub rename: char,
     pub quit: char,
+    pub switcher: char,
+    pub toggle_colors: char,
+    pub preview_resize: char,
+    pub zoom_in: char,
+    pub zoom_out: char,
+    pub grid: char,
+    pub remove: char,
+    pub clear_list: char,
+    pub delete: char,
+    pub reveal: char,
+    pub sort: char,
+    pub swatches: char,
+    pub copy: char,
+    pub undo: char,
+    pub apply: char,
+    pub light: char,
+    pub recolor: char,
+    pub edit: char,
+    pub favorites_only: char,
+    pub group: char,
+    pub blacklist: char,
+    pub collection: char,
+    // Queue tab
+    pub queue: char,
+    pub move_up: char,
+    pub move_down: char,
+    // Multi-select mode only, select_all and clear_selection also work with Ctrl
+    pub select_all: char,
+    pub clear_selection: char,
+    pub invert_selection: char,
+    pub toggle_selection: char,
+    pub range_select: char,
 }
 
+/// Settings from `config.toml` and `keybindings.toml`, see the README for
+/// every key.
 #[derive(Clone)]
 pub struct Config {
-    pub wallpaper_dir: PathBuf,
+    // Scanned in order, `wallpaper_dir` accepts one path or an array
+    pub wallpaper_dirs: Vec<PathBuf>,
     pub session: Session,
     pub vim_motion: bool,
+    pub wrap_navigation: bool,
     pub mouse_support: bool,
+    // Longest gap between the clicks of a double-click
+    pub double_click_ms: u64,
     pub image_cache_size: Option<usize>,
+    pub image_cache_mb: Option<usize>,
+    pub preview_max_size: u32,
     pub keybindings: CustomKeybindings,
     pub tabs: Vec<TabConfig>,
     pub list_position: String,
+    pub preview_resize: String,
+    pub favorites_sort: String,
+    pub search_mode: String,
+    pub show_relative_paths: bool,
+    pub show_statusbar: bool,
+    pub show_hints: bool,
+    pub preview_border: bool,
+    // Section headers for the subdirectories in the Wallpapers tab
+    pub group_by_dir: bool,
+    // Dominant colors beside the preview from the start
+    pub show_palette: bool,
+    // Pick a monitor before applying when more than one is connected
+    pub ask_monitor: bool,
+    // Reapply the last wallpaper from the history before the TUI opens
+    pub auto_apply_on_launch: bool,
+    // Off when NO_COLOR is set, see https://no-color.org
+    pub color_enabled: bool,
+    // auto, sixel, kitty, iterm2, halfblocks or off
+    pub image_protocol: String,
+    pub validate: bool,
+    // Off hides the History tab and stops recording applied wallpapers
+    pub enable_history: bool,
     pub transition_type: String,
+    pub transition_fps: u32,
+    // Seconds
+    pub transition_duration: f64,
     pub pywal: bool,
+    pub pywal_backend: String,
+    pub pywal_light: bool,
     pub hellwal: bool,
     pub mpvpaper: bool,
+    pub extensions: Vec<String>,
+    pub exclude: Vec<String>,
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+    pub show_hidden: bool,
+    pub min_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub aspect_ratio: Option<f64>,
+    pub aspect_tolerance: f64,
     pub commands: CommandConfig,
+    // Program and arguments, `{path}` and `{dir}` are replaced
+    pub file_manager: Vec<String>,
+    // Run after applying, empty to skip
+    pub reload_command: Vec<String>,
+    // Where --print writes current.<ext>, the XDG cache dir when unset
+    pub cache_dir: Option<PathBuf>,
+    // "auto" or "WIDTHxHEIGHT" to scale images before applying, empty for off
+    pub pre_scale: String,
+    // Tab opened at startup when enabled, the first enabled one otherwise
+    pub default_tab: Option<Tab>,
+    // Monitor given with --output, not read from config.toml
+    pub output: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -38,6 +130,7 @@ pub enum Session {
 #[derive(Clone)]
 pub struct CommandConfig {
     pub wal: Vec<String>,
+    pub hellwal: Vec<String>,
     pub swww: Vec<String>,
     pub feh: Vec<String>,
     pub mpvpaper: Vec<String>,
@@ -64,12 +157,16 @@ impl TabConfig {
                 tab: Tab::Favorites,
                 enabled: true,
             },
+            Self {
+                tab: Tab::Queue,
+                enabled: true,
+            },
         ]
     }
 }
 
-impl Config {
-    pub fn load() -> Self {
+impl Default for Config {
+    fn default() -> Self {
         // Detect session type
         let session = if env::var("WAYLAND_DISPLAY").is_ok() {
             Session::Wayland
@@ -77,78 +174,385 @@ impl Config {
             Session::X11
         };
 
-        // Resolve config paths
-        let xdg_config = env::var("XDG_CONFIG_HOME")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| dirs::home_dir().unwrap().join(".config"));
-
-        let config_file = xdg_config.join("wallrs/config.toml");
-        let keybindings_file = xdg_config.join("wallrs/keybindings.toml");
-
-        // Default values
-        let default_dir = dirs::home_dir().unwrap().join("Pictures/Wallpapers");
-        let mut wallpaper_dir = default_dir;
-        let mut vim_motion = false;
-        let mut mouse_support = false;
-        let mut keybindings = CustomKeybindings::default();
-        let mut tabs = TabConfig::default_tabs();
-        let mut list_position = String::from("left");
-        let mut transition_type = String::from("fade");
-        let mut image_cache_size = Some(50);
-
-        let mut pywal = false;
-        let mut hellwal = false;
-        let mut mpvpaper = false;
-        // Default command arguments
-        let default_commands = CommandConfig {
-            wal: vec![
-                "-i".into(),
-                "{path}".into(),
-                "-n".into(),
-                "--backend".into(),
-                "wal".into(),
-            ],
-            swww: vec![
-                "img".into(),
-                "{path}".into(),
-                "--transition-fps".into(),
-                "60".into(),
-                "--transition-type".into(),
-                "{transition}".into(),
-            ],
-            feh: vec!["--bg-scale".into(), "{path}".into()],
-            mpvpaper: vec![
-                "-vs".into(),
-                "-o".into(),
-                "no-audio loop".into(),
-                "--fork".into(),
-                "eDP-1".into(),
-                "{path}".into(),
+        Self {
+            wallpaper_dirs: vec![
+                dirs::home_dir()
+                    .unwrap_or_default()
+                    .join("Pictures/Wallpapers"),
             ],
-        };
-        let mut commands = default_commands.clone();
+            session,
+            vim_motion: false,
+            wrap_navigation: true,
+            mouse_support: false,
+            double_click_ms: 400,
+            image_cache_size: Some(50),
+            image_cache_mb: None,
+            preview_max_size: 1200,
+            keybindings: CustomKeybindings::default(),
+            tabs: TabConfig::default_tabs(),
+            list_position: String::from("left"),
+            preview_resize: String::from("fit"),
+            favorites_sort: String::from("recent"),
+            search_mode: String::from("fuzzy"),
+            show_relative_paths: false,
+            show_statusbar: true,
+            show_hints: true,
+            preview_border: true,
+            group_by_dir: false,
+            show_palette: false,
+            ask_monitor: false,
+            auto_apply_on_launch: false,
+            color_enabled: true,
+            image_protocol: String::from("auto"),
+            validate: false,
+            enable_history: true,
+            transition_type: String::from("fade"),
+            transition_fps: 60,
+            transition_duration: 3.0,
+            pywal: false,
+            pywal_backend: String::from("wal"),
+            pywal_light: false,
+            hellwal: false,
+            mpvpaper: false,
+            extensions: ["jpg", "jpeg", "png", "webp", "gif", "bmp", "tiff", "tif"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            exclude: Vec::new(),
+            max_depth: None,
+            follow_symlinks: false,
+            show_hidden: false,
+            min_width: None,
+            min_height: None,
+            aspect_ratio: None,
+            aspect_tolerance: 0.05,
+            // Default command arguments
+            commands: CommandConfig {
+                wal: vec![
+                    "-i".into(),
+                    "{path}".into(),
+                    "-n".into(),
+                    "--backend".into(),
+                    "{backend}".into(),
+                ],
+                hellwal: vec!["-i".into(), "{path}".into()],
+                swww: vec![
+                    "img".into(),
+                    "{path}".into(),
+                    "--transition-fps".into(),
+                    "{transition_fps}".into(),
+                    "--transition-duration".into(),
+                    "{transition_duration}".into(),
+                    "--transition-type".into(),
+                    "{transition}".into(),
+                ],
+                feh: vec!["--bg-scale".into(), "{path}".into()],
+                mpvpaper: vec![
+                    "-vs".into(),
+                    "-o".into(),
+                    "no-audio loop".into(),
+                    "--fork".into(),
+                    "{output}".into(),
+                    "{path}".into(),
+                ],
+            },
+            file_manager: vec!["xdg-open".into(), "{dir}".into()],
+            reload_command: vec!["pkill".into(), "-USR2".into(), "waybar".into()],
+            cache_dir: None,
+            pre_scale: String::new(),
+            default_tab: None,
+            output: None,
+        }
+    }
+}
+
+// Every top-level key `Config::load` reads
+const CONFIG_KEYS: &[&str] = &[
+    "wallpaper_dir",
+    "vim_motion",
+    "wrap_navigation",
+    "mouse_support",
+    "double_click_ms",
+    "list_position",
+    "validate",
+    "enable_history",
+    "show_relative_paths",
+    "show_statusbar",
+    "show_hints",
+    "preview_border",
+    "group_by_dir",
+    "show_palette",
+    "ask_monitor",
+    "auto_apply_on_launch",
+    "image_protocol",
+    "preview_resize",
+    "search_mode",
+    "favorites_sort",
+    "transition_type",
+    "transition_fps",
+    "transition_duration",
+    "pywal",
+    "pywal_backend",
+    "pywal_light",
+    "hellwal",
+    "video",
+    "extensions",
+    "file_manager",
+    "reload_command",
+    "cache_dir",
+    "pre_scale",
+    "default_tab",
+    "exclude",
+    "max_depth",
+    "follow_symlinks",
+    "show_hidden",
+    "min_width",
+    "min_height",
+    "aspect_ratio",
+    "aspect_tolerance",
+    "image_cache_size",
+    "image_cache_mb",
+    "preview_max_size",
+    "commands",
+    "tabs",
+];
+
+// Programs whose arguments can be set under [commands]
+const COMMAND_KEYS: &[&str] = &["wal", "hellwal", "swww", "feh", "mpvpaper"];
+
+const KEYBINDING_KEYS: &[&str] = &[
+    "search",
+    "favorite",
+    "multi_select",
+    "rename",
+    "quit",
+    "switcher",
+    "toggle_colors",
+    "preview_resize",
+    "zoom_in",
+    "zoom_out",
+    "grid",
+    "remove",
+    "clear_list",
+    "delete",
+    "reveal",
+    "sort",
+    "swatches",
+    "copy",
+    "undo",
+    "apply",
+    "light",
+    "recolor",
+    "edit",
+    "favorites_only",
+    "group",
+    "blacklist",
+    "collection",
+    "queue",
+    "move_up",
+    "move_down",
+    "select_all",
+    "clear_selection",
+    "invert_selection",
+    "toggle_selection",
+    "range_select",
+];
+
+// Unknown keys are otherwise ignored without a trace, point out likely typos
+fn warn_unknown_keys(value: &Value, known: &[&str], file: &str) {
+    let Some(table) = value.as_table() else {
+        return;
+    };
+
+    for key in table.keys().filter(|k| !known.contains(&k.as_str())) {
+        let suggestion = known
+            .iter()
+            .map(|k| (edit_distance(key, k), k))
+            .filter(|(distance, _)| *distance <= 3)
+            .min_by_key(|(distance, _)| *distance);
+        match suggestion {
+            Some((_, k)) => eprintln!(
+                "Warning: unknown key '{}' in {}, did you mean '{}'?",
+                key, file, k
+            ),
+            None => eprintln!("Warning: unknown key '{}' in {}", key, file),
+        }
+    }
+}
+
+// Levenshtein distance
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+
+    row[b.len()]
+}
+
+// $XDG_CONFIG_HOME/wallrs, falling back to ~/.config/wallrs
+pub fn config_dir() -> PathBuf {
+    env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| dirs::home_dir().unwrap_or_default().join(".config"))
+        .join("wallrs")
+}
+
+impl Config {
+    // Enabled tabs in the order they are listed, all of them when none are.
+    // History is left out while it isn't recorded.
+    pub fn active_tabs(&self) -> Vec<Tab> {
+        let mut tabs: Vec<Tab> = self
+            .tabs
+            .iter()
+            .filter(|t| t.enabled)
+            .map(|t| t.tab)
+            .collect();
+        if tabs.is_empty() {
+            tabs = vec![Tab::Wallpapers, Tab::History, Tab::Favorites, Tab::Queue];
+        }
+        tabs.retain(|&t| self.enable_history || t != Tab::History);
+        if tabs.is_empty() {
+            tabs.push(Tab::Wallpapers);
+        }
+        tabs
+    }
+
+    // default_tab when it's among the active tabs, the first of them otherwise
+    pub fn start_tab(&self) -> Tab {
+        let tabs = self.active_tabs();
+        self.default_tab
+            .filter(|tab| tabs.contains(tab))
+            .unwrap_or(tabs[0])
+    }
+
+    // For messages about the scanned roots
+    pub fn wallpaper_dirs_display(&self) -> String {
+        self.wallpaper_dirs
+            .iter()
+            .map(|d| d.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Reads both files from the config directory. Missing files, keys and
+    /// invalid values fall back to the defaults; a file that can't be read or
+    /// isn't valid TOML is an error.
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        // Resolve config paths
+        let config_file = config_dir().join("config.toml");
+        let keybindings_file = config_dir().join("keybindings.toml");
+
+        // Start from the defaults and override whatever the files set
+        let Config {
+            mut wallpaper_dirs,
+            session,
+            mut vim_motion,
+            mut wrap_navigation,
+            mut mouse_support,
+            mut double_click_ms,
+            mut image_cache_size,
+            mut image_cache_mb,
+            mut preview_max_size,
+            mut keybindings,
+            mut tabs,
+            mut list_position,
+            mut preview_resize,
+            mut favorites_sort,
+            mut search_mode,
+            mut show_relative_paths,
+            mut show_statusbar,
+            mut show_hints,
+            mut preview_border,
+            mut group_by_dir,
+            mut show_palette,
+            mut ask_monitor,
+            mut auto_apply_on_launch,
+            color_enabled: _,
+            mut image_protocol,
+            mut validate,
+            mut enable_history,
+            mut transition_type,
+            mut transition_fps,
+            mut transition_duration,
+            mut pywal,
+            mut pywal_backend,
+            mut pywal_light,
+            mut hellwal,
+            mut mpvpaper,
+            mut extensions,
+            mut exclude,
+            mut max_depth,
+            mut follow_symlinks,
+            mut show_hidden,
+            mut min_width,
+            mut min_height,
+            mut aspect_ratio,
+            mut aspect_tolerance,
+            mut commands,
+            mut file_manager,
+            mut reload_command,
+            mut cache_dir,
+            mut pre_scale,
+            mut default_tab,
+            output,
+        } = Config::default();
+        let default_commands = commands.clone();
 
         // Load main config.toml if it exists
         let value: Option<Value> = if config_file.exists() {
-            let contents = fs::read_to_string(&config_file).expect("Failed to read config.toml");
-            Some(toml::from_str(&contents).expect("Invalid TOML in config.toml"))
+            Some(read_toml(&config_file)?)
         } else {
             None
         };
 
         if let Some(value) = &value {
+            warn_unknown_keys(value, CONFIG_KEYS, "config.toml");
+
             // General settings
-            if let Some(path_str) = value.get("wallpaper_dir").and_then(|v| v.as_str()) {
-                wallpaper_dir = PathBuf::from(path_str);
+            let home = dirs::home_dir().ok_or("Can't find the home directory")?;
+            match value.get("wallpaper_dir") {
+                Some(Value::String(path_str)) => {
+                    wallpaper_dirs = vec![expand_path(path_str, &home)];
+                }
+                Some(Value::Array(arr)) => {
+                    let parsed: Vec<PathBuf> = arr
+                        .iter()
+                        .filter_map(|v| v.as_str())
+                        .map(|s| expand_path(s, &home))
+                        .collect();
+                    if !parsed.is_empty() {
+                        wallpaper_dirs = parsed;
+                    }
+                }
+                _ => {}
             }
 
             if let Some(v) = value.get("vim_motion").and_then(|v| v.as_bool()) {
                 vim_motion = v;
             }
+            if let Some(v) = value.get("wrap_navigation").and_then(|v| v.as_bool()) {
+                wrap_navigation = v;
+            }
 
             if let Some(v) = value.get("mouse_support").and_then(|v| v.as_bool()) {
                 mouse_support = v;
             }
+            if let Some(v) = value.get("double_click_ms").and_then(|v| v.as_integer()) {
+                double_click_ms = v.clamp(0, 5000) as u64;
+            }
 
             if let Some(v) = value.get("list_position").and_then(|v| v.as_str()) {
                 let lower = v.to_lowercase();
@@ -157,6 +561,67 @@ impl Config {
                 }
             }
 
+            if let Some(v) = value.get("validate").and_then(|v| v.as_bool()) {
+                validate = v;
+            }
+            if let Some(v) = value.get("enable_history").and_then(|v| v.as_bool()) {
+                enable_history = v;
+            }
+
+            if let Some(v) = value.get("show_relative_paths").and_then(|v| v.as_bool()) {
+                show_relative_paths = v;
+            }
+
+            if let Some(v) = value.get("show_statusbar").and_then(|v| v.as_bool()) {
+                show_statusbar = v;
+            }
+            if let Some(v) = value.get("show_hints").and_then(|v| v.as_bool()) {
+                show_hints = v;
+            }
+            if let Some(v) = value.get("preview_border").and_then(|v| v.as_bool()) {
+                preview_border = v;
+            }
+            if let Some(v) = value.get("group_by_dir").and_then(|v| v.as_bool()) {
+                group_by_dir = v;
+            }
+            if let Some(v) = value.get("show_palette").and_then(|v| v.as_bool()) {
+                show_palette = v;
+            }
+            if let Some(v) = value.get("ask_monitor").and_then(|v| v.as_bool()) {
+                ask_monitor = v;
+            }
+            if let Some(v) = value.get("auto_apply_on_launch").and_then(|v| v.as_bool()) {
+                auto_apply_on_launch = v;
+            }
+            if let Some(v) = value.get("image_protocol").and_then(|v| v.as_str()) {
+                let valid = ["auto", "sixel", "kitty", "iterm2", "halfblocks", "off"];
+                let lower = v.to_lowercase();
+                if valid.contains(&lower.as_str()) {
+                    image_protocol = lower;
+                }
+            }
+
+            if let Some(v) = value.get("preview_resize").and_then(|v| v.as_str()) {
+                let lower = v.to_lowercase();
+                if ["fit", "crop", "width", "height"].contains(&lower.as_str()) {
+                    preview_resize = lower;
+                }
+            }
+
+            if let Some(v) = value.get("search_mode").and_then(|v| v.as_str()) {
+                let lower = v.to_lowercase();
+                if ["fuzzy", "substring"].contains(&lower.as_str()) {
+                    search_mode = lower;
+                }
+            }
+
+            if let Some(v) = value.get("favorites_sort").and_then(|v| v.as_str()) {
+                let lower = v.to_lowercase();
+                if ["recent", "name"].contains(&lower.as_str()) {
+                    favorites_sort = lower;
+                }
+            }
+
             if let Some(v) = value.get("transition_type").and_then(|v| v.as_str()) {
                 let valid = ["fade", "wipe", "grow", "outer", "any", "none", "random"];
                 let lower = v.to_lowercase();
@@ -165,9 +630,31 @@ impl Config {
                 }
             }
 
+            if let Some(v) = value.get("transition_fps").and_then(|v| v.as_integer()) {
+                transition_fps = v.clamp(1, 240) as u32;
+            }
+
+            if let Some(v) = value
+                .get("transition_duration")
+                .and_then(|v| v.as_float().or_else(|| v.as_integer().map(|i| i as f64)))
+                && v >= 0.0
+            {
+                transition_duration = v;
+            }
+
             if let Some(v) = value.get("pywal").and_then(|v| v.as_bool()) {
                 pywal = v;
             }
+            if let Some(v) = value.get("pywal_backend").and_then(|v| v.as_str()) {
+                let valid = ["wal", "colorthief", "colorz", "haishoku", "schemer2"];
+                let lower = v.to_lowercase();
+                if valid.contains(&lower.as_str()) {
+                    pywal_backend = lower;
+                }
+            }
+            if let Some(v) = value.get("pywal_light").and_then(|v| v.as_bool()) {
+                pywal_light = v;
+            }
             if let Some(v) = value.get("hellwal").and_then(|v| v.as_bool()) {
                 hellwal = v;
             }
@@ -175,12 +662,116 @@ impl Config {
                 mpvpaper = v;
             }
 
-            if let Some(v) = value.get("image_cache_size").and_then(|v| v.as_integer()) {
+            if let Some(arr) = value.get("extensions").and_then(|v| v.as_array()) {
+                let parsed: Vec<String> = arr
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .map(|s| s.trim_start_matches('.').to_lowercase())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                if !parsed.is_empty() {
+                    extensions = parsed;
+                }
+            }
+
+            if let Some(arr) = value.get("file_manager").and_then(|v| v.as_array()) {
+                let parsed: Vec<String> = arr
+                    .iter()
+                    .filter_map(|v| v.as_str().map(String::from))
+                    .collect();
+                if !parsed.is_empty() {
+                    file_manager = parsed;
+                }
+            }
+
+            if let Some(arr) = value.get("reload_command").and_then(|v| v.as_array()) {
+                reload_command = arr
+                    .iter()
+                    .filter_map(|v| v.as_str().map(String::from))
+                    .collect();
+            }
+
+            if let Some(dir) = value.get("cache_dir").and_then(|v| v.as_str()) {
+                cache_dir = Some(expand_path(dir, &home));
+            }
+
+            if let Some(v) = value.get("pre_scale").and_then(|v| v.as_str()) {
+                let lower = v.trim().to_lowercase();
+                if lower.is_empty() || lower == "auto" || parse_resolution(&lower).is_ok() {
+                    pre_scale = lower;
+                }
+            }
+
+            if let Some(v) = value.get("default_tab").and_then(|v| v.as_str()) {
+                default_tab = Tab::from_name(v);
+            }
+
+            if let Some(arr) = value.get("exclude").and_then(|v| v.as_array()) {
+                exclude = arr
+                    .iter()
+                    .filter_map(|v| v.as_str().map(String::from))
+                    .collect();
+                // A bad pattern is reported here rather than failing the scan
+                build_exclude_sets(&exclude)?;
+            }
+
+            if let Some(v) = value.get("max_depth").and_then(|v| v.as_integer())
+                && v > 0
+            {
+                max_depth = Some(v as usize);
+            }
+
+            if let Some(v) = value.get("follow_symlinks").and_then(|v| v.as_bool()) {
+                follow_symlinks = v;
+            }
+
+            if let Some(v) = value.get("show_hidden").and_then(|v| v.as_bool()) {
+                show_hidden = v;
+            }
+
+            if let Some(v) = value.get("min_width").and_then(|v| v.as_integer())
+                && v > 0
+            {
+                min_width = Some(v as u32);
+            }
+
+            if let Some(v) = value.get("min_height").and_then(|v| v.as_integer())
+                && v > 0
+            {
+                min_height = Some(v as u32);
+            }
+
+            if let Some(v) = value.get("aspect_ratio").and_then(|v| v.as_str()) {
+                aspect_ratio = parse_aspect_ratio(v);
+            }
+
+            if let Some(v) = value.get("aspect_tolerance").and_then(|v| v.as_float())
+                && v >= 0.0
+            {
+                aspect_tolerance = v;
+            }
+
+            if let Some(v) = value.get("image_cache_size").and_then(|v| v.as_integer())
+                && v > 0
+            {
                 image_cache_size = Some(v as usize);
             }
 
+            if let Some(v) = value.get("image_cache_mb").and_then(|v| v.as_integer())
+                && v > 0
+            {
+                image_cache_mb = Some(v as usize);
+            }
+
+            if let Some(v) = value.get("preview_max_size").and_then(|v| v.as_integer())
+                && v > 0
+            {
+                preview_max_size = v as u32;
+            }
+
             // --- Load commands safely (merge with defaults) ---
             if let Some(cmds) = value.get("commands").and_then(|v| v.as_table()) {
+                warn_unknown_keys(&value["commands"], COMMAND_KEYS, "[commands]");
                 let merge = |default: &Vec<String>, custom: Option<&Vec<Value>>| -> Vec<String> {
                     match custom {
                         Some(arr) if !arr.is_empty() => {
@@ -215,6 +806,10 @@ impl Config {
                     &default_commands.wal,
                     cmds.get("wal").and_then(|v| v.as_array()),
                 );
+                commands.hellwal = merge(
+                    &default_commands.hellwal,
+                    cmds.get("hellwal").and_then(|v| v.as_array()),
+                );
                 commands.swww = merge(
                     &default_commands.swww,
                     cmds.get("swww").and_then(|v| v.as_array()),
@@ -254,6 +849,13 @@ impl Config {
                         _ => {}
                     }
                 }
+                // A tab listed twice keeps its first position
+                let mut seen = Vec::new();
+                parsed.retain(|t| {
+                    let first = !seen.contains(&t.tab);
+                    seen.push(t.tab);
+                    first
+                });
                 if !parsed.is_empty() {
                     tabs = parsed;
                 }
@@ -262,9 +864,8 @@ impl Config {
 
         // Load keybindings.toml if present
         if keybindings_file.exists() {
-            let contents =
-                fs::read_to_string(&keybindings_file).expect("Failed to read keybindings.toml");
-            let value: Value = toml::from_str(&contents).expect("Invalid TOML in keybindings.toml");
+            let value = read_toml(&keybindings_file)?;
+            warn_unknown_keys(&value, KEYBINDING_KEYS, "keybindings.toml");
 
             if let Some(c) = value
                 .get("search")
@@ -301,24 +902,371 @@ impl Config {
             {
                 keybindings.quit = c;
             }
+            if let Some(c) = value
+                .get("switcher")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.switcher = c;
+            }
+            if let Some(c) = value
+                .get("toggle_colors")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.toggle_colors = c;
+            }
+            if let Some(c) = value
+                .get("preview_resize")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.preview_resize = c;
+            }
+            if let Some(c) = value
+                .get("zoom_in")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.zoom_in = c;
+            }
+            if let Some(c) = value
+                .get("zoom_out")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.zoom_out = c;
+            }
+            if let Some(c) = value
+                .get("grid")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.grid = c;
+            }
+            if let Some(c) = value
+                .get("remove")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.remove = c;
+            }
+            if let Some(c) = value
+                .get("clear_list")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.clear_list = c;
+            }
+            if let Some(c) = value
+                .get("delete")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.delete = c;
+            }
+            if let Some(c) = value
+                .get("reveal")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.reveal = c;
+            }
+            if let Some(c) = value
+                .get("sort")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.sort = c;
+            }
+            if let Some(c) = value
+                .get("swatches")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.swatches = c;
+            }
+            if let Some(c) = value
+                .get("copy")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.copy = c;
+            }
+            if let Some(c) = value
+                .get("undo")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.undo = c;
+            }
+            if let Some(c) = value
+                .get("apply")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.apply = c;
+            }
+            if let Some(c) = value
+                .get("light")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.light = c;
+            }
+            if let Some(c) = value
+                .get("recolor")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.recolor = c;
+            }
+            if let Some(c) = value
+                .get("edit")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.edit = c;
+            }
+            if let Some(c) = value
+                .get("collection")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.collection = c;
+            }
+            if let Some(c) = value
+                .get("favorites_only")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.favorites_only = c;
+            }
+            if let Some(c) = value
+                .get("group")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.group = c;
+            }
+            if let Some(c) = value
+                .get("blacklist")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.blacklist = c;
+            }
+            if let Some(c) = value
+                .get("queue")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.queue = c;
+            }
+            if let Some(c) = value
+                .get("move_up")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.move_up = c;
+            }
+            if let Some(c) = value
+                .get("move_down")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.move_down = c;
+            }
+            if let Some(c) = value
+                .get("select_all")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.select_all = c;
+            }
+            if let Some(c) = value
+                .get("clear_selection")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.clear_selection = c;
+            }
+            if let Some(c) = value
+                .get("invert_selection")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.invert_selection = c;
+            }
+            if let Some(c) = value
+                .get("toggle_selection")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.toggle_selection = c;
+            }
+            if let Some(c) = value
+                .get("range_select")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.chars().next())
+            {
+                keybindings.range_select = c;
+            }
         }
 
-        Self {
-            wallpaper_dir,
+        Ok(Self {
+            wallpaper_dirs,
             session,
             vim_motion,
+            wrap_navigation,
             mouse_support,
+            double_click_ms,
             image_cache_size,
+            image_cache_mb,
+            preview_max_size,
             keybindings,
             tabs,
             list_position,
+            preview_resize,
+            favorites_sort,
+            search_mode,
+            show_relative_paths,
+            show_statusbar,
+            show_hints,
+            preview_border,
+            group_by_dir,
+            show_palette,
+            ask_monitor,
+            auto_apply_on_launch,
+            // Any non-empty value counts, whatever it says
+            color_enabled: env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()),
+            image_protocol,
+            validate,
+            enable_history,
             transition_type,
+            transition_fps,
+            transition_duration,
             pywal,
+            pywal_backend,
+            pywal_light,
             hellwal,
             commands,
+            file_manager,
+            reload_command,
+            cache_dir,
+            pre_scale,
+            default_tab,
             mpvpaper,
+            extensions,
+            exclude,
+            max_depth,
+            follow_symlinks,
+            show_hidden,
+            min_width,
+            min_height,
+            aspect_ratio,
+            aspect_tolerance,
+            output,
+        })
+    }
+}
+
+fn read_toml(path: &Path) -> Result<Value, Box<dyn std::error::Error>> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+    Ok(toml::from_str(&contents)
+        .map_err(|e| format!("Invalid TOML in {}: {}", path.display(), e))?)
+}
+
+// "1920x1080" -> (1920, 1080)
+pub fn parse_resolution(s: &str) -> Result<(u32, u32), String> {
+    let (w, h) = s
+        .to_lowercase()
+        .split_once('x')
+        .map(|(w, h)| (w.trim().parse::<u32>(), h.trim().parse::<u32>()))
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got '{}'", s))?;
+    match (w, h) {
+        (Ok(w), Ok(h)) => Ok((w, h)),
+        _ => Err(format!("expected WIDTHxHEIGHT, got '{}'", s)),
+    }
+}
+
+// "16:9" -> 1.777...
+fn parse_aspect_ratio(s: &str) -> Option<f64> {
+    let (w, h) = s.split_once(':')?;
+    let w: f64 = w.trim().parse().ok()?;
+    let h: f64 = h.trim().parse().ok()?;
+    (w > 0.0 && h > 0.0).then(|| w / h)
+}
+
+// Expand a leading `~` and `$VAR`/`${VAR}` references, resolving relative
+// paths against `base`
+pub fn expand_path(raw: &str, base: &Path) -> PathBuf {
+    let expanded = expand_env(raw);
+    let home = dirs::home_dir().unwrap_or_default();
+
+    let path = if expanded == "~" {
+        home
+    } else if let Some(rest) = expanded.strip_prefix("~/") {
+        home.join(rest)
+    } else {
+        PathBuf::from(expanded)
+    };
+
+    if path.is_relative() {
+        base.join(path)
+    } else {
+        path
+    }
+}
+
+// Unset variables are left untouched
+fn expand_env(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+
+        let braced = chars.peek() == Some(&'{');
+        if braced {
+            chars.next();
+        }
+
+        let mut name = String::new();
+        while let Some(&n) = chars.peek() {
+            if n.is_ascii_alphanumeric() || n == '_' {
+                name.push(n);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+
+        let closed = braced && chars.peek() == Some(&'}');
+        if closed {
+            chars.next();
+        }
+
+        match env::var(&name) {
+            Ok(value) if !name.is_empty() && (!braced || closed) => out.push_str(&value),
+            _ => {
+                out.push('$');
+                if braced {
+                    out.push('{');
+                }
+                out.push_str(&name);
+                if closed {
+                    out.push('}');
+                }
+            }
         }
     }
+
+    out
 }
 
 impl Default for CustomKeybindings {
@@ -329,6 +1277,493 @@ impl Default for CustomKeybindings {
             multi_select: 'v',
             rename: 'r',
             quit: 'q',
+            switcher: ':',
+            toggle_colors: 'p',
+            preview_resize: 'z',
+            zoom_in: '+',
+            zoom_out: '-',
+            grid: 'g',
+            remove: 'x',
+            clear_list: 'X',
+            delete: 'D',
+            reveal: 'R',
+            sort: 's',
+            swatches: 'C',
+            copy: 'c',
+            undo: 'u',
+            apply: ' ',
+            light: 'L',
+            recolor: 'w',
+            edit: 'e',
+            favorites_only: 'F',
+            group: 'G',
+            blacklist: 'H',
+            collection: 'o',
+            queue: 'Q',
+            move_up: 'K',
+            move_down: 'J',
+            select_all: 'a',
+            clear_selection: 'd',
+            invert_selection: 'i',
+            toggle_selection: ' ',
+            range_select: 'V',
+        }
+    }
+}
+
+// ------------------------
+// Default config files
+// ------------------------
+
+// Writes commented config.toml and keybindings.toml holding the defaults,
+// refusing to replace existing files unless `force` is set
+pub fn init_config(force: bool) -> io::Result<Vec<PathBuf>> {
+    let dir = config_dir();
+    let files = [
+        (dir.join("config.toml"), default_config_toml()),
+        (dir.join("keybindings.toml"), default_keybindings_toml()),
+    ];
+
+    if !force && let Some((path, _)) = files.iter().find(|(path, _)| path.exists()) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!(
+                "{} already exists, use --force to overwrite",
+                path.display()
+            ),
+        ));
+    }
+
+    fs::create_dir_all(&dir)?;
+    for (path, contents) in &files {
+        fs::write(path, contents)?;
+    }
+
+    Ok(files.into_iter().map(|(path, _)| path).collect())
+}
+
+fn toml_value(value: impl Into<Value>) -> String {
+    value.into().to_string()
+}
+
+fn default_config_toml() -> String {
+    let d = Config::default();
+    let home = dirs::home_dir().unwrap_or_default();
+    let wallpaper_dir = match d.wallpaper_dirs[0].strip_prefix(&home) {
+        Ok(rel) => format!("~/{}", rel.display()),
+        Err(_) => d.wallpaper_dirs[0].display().to_string(),
+    };
+    let mut out =
+        String::from("# wallrs configuration, every key is optional and set to its default\n\n");
+    let mut key = |comment: &str, line: String| {
+        out.push_str(&format!("# {}\n{}\n\n", comment, line));
+    };
+
+    key(
+        "Root of the wallpaper library, or an array of roots. `~` and `$VARS` are expanded",
+        format!("wallpaper_dir = {}", toml_value(wallpaper_dir)),
+    );
+    key(
+        "Move with h/j/k/l",
+        format!("vim_motion = {}", d.vim_motion),
+    );
+    key(
+        "Moving past the last entry goes back to the first and the other way round",
+        format!("wrap_navigation = {}", d.wrap_navigation),
+    );
+    key(
+        "Click and scroll in the list",
+        format!("mouse_support = {}", d.mouse_support),
+    );
+    key(
+        "Milliseconds between the clicks of a double-click, which applies",
+        format!("double_click_ms = {}", d.double_click_ms),
+    );
+    key(
+        "\"left\", \"right\", \"top\" or \"bottom\"",
+        format!("list_position = {}", toml_value(d.list_position)),
+    );
+    key(
+        "How the preview fills its pane: \"fit\", \"crop\", \"width\" or \"height\"",
+        format!("preview_resize = {}", toml_value(d.preview_resize)),
+    );
+    key(
+        "\"fuzzy\" or \"substring\"",
+        format!("search_mode = {}", toml_value(d.search_mode)),
+    );
+    key(
+        "Initial order of the Favorites tab: \"recent\" or \"name\"",
+        format!("favorites_sort = {}", toml_value(d.favorites_sort)),
+    );
+    key(
+        "Label wallpapers with their path below wallpaper_dir",
+        format!("show_relative_paths = {}", d.show_relative_paths),
+    );
+    key(
+        "Details of the highlighted wallpaper on the last line",
+        format!("show_statusbar = {}", d.show_statusbar),
+    );
+    key(
+        "Keys for the current view on the bottom line",
+        format!("show_hints = {}", d.show_hints),
+    );
+    key(
+        "Frame the preview, titled with the file name and dimensions",
+        format!("preview_border = {}", d.preview_border),
+    );
+    key(
+        "Group the wallpapers tab under a header per subdirectory",
+        format!("group_by_dir = {}", d.group_by_dir),
+    );
+    key(
+        "Show the dominant colors beside the preview at startup",
+        format!("show_palette = {}", d.show_palette),
+    );
+    key(
+        "Ask which monitor gets the wallpaper when more than one is connected",
+        format!("ask_monitor = {}", d.ask_monitor),
+    );
+    key(
+        "Reapply the last wallpaper from the history when the TUI opens",
+        format!("auto_apply_on_launch = {}", d.auto_apply_on_launch),
+    );
+    key(
+        "Graphics protocol: auto, sixel, kitty, iterm2, halfblocks or off",
+        format!("image_protocol = {}", toml_value(d.image_protocol)),
+    );
+    key(
+        "Check every image in the background and refuse unreadable ones",
+        format!("validate = {}", d.validate),
+    );
+    key(
+        "Record applied wallpapers and show the History tab",
+        format!("enable_history = {}", d.enable_history),
+    );
+    key(
+        "swww transition: fade, wipe, grow, outer, any, none or random",
+        format!("transition_type = {}", toml_value(d.transition_type)),
+    );
+    key(
+        "swww transition frame rate, 1 to 240",
+        format!("transition_fps = {}", d.transition_fps),
+    );
+    key(
+        "swww transition length in seconds",
+        format!(
+            "transition_duration = {}",
+            toml_value(d.transition_duration)
+        ),
+    );
+    key("Generate colors with pywal", format!("pywal = {}", d.pywal));
+    key(
+        "pywal color backend: wal, colorthief, colorz, haishoku or schemer2",
+        format!("pywal_backend = {}", toml_value(d.pywal_backend)),
+    );
+    key(
+        "Generate a light pywal theme",
+        format!("pywal_light = {}", d.pywal_light),
+    );
+    key(
+        "Generate colors with hellwal",
+        format!("hellwal = {}", d.hellwal),
+    );
+    key(
+        "Include mp4 wallpapers, applied with mpvpaper",
+        format!("video = {}", d.mpvpaper),
+    );
+    key(
+        "File extensions to list",
+        format!("extensions = {}", toml_value(d.extensions)),
+    );
+    key(
+        "Glob patterns, relative to wallpaper_dir, to skip while scanning",
+        format!("exclude = {}", toml_value(d.exclude)),
+    );
+    key(
+        "How many directory levels to descend, unlimited when unset",
+        "# max_depth = 2".into(),
+    );
+    key(
+        "Follow symlinked files and directories",
+        format!("follow_symlinks = {}", d.follow_symlinks),
+    );
+    key(
+        "Include dotfiles and dot-directories",
+        format!("show_hidden = {}", d.show_hidden),
+    );
+    key(
+        "Hide images smaller than this",
+        "# min_width = 1920\n# min_height = 1080".into(),
+    );
+    key(
+        "Only list images close to this aspect ratio",
+        "# aspect_ratio = \"16:9\"".into(),
+    );
+    key(
+        "Allowed difference from aspect_ratio",
+        format!("aspect_tolerance = {}", toml_value(d.aspect_tolerance)),
+    );
+    key(
+        "Decoded previews kept in memory",
+        format!(
+            "image_cache_size = {}",
+            d.image_cache_size.unwrap_or_default()
+        ),
+    );
+    key(
+        "Memory cap for decoded previews in megabytes, none when unset",
+        "# image_cache_mb = 256".into(),
+    );
+    key(
+        "Longest edge previews are downscaled to",
+        format!("preview_max_size = {}", d.preview_max_size),
+    );
+    key(
+        "Command used to reveal a wallpaper, `{path}` and `{dir}` are replaced",
+        format!("file_manager = {}", toml_value(d.file_manager)),
+    );
+    key(
+        "Run after applying to reload the bar, [] to skip",
+        format!("reload_command = {}", toml_value(d.reload_command)),
+    );
+    key(
+        "Where --print writes current.<ext>, the XDG cache dir when unset",
+        "# cache_dir = \"~/.cache/wallrs\"".into(),
+    );
+    key(
+        "Scale and crop images to the monitor before applying, \"auto\" or \"WIDTHxHEIGHT\"",
+        "# pre_scale = \"auto\"".into(),
+    );
+    key(
+        "Tab opened at startup, the first enabled one when unset or disabled",
+        "# default_tab = \"Favorites\"".into(),
+    );
+
+    // Custom arguments are merged with the defaults, so these stay as examples
+    out.push_str("# Arguments passed to each program, `{path}` is replaced\n# [commands]\n");
+    for (name, args) in [
+        ("wal", &d.commands.wal),
+        ("hellwal", &d.commands.hellwal),
+        ("swww", &d.commands.swww),
+        ("feh", &d.commands.feh),
+        ("mpvpaper", &d.commands.mpvpaper),
+    ] {
+        out.push_str(&format!("# {} = {}\n", name, toml_value(args.clone())));
+    }
+
+    out.push_str("\n# Tabs, in display order\n");
+    for t in &d.tabs {
+        out.push_str(&format!(
+            "[[tabs]]\nname = {}\nenabled = {}\n\n",
+            toml_value(t.tab.title()),
+            t.enabled
+        ));
+    }
+
+    format!("{}\n", out.trim_end())
+}
+
+fn default_keybindings_toml() -> String {
+    let k = CustomKeybindings::default();
+    let mut out =
+        String::from("# wallrs keybindings, every key is optional and set to its default\n\n");
+    for (name, key, comment) in [
+        ("search", k.search, "Start searching"),
+        ("favorite", k.favorite, "Toggle favorite"),
+        ("multi_select", k.multi_select, "Toggle multi-select"),
+        ("rename", k.rename, "Rename the wallpaper"),
+        ("quit", k.quit, "Quit"),
+        ("switcher", k.switcher, "Open the quick switcher"),
+        (
+            "toggle_colors",
+            k.toggle_colors,
+            "Skip pywal/hellwal on the next applies",
+        ),
+        (
+            "preview_resize",
+            k.preview_resize,
+            "Cycle preview fit/crop/width/height",
+        ),
+        (
+            "zoom_in",
+            k.zoom_in,
+            "Zoom into the preview, arrows pan while zoomed",
+        ),
+        ("zoom_out", k.zoom_out, "Zoom the preview back out"),
+        ("grid", k.grid, "Toggle the thumbnail grid"),
+        ("remove", k.remove, "Remove from History or Favorites"),
+        (
+            "clear_list",
+            k.clear_list,
+            "Empty History or Favorites after confirming",
+        ),
+        ("delete", k.delete, "Move to the trash"),
+        ("reveal", k.reveal, "Open the folder in the file manager"),
+        (
+            "sort",
+            k.sort,
+            "Cycle the Wallpapers order, Favorites by recency or name",
+        ),
+        (
+            "swatches",
+            k.swatches,
+            "Show the dominant colors beside the preview",
+        ),
+        ("copy", k.copy, "Copy the wallpaper into another folder"),
+        ("undo", k.undo, "Undo the last rename or delete"),
+        ("apply", k.apply, "Apply the wallpaper and keep browsing"),
+        (
+            "light",
+            k.light,
+            "Switch pywal between light and dark themes",
+        ),
+        (
+            "recolor",
+            k.recolor,
+            "Regenerate colors from the applied wallpaper",
+        ),
+        (
+            "edit",
+            k.edit,
+            "Rotate the image or convert it to another format",
+        ),
+        (
+            "favorites_only",
+            k.favorites_only,
+            "Only list favorites in the wallpapers tab",
+        ),
+        ("group", k.group, "Group the wallpapers tab by subdirectory"),
+        (
+            "blacklist",
+            k.blacklist,
+            "Hide the wallpaper from the wallpapers tab for good",
+        ),
+        (
+            "collection",
+            k.collection,
+            "Switch the favorites tab to another collection",
+        ),
+        ("queue", k.queue, "Add to the queue tab"),
+        ("move_up", k.move_up, "Move up the queue"),
+        ("move_down", k.move_down, "Move down the queue"),
+        (
+            "select_all",
+            k.select_all,
+            "In multi-select, select every listed item (Ctrl optional)",
+        ),
+        (
+            "clear_selection",
+            k.clear_selection,
+            "In multi-select, clear the selection (Ctrl optional)",
+        ),
+        (
+            "invert_selection",
+            k.invert_selection,
+            "In multi-select, invert the selection",
+        ),
+        (
+            "toggle_selection",
+            k.toggle_selection,
+            "In multi-select, select or unselect the highlighted item",
+        ),
+        (
+            "range_select",
+            k.range_select,
+            "In multi-select, start or end a range at the highlighted item",
+        ),
+    ] {
+        out.push_str(&format!(
+            "# {}\n{} = {}\n\n",
+            comment,
+            name,
+            toml_value(key.to_string())
+        ));
+    }
+
+    format!("{}\n", out.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_favorites(enabled: bool) -> Config {
+        let mut config = Config::default();
+        for t in &mut config.tabs {
+            if t.tab == Tab::Favorites {
+                t.enabled = enabled;
+            }
         }
+        config
+    }
+
+    #[test]
+    fn start_tab_is_default_tab_when_enabled() {
+        let mut config = with_favorites(true);
+        config.default_tab = Some(Tab::Favorites);
+        assert_eq!(config.start_tab(), Tab::Favorites);
+    }
+
+    #[test]
+    fn start_tab_falls_back_when_default_tab_is_disabled() {
+        let mut config = with_favorites(false);
+        config.default_tab = Some(Tab::Favorites);
+        assert_eq!(config.start_tab(), Tab::Wallpapers);
+    }
+
+    #[test]
+    fn start_tab_is_first_enabled_without_default_tab() {
+        let mut config = with_favorites(true);
+        config.tabs.rotate_left(1);
+        assert_eq!(config.default_tab, None);
+        assert_eq!(config.start_tab(), config.tabs[0].tab);
+    }
+
+    #[test]
+    fn tabs_follow_the_configured_order() {
+        let mut config = Config {
+            tabs: [Tab::Favorites, Tab::Wallpapers, Tab::Queue, Tab::History]
+                .into_iter()
+                .map(|tab| TabConfig {
+                    tab,
+                    enabled: tab != Tab::Wallpapers,
+                })
+                .collect(),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.active_tabs(),
+            [Tab::Favorites, Tab::Queue, Tab::History]
+        );
+        assert_eq!(config.start_tab(), Tab::Favorites);
+
+        // History is dropped while it isn't recorded
+        config.enable_history = false;
+        assert_eq!(config.active_tabs(), [Tab::Favorites, Tab::Queue]);
+    }
+
+    #[test]
+    fn expand_path_expands_tilde_and_home() {
+        let home = dirs::home_dir().unwrap();
+        let base = Path::new("/base");
+        assert_eq!(expand_path("~", base), home);
+        assert_eq!(expand_path("~/Walls", base), home.join("Walls"));
+
+        let var = PathBuf::from(env::var("HOME").unwrap());
+        assert_eq!(expand_path("$HOME/Walls", base), var.join("Walls"));
+        assert_eq!(expand_path("${HOME}/Walls", base), var.join("Walls"));
+    }
+
+    #[test]
+    fn expand_path_leaves_the_rest_alone() {
+        let base = Path::new("/base");
+        // `~` only expands at the start, unset variables stay as written
+        assert_eq!(expand_path("Walls/~", base), base.join("Walls/~"));
+        assert_eq!(
+            expand_path("/$WALLRS_UNSET_VAR/a", base),
+            PathBuf::from("/$WALLRS_UNSET_VAR/a")
+        );
+        assert_eq!(expand_path("/${HOME/a", base), PathBuf::from("/${HOME/a"));
+        assert_eq!(expand_path("/abs", base), PathBuf::from("/abs"));
     }
 }
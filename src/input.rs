@@ -1,99 +1,477 @@
 use crate::config::CustomKeybindings;
-use crate::persistence::save_list;
-use crate::tui::Tab;
-use crossterm::event::{DisableMouseCapture, KeyCode};
-use crossterm::execute;
-use crossterm::terminal::{disable_raw_mode, LeaveAlternateScreen};
+use crate::line_edit::LineEdit;
+use crate::persistence::{load_search_history, save_list, save_search_history};
+use crate::tui::{PreviewResize, Tab};
+use crate::wallpapers::{Wallpaper, paths};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::widgets::ListState;
-use std::io;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Multi-select picks, kept by path so they survive searching and sorting
+#[derive(Default)]
+pub struct Selection {
+    items: HashSet<PathBuf>,
+    // Where a visual range started and what was picked before it
+    range: Option<(PathBuf, HashSet<PathBuf>)>,
+}
+
+impl Selection {
+    pub fn contains(&self, path: &Path) -> bool {
+        self.items.contains(path)
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.range = None;
+    }
+
+    // Keeps a pick when its file is renamed
+    // Old and new path of each renamed file, files may swap names
+    pub fn rename(&mut self, renamed: &[(PathBuf, PathBuf)]) {
+        let picked: Vec<&PathBuf> = renamed
+            .iter()
+            .filter(|(old_path, _)| self.items.remove(old_path))
+            .map(|(_, new_path)| new_path)
+            .collect();
+        self.items.extend(picked.into_iter().cloned());
+    }
+
+    pub fn toggle(&mut self, path: &Path) {
+        self.range = None;
+        if !self.items.remove(path) {
+            self.items.insert(path.to_path_buf());
+        }
+    }
+
+    pub fn select_all(&mut self, filtered: &[Arc<Wallpaper>]) {
+        self.range = None;
+        self.items.extend(filtered.iter().map(|w| w.path.clone()));
+    }
+
+    // Only the listed items flip, picks hidden by the search are kept
+    pub fn invert(&mut self, filtered: &[Arc<Wallpaper>]) {
+        self.range = None;
+        for w in filtered {
+            if !self.items.remove(&w.path) {
+                self.items.insert(w.path.clone());
+            }
+        }
+    }
+
+    pub fn in_range(&self) -> bool {
+        self.range.is_some()
+    }
+
+    pub fn start_range(&mut self, path: &Path) {
+        self.range = Some((path.to_path_buf(), self.items.clone()));
+        self.items.insert(path.to_path_buf());
+    }
+
+    pub fn end_range(&mut self) {
+        self.range = None;
+    }
+
+    // Picks everything between the range start and the highlighted item
+    pub fn extend_range(&mut self, filtered: &[Arc<Wallpaper>], selected: usize) {
+        let Some((anchor, before)) = &self.range else {
+            return;
+        };
+        let Some(start) = filtered.iter().position(|w| w.path == *anchor) else {
+            self.range = None;
+            return;
+        };
+        let end = selected.min(filtered.len() - 1);
+
+        let mut items = before.clone();
+        items.extend(
+            filtered[start.min(end)..=start.max(end)]
+                .iter()
+                .map(|w| w.path.clone()),
+        );
+        self.items = items;
+    }
+
+    // Selected items among `filtered`, in list order
+    pub fn in_list<'a>(&self, filtered: &'a [Arc<Wallpaper>]) -> Vec<&'a Arc<Wallpaper>> {
+        filtered
+            .iter()
+            .filter(|w| self.items.contains(&w.path))
+            .collect()
+    }
+}
+
+// Submitted searches, Up and Down bring them back while searching
+pub struct SearchHistory {
+    queries: Vec<String>,
+    // Index of the recalled query and what was typed before recalling
+    recall: Option<(usize, String)>,
+}
+
+impl SearchHistory {
+    pub fn load() -> Self {
+        Self {
+            queries: load_search_history(),
+            recall: None,
+        }
+    }
+
+    // Steps to an older or a newer query, past the newest is the typed text
+    fn step(&mut self, query: &mut LineEdit, older: bool) {
+        let last = self.queries.len();
+        let current = self.recall.as_ref().map_or(last, |(i, _)| *i);
+        let target = if older {
+            current.saturating_sub(1)
+        } else {
+            (current + 1).min(last)
+        };
+        if last == 0 || target == current {
+            return;
+        }
+
+        let typed = match self.recall.take() {
+            Some((_, typed)) => typed,
+            None => query.text().to_string(),
+        };
+        if target == last {
+            query.set_text(typed);
+        } else {
+            query.set_text(self.queries[target].clone());
+            self.recall = Some((target, typed));
+        }
+    }
+
+    // Puts back what was typed, false when nothing was recalled
+    fn cancel(&mut self, query: &mut LineEdit) -> bool {
+        match self.recall.take() {
+            Some((_, typed)) => {
+                query.set_text(typed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Editing a recalled query keeps it as typed text
+    fn edited(&mut self) {
+        self.recall = None;
+    }
+
+    // Repeats of the newest query aren't stored twice
+    fn submit(&mut self, query: &str) {
+        self.recall = None;
+        if query.is_empty() || self.queries.last().is_some_and(|q| q == query) {
+            return;
+        }
+        self.queries.push(query.to_string());
+        save_search_history(&mut self.queries);
+    }
+}
+
+// Index after moving `delta` rows in a list of `len`. Moves stop at the
+// ends, a move starting at an end wraps to the other one when `wrap` is set.
+fn step(selected: usize, len: usize, delta: isize, wrap: bool) -> usize {
+    let Some(last) = len.checked_sub(1) else {
+        return 0;
+    };
+    let selected = selected.min(last);
+    match delta {
+        0 => selected,
+        d if d > 0 && selected == last => {
+            if wrap {
+                0
+            } else {
+                last
+            }
+        }
+        d if d < 0 && selected == 0 => {
+            if wrap {
+                last
+            } else {
+                0
+            }
+        }
+        d if d > 0 => selected.saturating_add(d.unsigned_abs()).min(last),
+        d => selected.saturating_sub(d.unsigned_abs()),
+    }
+}
+
+// Next or previous tab in the configured order. A tab that isn't shown
+// (anymore) counts as sitting before the first one.
+fn cycle_tab(active_tabs: &[Tab], current: Tab, forward: bool) -> Tab {
+    let Some(&first) = active_tabs.first() else {
+        return current;
+    };
+    let len = active_tabs.len();
+    match active_tabs.iter().position(|&t| t == current) {
+        Some(pos) if forward => active_tabs[(pos + 1) % len],
+        Some(pos) => active_tabs[(pos + len - 1) % len],
+        None => first,
+    }
+}
+
+// Opens `tab` at its first entry with nothing picked, for keys and tab clicks
+// alike. The open tab is left as it is.
+pub fn switch_tab(
+    tab: Tab,
+    current_tab: &mut Tab,
+    selected: &mut usize,
+    list_state: &mut ListState,
+    multi_select: &mut bool,
+    selection: &mut Selection,
+) -> Option<Action> {
+    if tab == *current_tab {
+        return None;
+    }
+    *current_tab = tab;
+    *selected = 0;
+    list_state.select(Some(*selected));
+    selection.clear();
+    *multi_select = false;
+    Some(Action::TabSwitched)
+}
+
+// What a key asks of the TUI beyond moving around and toggling views. Keys,
+// clicks and the context menu all go through these.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    // Apply the highlighted wallpaper, the TUI steps aside meanwhile
+    Select,
+    // Apply it and keep browsing
+    Apply,
+    Favorite,
+    Rename,
+    Copy,
+    Delete,
+    Reveal,
+    Sort,
+    // Narrows the Wallpapers tab to favorites
+    FavoritesOnly,
+    // Puts the Wallpapers tab under a header per subdirectory
+    GroupByDir,
+    // Hides wallpapers from browsing and --random, see --show-blacklist
+    Blacklist,
+    // Reruns pywal/hellwal on the wallpaper that's set, not the highlighted one
+    Recolor,
+    Undo,
+    ClearList,
+    Switcher,
+    // Appends to the queue, PrintQueue leaves the TUI to print it
+    Enqueue,
+    PrintQueue,
+    // Opens the image edits below as a menu
+    Edit,
+    RotateClockwise,
+    RotateCounterClockwise,
+    Convert,
+    // Opens the collection switcher. Collection picks from it, 0 for the
+    // plain favorites and then the named ones in order.
+    Collections,
+    Collection(usize),
+    NewCollection,
+    // Where a Select or Apply waiting on the monitor menu goes, 0 for every
+    // monitor and then the detected outputs in order
+    Output(usize),
+    // Sent by switch_tab once the tab has changed, the favorites-only view
+    // doesn't carry over
+    TabSwitched,
+    // Leave the TUI without picking anything
+    Quit,
+}
+
+// Unreadable files are refused by the caller, keep them out of history
+pub fn record_history(
+    history: &mut Vec<Arc<Wallpaper>>,
+    sel: &Arc<Wallpaper>,
+    current_tab: Tab,
+    broken: &HashSet<PathBuf>,
+) {
+    if current_tab == Tab::Wallpapers && !broken.contains(&sel.path) {
+        history.retain(|p| p != sel);
+        history.insert(0, sel.clone());
+        save_list("history", &paths(history));
+    }
+}
 
 pub struct Input<'a> {
     pub key: KeyCode,
+    pub modifiers: KeyModifiers,
     pub current_tab: &'a mut Tab,
     pub in_search: &'a mut bool,
-    pub search_query: &'a mut String,
+    pub search_query: &'a mut LineEdit,
+    pub search_history: &'a mut SearchHistory,
     pub selected: &'a mut usize,
     pub list_state: &'a mut ListState,
-    pub filtered: &'a [PathBuf],
-    pub history: &'a mut Vec<PathBuf>,
-    pub favorites: &'a mut Vec<PathBuf>,
+    pub filtered: &'a [Arc<Wallpaper>],
+    pub history: &'a mut Vec<Arc<Wallpaper>>,
+    pub favorites: &'a mut Vec<Arc<Wallpaper>>,
+    pub queue: &'a mut Vec<Arc<Wallpaper>>,
     pub vim_motion: bool,
-    pub mouse_support: bool,
+    pub wrap_navigation: bool,
     pub keybindings: &'a CustomKeybindings,
     pub active_tabs: &'a [Tab],
+    pub skip_colors: &'a mut bool,
+    pub light: &'a mut bool,
+    pub preview_resize: &'a mut PreviewResize,
+    pub grid: &'a mut bool,
+    pub favorites_by_name: &'a mut bool,
+    // Where the Favorites tab is saved, see collection_list
+    pub favorites_list: &'a str,
+    pub show_swatches: &'a mut bool,
+    // Set while the grid is shown
+    pub grid_columns: Option<usize>,
+    // Rows moved by PageUp/PageDown
+    pub page_size: usize,
 }
 
 pub fn handle_input(
     input: &mut Input,
     multi_select: &mut bool,
-    selected_items: &mut Vec<usize>,
-) -> Option<PathBuf> {
+    selection: &mut Selection,
+) -> Option<Action> {
     let Input {
         key,
+        modifiers,
         current_tab,
         in_search,
         search_query,
+        search_history,
         selected,
         list_state,
         filtered,
         history,
         favorites,
+        queue,
         vim_motion,
-        mouse_support,
+        wrap_navigation,
         keybindings,
         active_tabs,
+        skip_colors,
+        light,
+        preview_resize,
+        grid,
+        favorites_by_name,
+        favorites_list,
+        show_swatches,
+        grid_columns,
+        page_size,
     } = input;
 
     let current_tab = &mut **current_tab;
     let in_search = &mut **in_search;
     let selected = &mut **selected;
 
+    let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+
+    // In the grid, arrows (and hjkl with vim motion) move across rows and columns
+    let vim = |c| *vim_motion && *key == KeyCode::Char(c);
+    let grid_target = grid_columns
+        .filter(|_| !*in_search && !filtered.is_empty())
+        .and_then(|columns| {
+            let last = filtered.len() - 1;
+            if *key == KeyCode::Left || vim('h') {
+                Some(selected.saturating_sub(1))
+            } else if *key == KeyCode::Right || vim('l') {
+                Some((*selected + 1).min(last))
+            } else if *key == KeyCode::Up || vim('k') {
+                Some(selected.checked_sub(columns).unwrap_or(*selected))
+            } else if *key == KeyCode::Down || vim('j') {
+                Some(if *selected + columns <= last {
+                    *selected + columns
+                } else {
+                    *selected
+                })
+            } else {
+                None
+            }
+        });
+    if let Some(target) = grid_target {
+        *selected = target;
+        list_state.select(Some(*selected));
+        selection.extend_range(filtered, *selected);
+        return None;
+    }
+
+    // Selection keys only apply in multi-select mode, Ctrl is optional there
+    let selecting = *multi_select && !*in_search;
+    let typed = search_query.text().to_string();
+
     match key {
+        // Select every visible item
+        KeyCode::Char(c) if selecting && *c == keybindings.select_all => {
+            selection.select_all(filtered);
+        }
+
+        // Clear the selection but stay in multi-select mode
+        KeyCode::Char(c) if selecting && *c == keybindings.clear_selection => {
+            selection.clear();
+        }
+
+        KeyCode::Char(c) if selecting && !ctrl && *c == keybindings.invert_selection => {
+            selection.invert(filtered);
+        }
+
+        KeyCode::Char(c)
+            if selecting && !ctrl && *c == keybindings.toggle_selection && !filtered.is_empty() =>
+        {
+            selection.toggle(&filtered[*selected].path);
+        }
+
+        // Start a range at the highlighted item, pressing it again ends the range
+        KeyCode::Char(c)
+            if selecting && !ctrl && *c == keybindings.range_select && !filtered.is_empty() =>
+        {
+            if selection.in_range() {
+                selection.end_range();
+            } else {
+                selection.start_range(&filtered[*selected].path);
+            }
+        }
+
         // Toggle multi-select mode, only outside search
         KeyCode::Char(c) if *c == keybindings.multi_select && !*in_search => {
             *multi_select = !*multi_select;
             if !*multi_select {
-                selected_items.clear();
-            } else if !selected_items.contains(selected) {
-                selected_items.push(*selected);
+                selection.clear();
             }
         }
 
-        // Tab switching
-        KeyCode::Tab if !*in_search => {
-            if let Some(pos) = active_tabs.iter().position(|&t| t == *current_tab) {
-                *current_tab = active_tabs[(pos + 1) % active_tabs.len()];
-                *selected = 0;
-                list_state.select(Some(*selected));
-                selected_items.clear();
-                *multi_select = false;
-            }
+        // Tab switching, vim-style with h/l
+        KeyCode::Tab | KeyCode::BackTab if !*in_search => {
+            let tab = cycle_tab(active_tabs, *current_tab, *key == KeyCode::Tab);
+            return switch_tab(
+                tab,
+                current_tab,
+                selected,
+                list_state,
+                multi_select,
+                selection,
+            );
         }
-
-        // Vim-style tab switching
-        KeyCode::Char('l') if *vim_motion && !*in_search => {
-            if let Some(pos) = active_tabs.iter().position(|&t| t == *current_tab) {
-                *current_tab = active_tabs[(pos + 1) % active_tabs.len()];
-                *selected = 0;
-                list_state.select(Some(*selected));
-                selected_items.clear();
-                *multi_select = false;
-            }
+        KeyCode::Char(c @ ('h' | 'l')) if *vim_motion && !*in_search => {
+            let tab = cycle_tab(active_tabs, *current_tab, *c == 'l');
+            return switch_tab(
+                tab,
+                current_tab,
+                selected,
+                list_state,
+                multi_select,
+                selection,
+            );
         }
-
-        KeyCode::Char('h') if *vim_motion && !*in_search => {
-            if let Some(pos) = active_tabs.iter().position(|&t| t == *current_tab) {
-                let new_pos = if pos == 0 {
-                    active_tabs.len() - 1
-                } else {
-                    pos - 1
-                };
-                *current_tab = active_tabs[new_pos];
-                *selected = 0;
-                list_state.select(Some(*selected));
-                selected_items.clear();
-                *multi_select = false;
+        // Jump to the Nth tab in the configured order
+        KeyCode::Char(c @ '1'..='9') if !*in_search => {
+            let index = *c as usize - '1' as usize;
+            if let Some(&tab) = active_tabs.get(index) {
+                return switch_tab(
+                    tab,
+                    current_tab,
+                    selected,
+                    list_state,
+                    multi_select,
+                    selection,
+                );
             }
         }
         // Start search
@@ -106,166 +484,524 @@ pub fn handle_input(
             list_state.select(Some(*selected));
         }
 
-        // Exit search
-        KeyCode::Esc if *in_search => *in_search = false,
-        KeyCode::Enter if *in_search => *in_search = false,
+        // Skip pywal/hellwal for the following applies
+        KeyCode::Char(c) if *c == keybindings.toggle_colors && !*in_search => {
+            **skip_colors = !**skip_colors;
+        }
 
-        // Search input
-        KeyCode::Char(c) if *in_search => {
-            search_query.push(*c);
-            *selected = 0;
-            list_state.select(Some(*selected));
+        // Light or dark pywal theme for the following applies
+        KeyCode::Char(c) if *c == keybindings.light && !*in_search => {
+            **light = !**light;
         }
-        KeyCode::Backspace if *in_search => {
-            search_query.pop();
-            *selected = 0;
-            list_state.select(Some(*selected));
+
+        KeyCode::Char(c) if *c == keybindings.recolor && !*in_search => {
+            return Some(Action::Recolor);
         }
 
-        // Navigation
-        KeyCode::Down => {
-            if *selected < filtered.len().saturating_sub(1) {
-                *selected += 1;
-                list_state.select(Some(*selected));
-                if *multi_select && !selected_items.contains(selected) {
-                    selected_items.push(*selected);
-                }
-            } else {
-                *selected -= filtered.len().saturating_sub(1);
-                list_state.select(Some(*selected));
-                if *multi_select && !selected_items.contains(selected) {
-                    selected_items.push(*selected);
-                }
-            }
+        // Cycle how the preview fills its pane
+        KeyCode::Char(c) if *c == keybindings.preview_resize && !*in_search => {
+            **preview_resize = preview_resize.next();
         }
-        KeyCode::PageDown => {
-            if *selected < filtered.len().saturating_sub(5) {
-                *selected += 5;
-                list_state.select(Some(*selected));
-                if *multi_select && !selected_items.contains(selected) {
-                    selected_items.push(*selected);
-                }
-            } else {
-                *selected -= filtered.len().saturating_sub(1);
-                list_state.select(Some(*selected));
-                if *multi_select && !selected_items.contains(selected) {
-                    selected_items.push(*selected);
-                }
-            }
+
+        // Toggle the thumbnail grid
+        KeyCode::Char(c) if *c == keybindings.grid && !*in_search => {
+            **grid = !**grid;
         }
-        KeyCode::Char('j') if *vim_motion => {
-            if *selected < filtered.len().saturating_sub(1) {
-                *selected += 1;
-                list_state.select(Some(*selected));
-                if *multi_select && !selected_items.contains(selected) {
-                    selected_items.push(*selected);
-                }
-            } else {
-                *selected -= filtered.len().saturating_sub(1);
-                list_state.select(Some(*selected));
-                if *multi_select && !selected_items.contains(selected) {
-                    selected_items.push(*selected);
-                }
-            }
+
+        // Show the dominant colors beside the preview
+        KeyCode::Char(c) if *c == keybindings.swatches && !*in_search => {
+            **show_swatches = !**show_swatches;
         }
-        KeyCode::Up => {
-            if *selected > 0 {
-                *selected -= 1;
-                list_state.select(Some(*selected));
-                if *multi_select && !selected_items.contains(selected) {
-                    selected_items.push(*selected);
-                }
-            } else {
-                *selected += filtered.len();
-                list_state.select(Some(*selected));
-                if *multi_select && !selected_items.contains(selected) {
-                    selected_items.push(*selected);
-                }
-            }
+
+        KeyCode::Char(c)
+            if *c == keybindings.favorites_only
+                && !*in_search
+                && *current_tab == Tab::Wallpapers =>
+        {
+            return Some(Action::FavoritesOnly);
         }
-        KeyCode::PageUp => {
-            if *selected > 4 {
-                *selected -= 5;
-                list_state.select(Some(*selected));
-                if *multi_select && !selected_items.contains(selected) {
-                    selected_items.push(*selected);
-                }
-            } else {
-                *selected += filtered.len();
+
+        KeyCode::Char(c)
+            if *c == keybindings.group && !*in_search && *current_tab == Tab::Wallpapers =>
+        {
+            return Some(Action::GroupByDir);
+        }
+
+        KeyCode::Char(c)
+            if *c == keybindings.blacklist
+                && !*in_search
+                && *current_tab == Tab::Wallpapers
+                && !filtered.is_empty() =>
+        {
+            return Some(Action::Blacklist);
+        }
+
+        KeyCode::Char(c)
+            if *c == keybindings.collection && !*in_search && *current_tab == Tab::Favorites =>
+        {
+            return Some(Action::Collections);
+        }
+
+        // Cycle the Wallpapers order, show favorites by name or by when they were added
+        KeyCode::Char(c) if *c == keybindings.sort && !*in_search => match current_tab {
+            Tab::Wallpapers => return Some(Action::Sort),
+            Tab::Favorites => {
+                **favorites_by_name = !**favorites_by_name;
+                *selected = 0;
                 list_state.select(Some(*selected));
-                if *multi_select && !selected_items.contains(selected) {
-                    selected_items.push(*selected);
-                }
             }
+            Tab::History | Tab::Queue => {}
+        },
+
+        // Open quick switcher
+        KeyCode::Char(c) if *c == keybindings.switcher && !*in_search => {
+            return Some(Action::Switcher);
         }
-        KeyCode::Char('k') if *vim_motion => {
-            if *selected > 0 {
-                *selected -= 1;
+
+        // Exit search, Esc first drops a recalled query
+        KeyCode::Esc if *in_search => {
+            if search_history.cancel(search_query) {
+                *selected = 0;
                 list_state.select(Some(*selected));
-                if *multi_select && !selected_items.contains(selected) {
-                    selected_items.push(*selected);
-                }
             } else {
-                *selected += filtered.len();
-                list_state.select(Some(*selected));
-                if *multi_select && !selected_items.contains(selected) {
-                    selected_items.push(*selected);
-                }
+                *in_search = false;
             }
         }
+        KeyCode::Enter if *in_search => {
+            search_history.submit(search_query.text());
+            *in_search = false;
+        }
+
+        // Search input, Left/Right and Home/End only move the cursor
+        _ if *in_search
+            && search_query.handle_key(&KeyEvent::new(*key, *modifiers))
+            && search_query.text() != typed =>
+        {
+            search_history.edited();
+            *selected = 0;
+            list_state.select(Some(*selected));
+        }
+        KeyCode::Up | KeyCode::Down if *in_search => {
+            search_history.step(search_query, *key == KeyCode::Up);
+            *selected = 0;
+            list_state.select(Some(*selected));
+        }
+
+        // Navigation
+        KeyCode::Down | KeyCode::Up | KeyCode::PageDown | KeyCode::PageUp => {
+            let page = *page_size as isize;
+            let delta = match key {
+                KeyCode::Down => 1,
+                KeyCode::Up => -1,
+                KeyCode::PageDown => page,
+                _ => -page,
+            };
+            *selected = step(*selected, filtered.len(), delta, *wrap_navigation);
+            list_state.select(Some(*selected));
+            selection.extend_range(filtered, *selected);
+        }
+        KeyCode::Char(c @ ('j' | 'k')) if *vim_motion => {
+            let delta = if *c == 'j' { 1 } else { -1 };
+            *selected = step(*selected, filtered.len(), delta, *wrap_navigation);
+            list_state.select(Some(*selected));
+            selection.extend_range(filtered, *selected);
+        }
 
         // Toggle favorite
         KeyCode::Char(c) if *c == keybindings.favorite && !filtered.is_empty() => {
-            if *multi_select && !selected_items.is_empty() {
-                for &i in selected_items.iter() {
-                    let item = filtered[i].clone();
-                    if favorites.contains(&item) {
-                        favorites.retain(|p| p != &item);
-                    } else {
-                        favorites.insert(0, item);
-                    }
-                }
+            return Some(Action::Favorite);
+        }
+        // Drop entries from history, favorites or the queue, the files themselves stay
+        KeyCode::Char(c)
+            if *c == keybindings.remove
+                && !filtered.is_empty()
+                && !*in_search
+                && *current_tab != Tab::Wallpapers =>
+        {
+            let (list, name) = match current_tab {
+                Tab::History => (&mut **history, "history"),
+                Tab::Queue => (&mut **queue, "queue"),
+                _ => (&mut **favorites, *favorites_list),
+            };
+            let picked = selection.in_list(filtered);
+            let targets: Vec<Arc<Wallpaper>> = if *multi_select && !picked.is_empty() {
+                picked.into_iter().cloned().collect()
             } else {
-                let item = filtered[*selected].clone();
-                if favorites.contains(&item) {
-                    favorites.retain(|p| p != &item);
-                } else {
-                    favorites.insert(0, item);
-                }
+                vec![filtered[*selected].clone()]
+            };
+            list.retain(|w| !targets.contains(w));
+            save_list(name, &paths(list));
+
+            selection.clear();
+            *selected = (*selected).min(list.len().saturating_sub(1));
+            list_state.select((!list.is_empty()).then_some(*selected));
+        }
+
+        // Move the highlighted entry up or down the queue
+        KeyCode::Char(c)
+            if (*c == keybindings.move_up || *c == keybindings.move_down)
+                && *current_tab == Tab::Queue
+                && !filtered.is_empty() =>
+        {
+            let target = if *c == keybindings.move_up {
+                selected.checked_sub(1)
+            } else {
+                Some(*selected + 1).filter(|&i| i < queue.len())
+            };
+            if let Some(target) = target {
+                queue.swap(*selected, target);
+                save_list("queue", &paths(queue));
+                *selected = target;
+                list_state.select(Some(*selected));
             }
-            save_list("favorites.txt", favorites);
         }
+
+        KeyCode::Char(c)
+            if *c == keybindings.queue
+                && !filtered.is_empty()
+                && !*in_search
+                && *current_tab != Tab::Queue =>
+        {
+            return Some(Action::Enqueue);
+        }
+
+        // Ask before emptying History, Favorites or the queue
+        KeyCode::Char(c)
+            if *c == keybindings.clear_list
+                && !filtered.is_empty()
+                && !*in_search
+                && *current_tab != Tab::Wallpapers =>
+        {
+            return Some(Action::ClearList);
+        }
+
         KeyCode::Char(c)
             if *c == keybindings.rename
                 && !filtered.is_empty()
                 && !*in_search
                 && *current_tab == Tab::Wallpapers =>
         {
-            return Some(PathBuf::from("__rename__"));
+            return Some(Action::Rename);
         }
 
-        KeyCode::Enter if !*in_search && !filtered.is_empty() => {
-            let sel = filtered[*selected].clone();
-            if *current_tab == Tab::Wallpapers {
-                history.retain(|p| p != &sel);
-                history.insert(0, sel.clone());
-                save_list("history.txt", history);
-            }
-            return Some(sel);
+        KeyCode::Char(c) if *c == keybindings.undo && !*in_search => {
+            return Some(Action::Undo);
         }
 
-        // Quit
-        KeyCode::Char(c) if *c == keybindings.quit && !filtered.is_empty() && !*in_search => {
-            if *mouse_support {
-                execute!(io::stdout(), DisableMouseCapture).ok();
-            }
-            disable_raw_mode().unwrap();
-            execute!(io::stdout(), LeaveAlternateScreen).unwrap();
-            std::process::exit(0);
+        KeyCode::Char(c) if *c == keybindings.copy && !filtered.is_empty() && !*in_search => {
+            return Some(Action::Copy);
+        }
+
+        KeyCode::Char(c) if *c == keybindings.reveal && !filtered.is_empty() && !*in_search => {
+            return Some(Action::Reveal);
+        }
+
+        KeyCode::Char(c) if *c == keybindings.delete && !filtered.is_empty() && !*in_search => {
+            return Some(Action::Delete);
         }
 
+        KeyCode::Char(c) if *c == keybindings.edit && !filtered.is_empty() && !*in_search => {
+            return Some(Action::Edit);
+        }
+
+        // Enter hands the wallpaper to the caller, the apply key applies it in place
+        KeyCode::Enter
+            if modifiers.contains(KeyModifiers::ALT)
+                && *current_tab == Tab::Queue
+                && !filtered.is_empty() =>
+        {
+            return Some(Action::PrintQueue);
+        }
+        KeyCode::Enter if !*in_search && !filtered.is_empty() => return Some(Action::Select),
+        KeyCode::Char(c) if *c == keybindings.apply && !*in_search && !filtered.is_empty() => {
+            return Some(Action::Apply);
+        }
+
+        // Quit
+        KeyCode::Char(c) if *c == keybindings.quit && !*in_search => return Some(Action::Quit),
+
         _ => {}
     }
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Everything handle_input borrows, for driving it key by key
+    struct Keys {
+        tab: Tab,
+        in_search: bool,
+        query: LineEdit,
+        search_history: SearchHistory,
+        selected: usize,
+        list_state: ListState,
+        filtered: Vec<Arc<Wallpaper>>,
+        history: Vec<Arc<Wallpaper>>,
+        favorites: Vec<Arc<Wallpaper>>,
+        queue: Vec<Arc<Wallpaper>>,
+        keybindings: CustomKeybindings,
+        active_tabs: Vec<Tab>,
+        wrap: bool,
+        page_size: usize,
+        toggles: [bool; 5],
+        preview_resize: PreviewResize,
+        multi_select: bool,
+        selection: Selection,
+    }
+
+    impl Keys {
+        fn new(len: usize, active_tabs: &[Tab]) -> Self {
+            Self {
+                tab: active_tabs[0],
+                in_search: false,
+                query: LineEdit::default(),
+                search_history: SearchHistory {
+                    queries: Vec::new(),
+                    recall: None,
+                },
+                selected: 0,
+                list_state: ListState::default(),
+                filtered: (0..len)
+                    .map(|i| Arc::new(Wallpaper::from_path(PathBuf::from(format!("{}.png", i)))))
+                    .collect(),
+                history: Vec::new(),
+                favorites: Vec::new(),
+                queue: Vec::new(),
+                keybindings: CustomKeybindings::default(),
+                active_tabs: active_tabs.to_vec(),
+                wrap: true,
+                page_size: 10,
+                toggles: [false; 5],
+                preview_resize: PreviewResize::Fit,
+                multi_select: false,
+                selection: Selection::default(),
+            }
+        }
+
+        fn press(&mut self, key: KeyCode) -> Option<Action> {
+            let [skip_colors, light, grid, favorites_by_name, show_swatches] = &mut self.toggles;
+            let mut input = Input {
+                key,
+                modifiers: KeyModifiers::NONE,
+                current_tab: &mut self.tab,
+                in_search: &mut self.in_search,
+                search_query: &mut self.query,
+                search_history: &mut self.search_history,
+                selected: &mut self.selected,
+                list_state: &mut self.list_state,
+                filtered: &self.filtered,
+                history: &mut self.history,
+                favorites: &mut self.favorites,
+                queue: &mut self.queue,
+                vim_motion: false,
+                wrap_navigation: self.wrap,
+                keybindings: &self.keybindings,
+                active_tabs: &self.active_tabs,
+                skip_colors,
+                light,
+                preview_resize: &mut self.preview_resize,
+                grid,
+                favorites_by_name,
+                favorites_list: "favorites",
+                show_swatches,
+                grid_columns: None,
+                page_size: self.page_size,
+            };
+            handle_input(&mut input, &mut self.multi_select, &mut self.selection)
+        }
+
+        // Where the selection lands after each key
+        fn walk(&mut self, keys: &[KeyCode]) -> Vec<usize> {
+            keys.iter()
+                .map(|&key| {
+                    self.press(key);
+                    assert_eq!(self.list_state.selected(), Some(self.selected));
+                    self.selected
+                })
+                .collect()
+        }
+    }
+
+    const ALL_TABS: [Tab; 3] = [Tab::Wallpapers, Tab::History, Tab::Favorites];
+
+    // The tab shown after each key
+    fn tabs_after(keys: &mut Keys, pressed: &[KeyCode]) -> Vec<Tab> {
+        pressed
+            .iter()
+            .map(|&key| {
+                keys.press(key);
+                keys.tab
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tab_cycles_in_the_configured_order() {
+        let order = [Tab::Favorites, Tab::Queue, Tab::History];
+        let mut keys = Keys::new(5, &order);
+        assert_eq!(
+            tabs_after(&mut keys, &[KeyCode::Tab; 4]),
+            [Tab::Queue, Tab::History, Tab::Favorites, Tab::Queue]
+        );
+        assert_eq!(
+            tabs_after(&mut keys, &[KeyCode::BackTab; 3]),
+            [Tab::Favorites, Tab::History, Tab::Queue]
+        );
+    }
+
+    #[test]
+    fn number_keys_pick_the_nth_active_tab() {
+        let order = [Tab::History, Tab::Favorites, Tab::Wallpapers];
+        let mut keys = Keys::new(5, &order);
+        let digits = [KeyCode::Char('3'), KeyCode::Char('1'), KeyCode::Char('2')];
+        assert_eq!(
+            tabs_after(&mut keys, &digits),
+            [Tab::Wallpapers, Tab::History, Tab::Favorites]
+        );
+    }
+
+    #[test]
+    fn number_keys_past_the_tabs_do_nothing() {
+        let mut keys = Keys::new(5, &ALL_TABS);
+        keys.press(KeyCode::Char('2'));
+        keys.selected = 3;
+        for digit in ['4', '9', '0'] {
+            keys.press(KeyCode::Char(digit));
+            assert_eq!((keys.tab, keys.selected), (Tab::History, 3));
+        }
+    }
+
+    #[test]
+    fn number_keys_reset_the_selection_like_tab() {
+        let mut keys = Keys::new(5, &ALL_TABS);
+        keys.selected = 3;
+        keys.multi_select = true;
+        keys.selection.toggle(Path::new("1.png"));
+        keys.press(KeyCode::Char('3'));
+        assert_eq!((keys.tab, keys.selected), (Tab::Favorites, 0));
+        assert!(!keys.multi_select);
+        assert!(!keys.selection.contains(Path::new("1.png")));
+    }
+
+    #[test]
+    fn number_keys_type_while_searching() {
+        let mut keys = Keys::new(5, &ALL_TABS);
+        keys.in_search = true;
+        keys.press(KeyCode::Char('2'));
+        assert_eq!(keys.tab, Tab::Wallpapers);
+        assert_eq!(keys.query.text(), "2");
+    }
+
+    #[test]
+    fn search_edits_at_the_cursor() {
+        let mut keys = Keys::new(5, &ALL_TABS);
+        keys.in_search = true;
+        for key in [KeyCode::Char('s'), KeyCode::Char('k'), KeyCode::Left] {
+            keys.press(key);
+        }
+        keys.selected = 3;
+        // Moving the cursor leaves the results alone
+        keys.press(KeyCode::Home);
+        keys.press(KeyCode::End);
+        assert_eq!(keys.selected, 3);
+        keys.press(KeyCode::Left);
+        keys.press(KeyCode::Char('a'));
+        assert_eq!(keys.query.text(), "sak");
+        assert_eq!(keys.selected, 0);
+    }
+
+    #[test]
+    fn tab_from_a_hidden_tab_goes_to_the_first() {
+        let mut keys = Keys::new(5, &[Tab::History, Tab::Favorites]);
+        keys.tab = Tab::Wallpapers;
+        assert_eq!(tabs_after(&mut keys, &[KeyCode::Tab]), [Tab::History]);
+    }
+
+    #[test]
+    fn paging_stops_at_the_bottom_then_wraps() {
+        let mut keys = Keys::new(25, &ALL_TABS);
+        let down = KeyCode::PageDown;
+        assert_eq!(keys.walk(&[down, down, down, down]), [10, 20, 24, 0]);
+    }
+
+    #[test]
+    fn paging_stops_at_the_top_then_wraps() {
+        let mut keys = Keys::new(25, &ALL_TABS);
+        keys.selected = 13;
+        let up = KeyCode::PageUp;
+        assert_eq!(keys.walk(&[up, up, up]), [3, 0, 24]);
+    }
+
+    #[test]
+    fn paging_clamps_at_both_ends_without_wrap() {
+        let mut keys = Keys::new(25, &ALL_TABS);
+        keys.wrap = false;
+        keys.selected = 22;
+        assert_eq!(keys.walk(&[KeyCode::PageDown, KeyCode::PageDown]), [24, 24]);
+        keys.selected = 2;
+        assert_eq!(keys.walk(&[KeyCode::PageUp, KeyCode::PageUp]), [0, 0]);
+    }
+
+    #[test]
+    fn paging_follows_the_list_height() {
+        let mut keys = Keys::new(25, &ALL_TABS);
+        keys.page_size = 3;
+        assert_eq!(
+            keys.walk(&[KeyCode::PageDown, KeyCode::PageDown, KeyCode::PageUp]),
+            [3, 6, 3]
+        );
+        // A list shorter than a page goes straight to its ends
+        let mut keys = Keys::new(4, &ALL_TABS);
+        assert_eq!(keys.walk(&[KeyCode::PageDown, KeyCode::PageUp]), [3, 0]);
+    }
+
+    #[test]
+    fn step_on_empty_list_stays_at_zero() {
+        for delta in [-10, -1, 0, 1, 10] {
+            assert_eq!(step(0, 0, delta, true), 0);
+            assert_eq!(step(3, 0, delta, false), 0);
+        }
+    }
+
+    #[test]
+    fn step_on_single_item_list_stays_on_it() {
+        for delta in [-10, -1, 0, 1, 10] {
+            assert_eq!(step(0, 1, delta, true), 0);
+            assert_eq!(step(0, 1, delta, false), 0);
+        }
+    }
+
+    #[test]
+    fn step_wraps_only_from_the_ends() {
+        assert_eq!(step(4, 5, 1, true), 0);
+        assert_eq!(step(0, 5, -1, true), 4);
+        // A page jump stops at the end before wrapping on the next one
+        assert_eq!(step(3, 5, 10, true), 4);
+        assert_eq!(step(1, 5, -10, true), 0);
+    }
+
+    #[test]
+    fn step_clamps_without_wrap() {
+        assert_eq!(step(4, 5, 1, false), 4);
+        assert_eq!(step(0, 5, -1, false), 0);
+        assert_eq!(step(3, 5, 10, false), 4);
+        assert_eq!(step(2, 5, -10, false), 0);
+    }
+
+    #[test]
+    fn step_moves_within_the_list() {
+        assert_eq!(step(2, 5, 1, true), 3);
+        assert_eq!(step(2, 5, -1, false), 1);
+        assert_eq!(step(2, 5, 0, true), 2);
+    }
+
+    #[test]
+    fn step_clamps_a_selection_past_the_end() {
+        assert_eq!(step(9, 5, 0, true), 4);
+        assert_eq!(step(9, 5, -1, true), 3);
+        assert_eq!(step(9, 5, 1, true), 0);
+    }
+}
@@ -0,0 +1,49 @@
+//! Wallpaper scanning, selection and application behind the `wallrs` TUI.
+//!
+//! The binary is a thin layer over this crate, other tools can use the same
+//! pieces without shelling out:
+//!
+//! ```no_run
+//! use wallrs::{Config, apply_wallpaper, select};
+//!
+//! let config = Config::load()?;
+//! if let Some(path) = select(&config, "mountain")? {
+//!     apply_wallpaper(&path, None, &config)?;
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub mod apply;
+mod colors;
+pub mod config;
+mod input;
+mod line_edit;
+mod mouse;
+mod ops;
+mod persistence;
+mod search;
+mod tui;
+pub mod wallpapers;
+
+pub use apply::apply_wallpaper;
+pub use config::Config;
+pub use persistence::{SavedList, load_outputs, load_recent_dirs, record_recent_dirs};
+pub use tui::{Tab, TuiApp, graphics_protocol, restore_terminal};
+pub use wallpapers::{ScanOptions, Wallpaper, load_wallpapers};
+
+use std::path::PathBuf;
+
+/// Picks the wallpaper whose file name best matches `query` without opening
+/// the TUI, ranked like the quick switcher. Ties go to the first in scan
+/// order, blacklisted wallpapers are skipped and `None` means nothing matched.
+pub fn select(config: &Config, query: &str) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+    let (wallpapers, _) =
+        load_wallpapers(&config.wallpaper_dirs, &ScanOptions::from_config(config))?;
+    let blacklist = SavedList::Blacklist.load();
+    Ok(wallpapers
+        .into_iter()
+        .filter(|w| !blacklist.contains(&w.path))
+        .filter_map(|w| search::fuzzy_score(&w.file_name(), query).map(|score| (score, w)))
+        .min_by_key(|(score, _)| std::cmp::Reverse(*score))
+        .map(|(_, w)| w.path))
+}
@@ -0,0 +1,191 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+// ------------------------
+// Single-line text input
+// ------------------------
+
+// Text with a cursor counted in grapheme clusters, so "ü" or a CJK
+// character is one step however many bytes it takes
+#[derive(Default)]
+pub struct LineEdit {
+    text: String,
+    cursor: usize,
+}
+
+impl LineEdit {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    // Replaces the text, the cursor goes to its end
+    pub fn set_text(&mut self, text: String) {
+        self.text = text;
+        self.cursor = self.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text(String::new());
+    }
+
+    // Editing and movement keys, false when the key isn't one of them
+    pub fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.len(),
+            KeyCode::Char('u') if ctrl => {
+                self.text.drain(..self.byte_offset(self.cursor));
+                self.cursor = 0;
+            }
+            KeyCode::Char(_) if ctrl => return false,
+            KeyCode::Char(c) => self.insert(c),
+            KeyCode::Backspace => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    self.remove_at(self.cursor);
+                }
+            }
+            KeyCode::Delete => self.remove_at(self.cursor),
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.len()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.len(),
+            _ => return false,
+        }
+        true
+    }
+
+    // The part of the text that fits in `width` columns and the cursor
+    // column within it. The window scrolls just enough to keep the cursor
+    // in view, with a column left for it past the end.
+    pub fn visible(&self, width: u16) -> (String, u16) {
+        let width = width as usize;
+        let graphemes: Vec<&str> = self.text.graphemes(true).collect();
+
+        let mut start = 0;
+        while start < self.cursor && columns(&graphemes[start..self.cursor]) + 1 > width {
+            start += 1;
+        }
+
+        let mut shown = String::new();
+        let mut used = 0;
+        for g in &graphemes[start..] {
+            let w = g.width();
+            if used + w > width {
+                break;
+            }
+            shown.push_str(g);
+            used += w;
+        }
+        (shown, columns(&graphemes[start..self.cursor]) as u16)
+    }
+
+    fn insert(&mut self, c: char) {
+        let at = self.byte_offset(self.cursor);
+        self.text.insert(at, c);
+        // A combining mark joins the previous cluster instead of adding one
+        self.cursor = self.text[..at + c.len_utf8()].graphemes(true).count();
+    }
+
+    fn remove_at(&mut self, index: usize) {
+        if index < self.len() {
+            let start = self.byte_offset(index);
+            let end = self.byte_offset(index + 1);
+            self.text.replace_range(start..end, "");
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.text.graphemes(true).count()
+    }
+
+    fn byte_offset(&self, index: usize) -> usize {
+        self.text
+            .grapheme_indices(true)
+            .nth(index)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+}
+
+fn columns(graphemes: &[&str]) -> usize {
+    graphemes.iter().map(|g| g.width()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str) -> LineEdit {
+        let mut edit = LineEdit::default();
+        for c in text.chars() {
+            edit.handle_key(&KeyEvent::from(KeyCode::Char(c)));
+        }
+        edit
+    }
+
+    fn press(edit: &mut LineEdit, keys: &[KeyCode]) {
+        for &key in keys {
+            assert!(edit.handle_key(&KeyEvent::from(key)));
+        }
+    }
+
+    #[test]
+    fn combining_marks_are_one_step() {
+        let mut edit = typed("cafe\u{301}s");
+        assert_eq!(edit.cursor, 5);
+        press(&mut edit, &[KeyCode::Left, KeyCode::Left]);
+        assert_eq!(edit.cursor, 3);
+        press(&mut edit, &[KeyCode::Right]);
+        assert_eq!(edit.cursor, 4);
+        press(&mut edit, &[KeyCode::Backspace]);
+        assert_eq!(edit.text(), "cafs");
+    }
+
+    #[test]
+    fn edits_in_the_middle() {
+        let mut edit = typed("sunet");
+        press(&mut edit, &[KeyCode::Left, KeyCode::Left]);
+        press(&mut edit, &[KeyCode::Char('s')]);
+        assert_eq!(edit.text(), "sunset");
+        press(&mut edit, &[KeyCode::Backspace, KeyCode::Backspace]);
+        assert_eq!((edit.text(), edit.cursor), ("suet", 2));
+        press(&mut edit, &[KeyCode::Delete]);
+        assert_eq!((edit.text(), edit.cursor), ("sut", 2));
+    }
+
+    #[test]
+    fn home_and_end_jump_to_the_ends() {
+        let mut edit = typed("dune");
+        press(&mut edit, &[KeyCode::Home, KeyCode::Char('>')]);
+        assert_eq!((edit.text(), edit.cursor), (">dune", 1));
+        press(&mut edit, &[KeyCode::End, KeyCode::Char('!')]);
+        assert_eq!((edit.text(), edit.cursor), (">dune!", 6));
+        // Delete at the end and Backspace at the start change nothing
+        press(
+            &mut edit,
+            &[KeyCode::Delete, KeyCode::Home, KeyCode::Backspace],
+        );
+        assert_eq!(edit.text(), ">dune!");
+    }
+
+    #[test]
+    fn visible_scrolls_to_keep_the_cursor_in_view() {
+        let mut edit = typed("mountains");
+        // A column is left for the cursor past the end
+        assert_eq!(edit.visible(5), ("ains".to_string(), 4));
+        press(&mut edit, &[KeyCode::Home]);
+        assert_eq!(edit.visible(5), ("mount".to_string(), 0));
+        assert_eq!(edit.visible(20), ("mountains".to_string(), 0));
+
+        // Wide characters take two columns each
+        let edit = typed("壁紙一覧");
+        assert_eq!(edit.visible(5), ("一覧".to_string(), 4));
+        assert_eq!(edit.visible(4), ("覧".to_string(), 2));
+    }
+}
@@ -1,42 +1,106 @@
-mod apply;
-mod config;
-mod input;
-mod mouse;
-mod persistence;
-mod tui;
-mod wallpapers;
-
-use apply::apply_wallpaper;
-use clap::Parser;
-use config::Config;
-use crossterm::execute;
-use crossterm::terminal::enable_raw_mode;
-use crossterm::terminal::EnterAlternateScreen;
-use crossterm::terminal::LeaveAlternateScreen;
+use clap::{Parser, ValueEnum};
+use rand::seq::SliceRandom;
+use std::env;
 use std::fs;
-use std::io;
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use wallpapers::load_wallpapers;
+use wallrs::apply::{
+    apply_wallpaper, connected_outputs, find_in_path, run_reload_command, targets_outputs,
+};
+use wallrs::config::{self, Config};
+use wallrs::wallpapers::{ScanOptions, find_broken, load_wallpapers, spawn_scan};
+use wallrs::{
+    SavedList, TuiApp, graphics_protocol, load_outputs, load_recent_dirs, record_recent_dirs,
+    restore_terminal,
+};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Path to the wallpaper directory
+    /// Path to the wallpaper directory, can be given several times
     #[arg(short, long)]
-    path: Option<PathBuf>,
+    path: Vec<PathBuf>,
 
     /// Only print wallpaper info instead of applying
     #[arg(short, long)]
     print: bool,
 
+    /// Exit after the first pick and write its absolute path to stdout
+    #[arg(long)]
+    print_selection: bool,
+
     /// Generate colors using pywal
     #[arg(long)]
     pywal: Option<bool>,
 
+    /// Generate a light pywal theme
+    #[arg(long)]
+    light: bool,
+
     /// Generate colors using hellwal
     #[arg(long)]
     hellwal: Option<bool>,
+
+    /// Print details about the wallpaper scan
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// List wallpapers that can't be decoded and exit
+    #[arg(long)]
+    validate_images: bool,
+
+    /// Write a commented default config.toml and keybindings.toml and exit
+    #[arg(long)]
+    init_config: bool,
+
+    /// Overwrite existing files with --init-config
+    #[arg(long, requires = "init_config")]
+    force: bool,
+
+    /// Apply the wallpaper to this monitor only
+    #[arg(short, long, value_name = "NAME")]
+    output: Option<String>,
+
+    /// Reapply the last wallpaper of every connected monitor and exit
+    #[arg(long)]
+    restore_all: bool,
+
+    /// Apply a random wallpaper without the TUI and exit
+    #[arg(long)]
+    random: bool,
+
+    /// Where --random picks from
+    #[arg(long, value_enum, default_value_t = RandomSource::All, requires = "random")]
+    from: RandomSource,
+
+    /// Choose the wallpaper directory from the recently used ones
+    #[arg(long, conflicts_with = "path")]
+    pick_dir: bool,
+
+    /// Print the detected environment for bug reports and exit
+    #[arg(long)]
+    doctor: bool,
+
+    /// Print the wallpapers hidden with the blacklist key and exit
+    #[arg(long)]
+    show_blacklist: bool,
+
+    /// Show every hidden wallpaper again and exit
+    #[arg(long)]
+    clear_blacklist: bool,
+
+    /// Hide wallpapers smaller than WIDTHxHEIGHT
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = config::parse_resolution)]
+    min_resolution: Option<(u32, u32)>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum RandomSource {
+    Favorites,
+    History,
+    Queue,
+    All,
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -44,76 +108,420 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse CLI flags
     let args = Args::parse();
 
+    if args.init_config {
+        for path in config::init_config(args.force)? {
+            println!("Wrote {}", path.display());
+        }
+        return Ok(());
+    }
+
     // Load config
-    let mut cfg = Config::load();
+    let mut cfg = Config::load()?;
 
     if let Some(pywal_flag) = args.pywal {
         cfg.pywal = pywal_flag; // only override if user passed --pywal
     }
+    if args.light {
+        cfg.pywal_light = true;
+    }
     if let Some(hellwal_flag) = args.hellwal {
         cfg.hellwal = hellwal_flag; // only override if user passed --pywal
     }
+    if let Some((width, height)) = args.min_resolution {
+        cfg.min_width = Some(width);
+        cfg.min_height = Some(height);
+    }
+    cfg.output = args.output.clone();
+    if let Some(output) = &cfg.output
+        && !targets_outputs(&cfg)
+    {
+        return Err(format!(
+            "--output {} needs swww, or {{output}} in the feh or mpvpaper command",
+            output
+        )
+        .into());
+    }
+
+    if args.restore_all {
+        restore_all(&cfg);
+        return Ok(());
+    }
+
     // If --path is set, override wallpaper_dir
-    if let Some(path) = args.path {
-        if !path.is_dir() {
-            eprintln!(
-                "Error: specified path is not a directory: {}",
-                path.display()
-            );
-            return Ok(());
+    if !args.path.is_empty() {
+        let cwd = env::current_dir()?;
+        let mut dirs = Vec::new();
+        for path in &args.path {
+            let path = config::expand_path(&path.to_string_lossy(), &cwd);
+            if !path.is_dir() {
+                eprintln!(
+                    "Error: specified path is not a directory: {}",
+                    path.display()
+                );
+                return Ok(());
+            }
+            dirs.push(path);
         }
-        cfg.wallpaper_dir = path;
+        cfg.wallpaper_dirs = dirs;
     }
 
-    // Load wallpapers
-    let wallpapers = load_wallpapers(&cfg.wallpaper_dir, &cfg.mpvpaper)?;
-    if wallpapers.is_empty() {
-        eprintln!("No wallpapers found in {}", cfg.wallpaper_dir.display());
+    if args.pick_dir {
+        match pick_dir()? {
+            Some(dir) => cfg.wallpaper_dirs = vec![dir],
+            None => return Ok(()),
+        }
+    }
+
+    if args.doctor {
+        doctor(&cfg);
         return Ok(());
     }
 
-    enable_raw_mode()?;
+    if args.show_blacklist {
+        for path in SavedList::Blacklist.load() {
+            println!("{}", path.display());
+        }
+        return Ok(());
+    }
+    if args.clear_blacklist {
+        SavedList::Blacklist.save(&[]);
+        return Ok(());
+    }
 
-    execute!(io::stdout(), EnterAlternateScreen)?;
-    let mut tui = tui::TuiApp::new(&wallpapers, &cfg)?;
-    loop {
-        // Run TUI to select a wallpaper
-        let selected_wallpaper = tui.run().await?;
+    record_recent_dirs(&cfg.wallpaper_dirs);
+
+    if args.random {
+        return apply_random(&cfg, args.from);
+    }
+
+    // The listing flags need the full scan up front, the TUI streams it in
+    let (wallpapers, scan_rx) = if args.verbose || args.validate_images {
+        let (wallpapers, stats) =
+            load_wallpapers(&cfg.wallpaper_dirs, &ScanOptions::from_config(&cfg))?;
+        if stats.below_resolution > 0 {
+            eprintln!(
+                "Skipped {} images below minimum resolution",
+                stats.below_resolution
+            );
+        }
+        if stats.wrong_aspect > 0 {
+            eprintln!(
+                "Skipped {} images not matching the aspect ratio",
+                stats.wrong_aspect
+            );
+        }
+        if args.verbose {
+            eprintln!(
+                "Found {} wallpapers in {}",
+                wallpapers.len(),
+                cfg.wallpaper_dirs_display()
+            );
+            eprintln!("Skipped {} hidden entries", stats.hidden);
+            eprintln!("Skipped {} empty files", stats.empty);
+        }
+        if wallpapers.is_empty() {
+            eprintln!("No wallpapers found in {}", cfg.wallpaper_dirs_display());
+            return Ok(());
+        }
+
+        if args.validate_images {
+            let entries = wallpapers
+                .iter()
+                .map(|w| (w.path.clone(), w.modified))
+                .collect();
+            let mut broken: Vec<PathBuf> = find_broken(entries).into_iter().collect();
+            broken.sort();
+            for path in &broken {
+                println!("{}", path.display());
+            }
+            return Ok(());
+        }
+
+        (wallpapers, None)
+    } else {
+        (Vec::new(), Some(spawn_scan(cfg.clone())))
+    };
+
+    let mut tui = TuiApp::new(wallpapers, scan_rx, &cfg)?;
+    // Colors are live while browsing, the TUI opens right away and shows the
+    // outcome. --print only copies picks, so it leaves the wallpaper alone.
+    if cfg.auto_apply_on_launch
+        && cfg.enable_history
+        && !args.print
+        && let Some(path) = last_applied()
+    {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let message = format!(" Reapplying {}… ", name);
+        let reapply_cfg = cfg.clone();
+        tui.run_in_background(message, move || reapply(&path, &reapply_cfg));
+    }
+    // Printed with --print-selection once the terminal is restored
+    let mut picked = None;
+    // Set when --print had nowhere but /tmp to write to
+    let mut print_fallback = false;
+    // Run TUI to select a wallpaper, None when the user quits
+    while let Some(selected_wallpaper) = tui.run().await? {
         if args.print {
-            if cfg.pywal {
+            if cfg.pywal && !tui.skip_colors() {
+                if find_in_path("wal").is_none() {
+                    tui.set_status(" wal is not installed or not on PATH ".into());
+                    continue;
+                }
                 Command::new("wal")
                     .args([
                         "-i",
                         selected_wallpaper.to_str().unwrap(),
                         "-n",
                         "--backend",
-                        "wal",
+                        &cfg.pywal_backend,
                     ])
+                    .args(tui.light().then_some("-l"))
                     .stdout(Stdio::null())
                     .stderr(Stdio::null())
                     .status()?;
             }
             // Save selected wallpaper to cache as current.<ext>
-            let cache_dir: PathBuf = dirs::cache_dir()
-                .unwrap_or_else(|| PathBuf::from("/tmp"))
-                .join("wallrs");
-            fs::create_dir_all(&cache_dir)?;
-
+            let cache_dir = print_dir(&cfg).unwrap_or_else(|| {
+                print_fallback = true;
+                PathBuf::from("/tmp/wallrs")
+            });
             let ext = selected_wallpaper
                 .extension()
                 .and_then(|e| e.to_str())
                 .unwrap_or("png");
             let cache_file = cache_dir.join(format!("current.{}", ext));
 
-            fs::copy(&selected_wallpaper, &cache_file)?;
-            Command::new("pkill").args(["-USR2", "waybar"]).status()?;
-
-            execute!(io::stdout(), LeaveAlternateScreen)?;
+            let copied = fs::create_dir_all(&cache_dir)
+                .and_then(|_| fs::copy(&selected_wallpaper, &cache_file));
+            if let Err(e) = copied {
+                tui.set_status(format!(" Can't write {}: {} ", cache_file.display(), e));
+                continue;
+            }
+            run_reload_command(&cfg);
 
-            std::process::exit(0);
+            picked = Some(selected_wallpaper);
+            break;
         } else {
-            // Apply wallpaper normally
-            apply_wallpaper(&selected_wallpaper, &cfg)?;
+            // Apply wallpaper normally, honoring the in-TUI color toggle
+            let mut apply_cfg = cfg.clone();
+            apply_cfg.pywal_light = tui.light();
+            apply_cfg.output = tui.output();
+            if tui.skip_colors() {
+                apply_cfg.pywal = false;
+                apply_cfg.hellwal = false;
+            }
+            // Stay in the TUI so a missing backend can be fixed or another pick made
+            if let Err(e) =
+                apply_wallpaper(&selected_wallpaper, apply_cfg.output.as_deref(), &apply_cfg)
+            {
+                tui.set_status(format!(" Failed to apply: {} ", e));
+                continue;
+            }
+            if args.print_selection {
+                picked = Some(selected_wallpaper);
+                break;
+            }
+        }
+    }
+
+    // The one teardown for every way out of the TUI
+    restore_terminal();
+    if print_fallback {
+        eprintln!(
+            "No cache directory (XDG_CACHE_HOME and HOME are unset), wrote to /tmp/wallrs. Set cache_dir in config.toml to choose one"
+        );
+    }
+    if args.print_selection
+        && let Some(path) = picked
+    {
+        print_selection(&path);
+    }
+    // One path per line for a slideshow script
+    for path in tui.queue_to_print().unwrap_or_default() {
+        print_selection(&path);
+    }
+    Ok(())
+}
+
+// Numbered prompt on the terminal, None when there is nothing to pick or the
+// answer is empty
+fn pick_dir() -> io::Result<Option<PathBuf>> {
+    let dirs: Vec<PathBuf> = load_recent_dirs()
+        .into_iter()
+        .filter(|dir| dir.is_dir())
+        .collect();
+    if dirs.is_empty() {
+        eprintln!("No recent wallpaper directories yet");
+        return Ok(None);
+    }
+
+    for (i, dir) in dirs.iter().enumerate() {
+        println!("{:>3}) {}", i + 1, dir.display());
+    }
+    loop {
+        print!("Wallpaper directory [1-{}]: ", dirs.len());
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer)? == 0 || answer.trim().is_empty() {
+            return Ok(None);
+        }
+        match answer.trim().parse::<usize>() {
+            Ok(n) if (1..=dirs.len()).contains(&n) => return Ok(Some(dirs[n - 1].clone())),
+            _ => eprintln!("Enter a number between 1 and {}", dirs.len()),
+        }
+    }
+}
+
+// Everything a bug report needs, each line stands on its own so a failing
+// probe doesn't hide the rest
+fn doctor(cfg: &Config) {
+    println!("wallrs {}", env!("CARGO_PKG_VERSION"));
+    println!(
+        "Session: {:?} (WAYLAND_DISPLAY {})",
+        cfg.session,
+        env::var("WAYLAND_DISPLAY").unwrap_or_else(|_| "unset".into())
+    );
+
+    match graphics_protocol(&cfg.image_protocol) {
+        Some((protocol, (width, height))) => {
+            println!(
+                "Graphics protocol: {} (image_protocol = {}, cell {}x{} px)",
+                protocol, cfg.image_protocol, width, height
+            );
+        }
+        None => println!("Graphics protocol: off, previews are text only"),
+    }
+
+    println!("Programs:");
+    for program in ["swww", "feh", "mpvpaper", "wal", "hellwal", "ffmpeg"] {
+        match find_in_path(program) {
+            Some(path) => println!("  {}: {}", program, path.display()),
+            None => println!("  {}: not found", program),
+        }
+    }
+
+    println!("Config:");
+    for file in ["config.toml", "keybindings.toml"] {
+        let path = config::config_dir().join(file);
+        let note = if path.is_file() { "" } else { " (missing)" };
+        println!("  {}{}", path.display(), note);
+    }
+
+    match print_dir(cfg) {
+        Some(dir) => println!("Cache dir (--print): {}", dir.display()),
+        None => println!("Cache dir (--print): unknown, falls back to /tmp/wallrs"),
+    }
+
+    println!("Wallpaper dirs:");
+    let options = ScanOptions::from_config(cfg);
+    for dir in &cfg.wallpaper_dirs {
+        match load_wallpapers(std::slice::from_ref(dir), &options) {
+            Ok((wallpapers, _)) => println!("  {}: {} wallpapers", dir.display(), wallpapers.len()),
+            Err(e) => println!("  {}: {}", dir.display(), e),
+        }
+    }
+}
+
+// Where --print writes current.<ext>, None when neither cache_dir nor the
+// XDG cache dir is known
+fn print_dir(cfg: &Config) -> Option<PathBuf> {
+    cfg.cache_dir
+        .clone()
+        .or_else(|| dirs::cache_dir().map(|dir| dir.join("wallrs")))
+}
+
+// Called once the alternate screen is gone so the path lands on the real stdout
+fn print_selection(path: &Path) {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    println!("{}", path.display());
+}
+
+// Picks from the saved list or a full scan, entries that are gone or are
+// videos without mpvpaper are left out. The pick goes to the top of history
+// unless enable_history is off.
+fn apply_random(cfg: &Config, source: RandomSource) -> Result<(), Box<dyn std::error::Error>> {
+    let saved = |list: SavedList| -> Vec<PathBuf> {
+        list.load()
+            .into_iter()
+            .filter(|p| p.is_file() && (cfg.mpvpaper || !is_mp4(p)))
+            .collect()
+    };
+    let candidates = match source {
+        RandomSource::Favorites => saved(SavedList::Favorites),
+        RandomSource::History => saved(SavedList::History),
+        RandomSource::Queue => saved(SavedList::Queue),
+        RandomSource::All => {
+            let (wallpapers, _) =
+                load_wallpapers(&cfg.wallpaper_dirs, &ScanOptions::from_config(cfg))?;
+            let blacklist = SavedList::Blacklist.load();
+            wallpapers
+                .into_iter()
+                .map(|w| w.path)
+                .filter(|p| !blacklist.contains(p))
+                .collect()
+        }
+    };
+
+    let Some(path) = candidates.choose(&mut rand::thread_rng()) else {
+        eprintln!("No wallpapers to pick from");
+        return Ok(());
+    };
+    apply_wallpaper(path, cfg.output.as_deref(), cfg)?;
+    if !cfg.enable_history {
+        return Ok(());
+    }
+
+    let mut history = SavedList::History.load();
+    history.retain(|p| p != path);
+    history.insert(0, path.clone());
+    SavedList::History.save(&history);
+    Ok(())
+}
+
+fn is_mp4(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("mp4"))
+}
+
+// The newest history entry, None when the history is empty or the file is
+// gone
+fn last_applied() -> Option<PathBuf> {
+    let path = SavedList::History.load().into_iter().next()?;
+    path.is_file().then_some(path)
+}
+
+// The status line reporting how applying `path` again went
+fn reapply(path: &Path, cfg: &Config) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    match apply_wallpaper(path, cfg.output.as_deref(), cfg) {
+        Ok(()) => format!(" Reapplied {} ", name),
+        Err(e) => format!(" Failed to reapply {}: {} ", name, e),
+    }
+}
+
+// Monitors missing from the current session are skipped, failures don't stop
+// the remaining ones. Color schemes are left as they are.
+fn restore_all(cfg: &Config) {
+    if !targets_outputs(cfg) {
+        eprintln!("Nothing to restore: the backend sets every monitor at once");
+        return;
+    }
+    let mut cfg = cfg.clone();
+    cfg.pywal = false;
+    cfg.hellwal = false;
+
+    let connected = connected_outputs(&cfg);
+    for (output, path) in load_outputs() {
+        if connected.as_ref().is_some_and(|c| !c.contains(&output)) {
+            eprintln!("Skipping {}: not connected", output);
+            continue;
+        }
+        if !path.is_file() {
+            eprintln!("Skipping {}: {} no longer exists", output, path.display());
+            continue;
+        }
+        if let Err(e) = apply_wallpaper(&path, Some(&output), &cfg) {
+            eprintln!("Failed to restore {}: {}", output, e);
         }
     }
 }
@@ -1,75 +1,366 @@
+use crate::input::{Action, Selection, switch_tab};
 use crate::tui::Tab;
-use crossterm::event::MouseEvent;
+use crate::wallpapers::Wallpaper;
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
 use ratatui::layout::Rect;
 use ratatui::widgets::ListState;
-use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthStr;
+
+// Where the last frame put things, clicks are tested against these
+#[derive(Default)]
+pub struct MouseAreas {
+    // Rows of the list entries, inside the border
+    pub list: Rect,
+    // Entry on each visible row of the list, None for a section header
+    pub rows: Vec<Option<usize>>,
+    // Column of the list scrollbar
+    pub scrollbar: Rect,
+    pub preview: Rect,
+    // Tab titles, inside the border
+    pub tabs: Rect,
+    // Grid cells and the entry each one shows, empty without the grid
+    pub cells: Vec<(usize, Rect)>,
+}
+
+impl MouseAreas {
+    // The list drawn in `list_area`: its entries inside the border, right of
+    // the scrollbar on the left edge
+    pub fn with_list(list_area: Rect, preview: Rect, tabs: Rect) -> Self {
+        Self {
+            list: Rect::new(
+                list_area.x + 2,
+                list_area.y + 1,
+                list_area.width.saturating_sub(3),
+                list_area.height.saturating_sub(2),
+            ),
+            rows: Vec::new(),
+            scrollbar: Rect::new(list_area.x, list_area.y, 1, list_area.height),
+            preview,
+            tabs,
+            cells: Vec::new(),
+        }
+    }
+}
 
 pub struct MouseInput<'a> {
     pub me: MouseEvent,
     pub selected: &'a mut usize,
     pub list_state: &'a mut ListState,
-    pub filtered: &'a [PathBuf],
-    pub list_area: &'a Rect,
-    pub tabs_area: &'a Rect,
+    pub filtered: &'a [Arc<Wallpaper>],
+    pub areas: &'a MouseAreas,
+    // Enabled tabs, in the order they are drawn
+    pub tabs: &'a [Tab],
     pub current_tab: &'a mut Tab,
+    pub multi_select: &'a mut bool,
+    pub selection: &'a mut Selection,
+    // Entry of the previous click in the list and when it happened
+    pub last_click: &'a mut Option<(usize, Instant)>,
+    pub double_click: Duration,
+}
+
+fn contains(area: &Rect, column: u16, row: u16) -> bool {
+    column >= area.x && column < area.x + area.width && row >= area.y && row < area.y + area.height
+}
+
+// Entry under a click in the list, headers have none
+fn list_hit(areas: &MouseAreas, column: u16, row: u16) -> Option<usize> {
+    if !contains(&areas.list, column, row) {
+        return None;
+    }
+    areas
+        .rows
+        .get((row - areas.list.y) as usize)
+        .copied()
+        .flatten()
+}
+
+// Entry under the pointer in the list or the grid
+pub fn entry_at(areas: &MouseAreas, column: u16, row: u16) -> Option<usize> {
+    list_hit(areas, column, row).or_else(|| {
+        areas
+            .cells
+            .iter()
+            .find(|(_, cell)| contains(cell, column, row))
+            .map(|(i, _)| *i)
+    })
+}
+
+// Presses count inside the scrollbar, a drag keeps following the pointer
+// above and below it
+fn on_scrollbar(area: &Rect, me: &MouseEvent) -> bool {
+    match me.kind {
+        MouseEventKind::Drag(_) => area.width > 0 && me.column == area.x,
+        _ => contains(area, me.column, me.row),
+    }
+}
+
+// Entry matching a row of the scrollbar, top and bottom reach the ends
+fn scrollbar_hit(area: &Rect, len: usize, row: u16) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    let last_row = area.height.saturating_sub(1).max(1) as usize;
+    let row = row.clamp(area.y, area.y + area.height.saturating_sub(1)) - area.y;
+    Some((row as usize * (len - 1) + last_row / 2) / last_row)
 }
 
-pub fn handle_mouse(input: &mut MouseInput) {
+// Tabs draws each title with a space on both sides and a divider between
+fn tab_hit(area: &Rect, titles: &[String], column: u16, row: u16) -> Option<usize> {
+    if !contains(area, column, row) {
+        return None;
+    }
+    let mut x = area.x as usize;
+    for (i, title) in titles.iter().enumerate() {
+        let end = x + title.width() + 2;
+        if (column as usize) < end {
+            return Some(i);
+        }
+        x = end + 1;
+    }
+    None
+}
+
+// A double-click selects the wallpaper like Enter
+pub fn handle_mouse(input: &mut MouseInput) -> Option<Action> {
     let MouseInput {
         me,
         selected,
         list_state,
         filtered,
-        list_area,
-        tabs_area,
+        areas,
+        tabs,
         current_tab,
+        multi_select,
+        selection,
+        last_click,
+        double_click,
     } = input;
 
     match me.kind {
+        // Pressing or dragging on the scrollbar jumps there
+        MouseEventKind::Down(MouseButton::Left) | MouseEventKind::Drag(MouseButton::Left)
+            if on_scrollbar(&areas.scrollbar, me) =>
+        {
+            if let Some(index) = scrollbar_hit(&areas.scrollbar, filtered.len(), me.row) {
+                **selected = index;
+                list_state.select(Some(**selected));
+            }
+            **last_click = None;
+        }
+
         // Click inside the list
         crossterm::event::MouseEventKind::Down(_) => {
-            // List selection
-            if me.column >= list_area.x
-                && me.column < list_area.x + list_area.width
-                && me.row >= list_area.y
-                && me.row < list_area.y + list_area.height
+            // List or grid selection
+            let index = entry_at(areas, me.column, me.row);
+            // Anything but a second click on the same entry starts over
+            let previous = last_click.take();
+            if let Some(index) = index
+                && index < filtered.len()
             {
-                let index = (me.row - list_area.y) as usize;
-                if index < filtered.len() {
-                    **selected = index;
-                    list_state.select(Some(**selected));
+                **selected = index;
+                list_state.select(Some(**selected));
+
+                if me.kind == MouseEventKind::Down(MouseButton::Left) {
+                    if previous.is_some_and(|(i, at)| i == index && at.elapsed() <= *double_click) {
+                        return Some(Action::Select);
+                    }
+                    **last_click = Some((index, Instant::now()));
                 }
             }
 
-            // Tab click
-            if me.row >= tabs_area.y && me.row < tabs_area.y + tabs_area.height {
-                let tab_width = tabs_area.width / 3;
-                let tab_index = ((me.column - tabs_area.x) / tab_width) as usize;
-                **current_tab = match tab_index {
-                    0 => Tab::Wallpapers,
-                    1 => Tab::History,
-                    2 => Tab::Favorites,
-                    _ => **current_tab,
-                };
-                **selected = 0;
-                list_state.select(Some(**selected));
+            // Tab click, switching like the keys do
+            let titles: Vec<String> = tabs.iter().map(|t| t.title()).collect();
+            if let Some(i) = tab_hit(&areas.tabs, &titles, me.column, me.row) {
+                return switch_tab(
+                    tabs[i],
+                    current_tab,
+                    selected,
+                    list_state,
+                    multi_select,
+                    selection,
+                );
             }
         }
 
-        // Scroll up/down
-        crossterm::event::MouseEventKind::ScrollUp => {
-            if **selected > 0 {
-                **selected -= 1;
-                list_state.select(Some(**selected));
-            }
-        }
-        crossterm::event::MouseEventKind::ScrollDown => {
-            if **selected < filtered.len().saturating_sub(1) {
-                **selected += 1;
-                list_state.select(Some(**selected));
+        // Scroll up/down over the list or the preview, the preview follows
+        // the highlighted entry so this flips through the images
+        MouseEventKind::ScrollUp | MouseEventKind::ScrollDown
+            if [&areas.list, &areas.scrollbar, &areas.preview]
+                .into_iter()
+                .chain(areas.cells.iter().map(|(_, cell)| cell))
+                .any(|area| contains(area, me.column, me.row)) =>
+        {
+            if me.kind == MouseEventKind::ScrollUp {
+                **selected = selected.saturating_sub(1);
+            } else {
+                **selected = (**selected + 1).min(filtered.len().saturating_sub(1));
             }
+            list_state.select(Some(**selected));
         }
 
         _ => {}
     }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::split_main;
+
+    const POSITIONS: [&str; 4] = ["left", "right", "top", "bottom"];
+
+    // A 100x30 main area below the tabs, the list scrolled down by `offset`
+    fn areas(position: &str, offset: usize) -> MouseAreas {
+        let (list, preview) = split_main(Rect::new(0, 3, 100, 30), position);
+        let mut areas = MouseAreas::with_list(list, preview, Rect::new(1, 1, 98, 1));
+        areas.rows = (offset..offset + areas.list.height as usize)
+            .map(Some)
+            .collect();
+        areas
+    }
+
+    #[test]
+    fn list_position_places_the_list() {
+        let main = Rect::new(0, 3, 100, 30);
+        let (left, _) = split_main(main, "left");
+        let (right, _) = split_main(main, "right");
+        let (top, _) = split_main(main, "top");
+        let (bottom, _) = split_main(main, "BOTTOM");
+        assert_eq!((left.x, left.y), (0, 3));
+        assert_eq!((right.x, right.y), (50, 3));
+        assert_eq!((top.x, top.y), (0, 3));
+        assert_eq!((bottom.x, bottom.y), (0, 18));
+        assert_eq!(split_main(main, "nonsense"), split_main(main, "left"));
+    }
+
+    #[test]
+    fn list_hit_follows_the_list_and_its_scroll() {
+        for position in POSITIONS {
+            let areas = areas(position, 7);
+            let list = areas.list;
+            assert_eq!(list_hit(&areas, list.x, list.y), Some(7), "{}", position);
+            assert_eq!(
+                list_hit(&areas, list.x + list.width - 1, list.y + 2),
+                Some(9),
+                "{}",
+                position
+            );
+            // The border and the scrollbar aren't entries
+            assert_eq!(list_hit(&areas, list.x, list.y - 1), None, "{}", position);
+            assert_eq!(
+                list_hit(&areas, areas.scrollbar.x, list.y),
+                None,
+                "{}",
+                position
+            );
+            // Neither is the preview
+            let preview = areas.preview;
+            assert_eq!(
+                entry_at(&areas, preview.x + 1, preview.y + 1),
+                None,
+                "{}",
+                position
+            );
+        }
+    }
+
+    #[test]
+    fn list_hit_skips_headers() {
+        let mut areas = areas("left", 0);
+        areas.rows[0] = None;
+        let list = areas.list;
+        assert_eq!(list_hit(&areas, list.x, list.y), None);
+        assert_eq!(list_hit(&areas, list.x, list.y + 1), Some(1));
+    }
+
+    #[test]
+    fn scrollbar_hit_reaches_both_ends() {
+        for position in POSITIONS {
+            let bar = areas(position, 0).scrollbar;
+            let bottom = bar.y + bar.height - 1;
+            assert_eq!(scrollbar_hit(&bar, 50, bar.y), Some(0), "{}", position);
+            assert_eq!(scrollbar_hit(&bar, 50, bottom), Some(49), "{}", position);
+            // Dragged past the ends
+            assert_eq!(scrollbar_hit(&bar, 50, 0), Some(0), "{}", position);
+            assert_eq!(scrollbar_hit(&bar, 50, 200), Some(49), "{}", position);
+            assert_eq!(scrollbar_hit(&bar, 0, bar.y), None, "{}", position);
+        }
+    }
+
+    // Left click at `column` on the tab row with only History and Favorites
+    // shown, starting on History with the third entry picked and highlighted
+    fn click_tab(column: u16) -> (Option<Action>, Tab, usize, bool) {
+        use crossterm::event::KeyModifiers;
+        use std::path::{Path, PathBuf};
+
+        let tabs = [Tab::History, Tab::Favorites];
+        let filtered: Vec<Arc<Wallpaper>> = (0..5)
+            .map(|i| Arc::new(Wallpaper::from_path(PathBuf::from(format!("{}.png", i)))))
+            .collect();
+        let areas = areas("left", 0);
+        let mut selected = 2;
+        let mut list_state = ListState::default();
+        let mut current_tab = Tab::History;
+        let mut multi_select = true;
+        let mut selection = Selection::default();
+        selection.toggle(Path::new("2.png"));
+        let mut input = MouseInput {
+            me: MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column,
+                row: areas.tabs.y,
+                modifiers: KeyModifiers::NONE,
+            },
+            selected: &mut selected,
+            list_state: &mut list_state,
+            filtered: &filtered,
+            areas: &areas,
+            tabs: &tabs,
+            current_tab: &mut current_tab,
+            multi_select: &mut multi_select,
+            selection: &mut selection,
+            last_click: &mut None,
+            double_click: Duration::from_millis(400),
+        };
+        let action = handle_mouse(&mut input);
+        let picked = multi_select && selection.contains(Path::new("2.png"));
+        (action, current_tab, selected, picked)
+    }
+
+    #[test]
+    fn tab_click_maps_to_the_enabled_tabs() {
+        // " History " takes columns 1-9, the divider 10, " Favorites " 11-21
+        assert_eq!(
+            click_tab(15),
+            (Some(Action::TabSwitched), Tab::Favorites, 0, false)
+        );
+        assert_eq!(
+            click_tab(21),
+            (Some(Action::TabSwitched), Tab::Favorites, 0, false)
+        );
+        // Past the last title
+        assert_eq!(click_tab(22), (None, Tab::History, 2, true));
+    }
+
+    #[test]
+    fn tab_click_on_the_open_tab_keeps_the_selection() {
+        assert_eq!(click_tab(1), (None, Tab::History, 2, true));
+        assert_eq!(click_tab(9), (None, Tab::History, 2, true));
+    }
+
+    #[test]
+    fn tab_hit_counts_padding_and_dividers() {
+        let area = Rect::new(1, 1, 98, 1);
+        let titles = vec!["Wallpapers".to_string(), "History".to_string()];
+        // " Wallpapers " takes columns 1-12, the divider 13, " History " 14-22
+        assert_eq!(tab_hit(&area, &titles, 1, 1), Some(0));
+        assert_eq!(tab_hit(&area, &titles, 12, 1), Some(0));
+        assert_eq!(tab_hit(&area, &titles, 14, 1), Some(1));
+        assert_eq!(tab_hit(&area, &titles, 22, 1), Some(1));
+        assert_eq!(tab_hit(&area, &titles, 23, 1), None);
+        assert_eq!(tab_hit(&area, &titles, 5, 2), None);
+    }
 }
@@ -0,0 +1,753 @@
+use crate::persistence::ListEntry;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use tempfile::NamedTempFile;
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+use trash::os_limited;
+
+// ------------------------
+// Reversible file operations
+// ------------------------
+
+// Old and new path of a file
+pub type Move = (PathBuf, PathBuf);
+
+// A file an operation left alone and why
+pub type Failure = (PathBuf, String);
+
+// What an undo changed on disk, so the lists can follow
+pub enum Undone {
+    Renamed(Vec<Move>),
+    // Restored files and the list entries they had, see Delete::remember
+    Restored(Vec<PathBuf>, Vec<(String, usize, ListEntry)>),
+}
+
+pub trait FileOp {
+    // Shown in the status after undoing, e.g. "rename of foo.png"
+    fn describe(&self) -> String;
+    fn undo(&self) -> io::Result<Undone>;
+}
+
+pub struct Rename {
+    from: PathBuf,
+    to: PathBuf,
+}
+
+// Longest file name most filesystems accept, in bytes
+const NAME_MAX: usize = 255;
+
+// A new name must stay a plain file name in the same folder
+fn check_name(name: &str) -> io::Result<()> {
+    let reason = if name.trim().is_empty() {
+        "Name cannot be empty"
+    } else if name.contains(['/', '\\']) {
+        "Name cannot contain / or \\"
+    } else if name == "." || name == ".." {
+        "Name cannot be . or .."
+    } else if name.chars().any(char::is_control) {
+        "Name cannot contain control characters"
+    } else if name.len() > NAME_MAX {
+        "Name is too long"
+    } else {
+        return Ok(());
+    };
+    Err(io::Error::new(io::ErrorKind::InvalidInput, reason))
+}
+
+// Where `new_name` puts the file, the extension is kept when it has none
+fn target_path(old_path: &Path, new_name: &str) -> io::Result<PathBuf> {
+    check_name(new_name)?;
+    let parent_dir = old_path
+        .parent()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid file path"))?;
+
+    let mut new_path = parent_dir.join(new_name);
+
+    // Add file extension if missing
+    if let Some(ext) = old_path.extension()
+        && new_path.extension().is_none()
+    {
+        new_path.set_extension(ext);
+    }
+    Ok(new_path)
+}
+
+// Renames within the same folder, the extension is kept when `new_name` has none
+pub fn rename(old_path: &Path, new_name: &str) -> io::Result<Rename> {
+    move_to(old_path, target_path(old_path, new_name)?)
+}
+
+// Refuses to replace another file, a change of case only is fine
+fn move_to(old_path: &Path, new_path: PathBuf) -> io::Result<Rename> {
+    if new_path == old_path {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "That is already its name",
+        ));
+    }
+
+    // Check if new name already exists
+    if new_path.exists() && !case_variant(old_path, &new_path) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "File with that name already exists",
+        ));
+    }
+
+    fs::rename(old_path, &new_path)?;
+
+    Ok(Rename {
+        from: old_path.to_path_buf(),
+        to: new_path,
+    })
+}
+
+impl Rename {
+    pub fn new_path(&self) -> &Path {
+        &self.to
+    }
+}
+
+impl FileOp for Rename {
+    fn describe(&self) -> String {
+        format!("rename of {}", file_name(&self.to))
+    }
+
+    fn undo(&self) -> io::Result<Undone> {
+        if self.from.exists() && !case_variant(&self.to, &self.from) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} is taken now", file_name(&self.from)),
+            ));
+        }
+        fs::rename(&self.to, &self.from)?;
+
+        Ok(Undone::Renamed(vec![(self.to.clone(), self.from.clone())]))
+    }
+}
+
+// Name of the `index`th file (from 0) of a batch rename. `{n}` is the
+// counter from 1, `{n:03}` pads it with zeros to three digits, `{name}` and
+// `{ext}` are the original stem and extension.
+pub fn expand_pattern(pattern: &str, index: usize, path: &Path) -> io::Result<String> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let mut name = String::new();
+    let mut rest = pattern;
+    while let Some(open) = rest.find('{') {
+        name.push_str(&rest[..open]);
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| invalid("Unclosed { in pattern".into()))?;
+        let placeholder = &rest[open + 1..open + close];
+        match placeholder {
+            "n" => name.push_str(&(index + 1).to_string()),
+            "name" => name.push_str(&path.file_stem().unwrap_or_default().to_string_lossy()),
+            "ext" => name.push_str(&path.extension().unwrap_or_default().to_string_lossy()),
+            _ => {
+                let width = placeholder
+                    .strip_prefix("n:")
+                    .and_then(|width| width.parse::<usize>().ok())
+                    .ok_or_else(|| invalid(format!("Unknown placeholder {{{}}}", placeholder)))?;
+                name.push_str(&format!("{:0width$}", index + 1, width = width));
+            }
+        }
+        rest = &rest[open + close + 1..];
+    }
+    name.push_str(rest);
+    Ok(name)
+}
+
+pub struct BatchRename {
+    renames: Vec<Rename>,
+}
+
+// Renames `paths` in order after a pattern, see `expand_pattern`. Files the
+// pattern leaves unchanged are skipped. A pattern that can't tell the files
+// apart fails as a whole, any other failure is reported per file and the
+// rest are still renamed.
+pub fn batch_rename(paths: &[PathBuf], pattern: &str) -> io::Result<(BatchRename, Vec<Failure>)> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    if let [first, _, ..] = paths
+        && expand_pattern(pattern, 0, first)? == expand_pattern(pattern, 1, first)?
+    {
+        return Err(invalid("Number the files with {n} or {n:03}".into()));
+    }
+
+    let mut targets = Vec::new();
+    let mut failed = Vec::new();
+    let mut seen = HashSet::new();
+    for (index, path) in paths.iter().enumerate() {
+        let name = expand_pattern(pattern, index, path)?;
+        match target_path(path, &name) {
+            Ok(target) => {
+                if !seen.insert(target.clone()) {
+                    return Err(invalid(format!(
+                        "More than one file would be named {}",
+                        file_name(&target)
+                    )));
+                }
+                targets.push((path, target));
+            }
+            Err(e) => failed.push((path.clone(), format!("{}: {}", name, e))),
+        }
+    }
+
+    let moves: Vec<Move> = targets
+        .into_iter()
+        .filter(|(path, target)| target != *path)
+        .map(|(path, target)| (path.clone(), target))
+        .collect();
+    let (moved, move_failed) = move_all(&moves);
+    failed.extend(move_failed);
+    let renames = moved
+        .into_iter()
+        .map(|(from, to)| Rename { from, to })
+        .collect();
+    Ok((BatchRename { renames }, failed))
+}
+
+// Moves every file aside to a temporary name before giving it its new one,
+// so a file may take the current name of another one that moves too.
+// Returns what was moved and the files that kept their name, with why.
+fn move_all(moves: &[Move]) -> (Vec<Move>, Vec<Failure>) {
+    let moving: HashSet<&Path> = moves.iter().map(|(from, _)| from.as_path()).collect();
+    let mut failed = Vec::new();
+
+    let mut parked = Vec::new();
+    for (index, (from, to)) in moves.iter().enumerate() {
+        if to.exists() && !moving.contains(to.as_path()) && !case_variant(from, to) {
+            failed.push((from.clone(), "File with that name already exists".into()));
+            continue;
+        }
+        let temp = from.with_file_name(format!(".wallrs-rename-{}-{}", std::process::id(), index));
+        match fs::rename(from, &temp) {
+            Ok(()) => parked.push((from, temp, to)),
+            Err(e) => failed.push((from.clone(), e.to_string())),
+        }
+    }
+
+    let mut moved = Vec::new();
+    for (from, temp, to) in parked {
+        // Still there when the file holding it couldn't be moved aside
+        let result = if to.exists() {
+            Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "File with that name already exists",
+            ))
+        } else {
+            fs::rename(&temp, to)
+        };
+        match result {
+            Ok(()) => moved.push((from.clone(), to.clone())),
+            Err(e) if from.exists() => {
+                failed.push((from.clone(), format!("{}, left as {}", e, file_name(&temp))))
+            }
+            Err(e) => {
+                let _ = fs::rename(&temp, from);
+                failed.push((from.clone(), e.to_string()));
+            }
+        }
+    }
+    (moved, failed)
+}
+
+impl BatchRename {
+    // Old and new path of each renamed file
+    pub fn renamed(&self) -> Vec<Move> {
+        self.renames
+            .iter()
+            .map(|op| (op.from.clone(), op.to.clone()))
+            .collect()
+    }
+}
+
+impl FileOp for BatchRename {
+    fn describe(&self) -> String {
+        format!("rename of {} wallpapers", self.renames.len())
+    }
+
+    // All or nothing, names given back are taken again on failure
+    fn undo(&self) -> io::Result<Undone> {
+        let back: Vec<Move> = self
+            .renames
+            .iter()
+            .map(|op| (op.to.clone(), op.from.clone()))
+            .collect();
+        let (undone, failed) = move_all(&back);
+        if let Some((path, e)) = failed.first() {
+            let again: Vec<Move> = undone.into_iter().map(|(from, to)| (to, from)).collect();
+            move_all(&again);
+            return Err(io::Error::other(format!("{}: {}", file_name(path), e)));
+        }
+        Ok(Undone::Renamed(undone))
+    }
+}
+
+// The trash can be listed and restored from on Windows and freedesktop
+// systems, deletes elsewhere can't be undone
+const CAN_RESTORE: bool = cfg!(any(
+    windows,
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+));
+
+pub struct Delete {
+    // Files moved to the trash, their trash entries are only looked up when
+    // undoing
+    paths: Vec<PathBuf>,
+    // List name, position and entry of each file in history, favorites and
+    // the queue, in the order they were taken out
+    lists: Vec<(String, usize, ListEntry)>,
+}
+
+// Moves files to the trash, failures are reported per file
+pub fn delete(paths: &[PathBuf]) -> (Delete, Vec<Failure>) {
+    let mut deleted = Vec::new();
+    let mut failed = Vec::new();
+    for path in paths {
+        match trash::delete(path) {
+            Ok(()) => deleted.push(path.clone()),
+            Err(e) => failed.push((path.clone(), e.to_string())),
+        }
+    }
+
+    (
+        Delete {
+            paths: deleted,
+            lists: Vec::new(),
+        },
+        failed,
+    )
+}
+
+impl Delete {
+    pub fn can_undo(&self) -> bool {
+        CAN_RESTORE && !self.paths.is_empty()
+    }
+
+    // Entries the deleted files had in lists, handed back on undo
+    pub fn remember(&mut self, lists: Vec<(String, usize, ListEntry)>) {
+        self.lists = lists;
+    }
+}
+
+// Puts the newest trash entry of each path back
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+fn restore(paths: &[PathBuf]) -> io::Result<()> {
+    let items = os_limited::list().map_err(io::Error::other)?;
+    let mut restore = Vec::new();
+    for path in paths {
+        let newest = items
+            .iter()
+            .filter(|item| item.original_path() == *path)
+            .max_by_key(|item| item.time_deleted)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} is no longer in the trash", file_name(path)),
+                )
+            })?;
+        restore.push(newest.clone());
+    }
+    os_limited::restore_all(restore).map_err(io::Error::other)
+}
+
+#[cfg(not(any(
+    windows,
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+fn restore(_paths: &[PathBuf]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the trash can't be restored from on this system",
+    ))
+}
+
+impl FileOp for Delete {
+    fn describe(&self) -> String {
+        match self.paths.as_slice() {
+            [path] => format!("delete of {}", file_name(path)),
+            paths => format!("delete of {} files", paths.len()),
+        }
+    }
+
+    fn undo(&self) -> io::Result<Undone> {
+        if let Some(taken) = self.paths.iter().find(|p| p.exists()) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} is taken now", file_name(taken)),
+            ));
+        }
+        restore(&self.paths)?;
+
+        Ok(Undone::Restored(self.paths.clone(), self.lists.clone()))
+    }
+}
+
+// ------------------------
+// Image edits
+// ------------------------
+
+// Extensions `convert` accepts
+pub const CONVERT_FORMATS: &[&str] = &["png", "jpg", "jpeg", "webp", "bmp", "tiff", "gif"];
+
+// High enough that rewriting a JPEG once doesn't visibly degrade it
+const JPEG_QUALITY: u8 = 95;
+
+fn image_error(e: image::ImageError) -> io::Error {
+    io::Error::other(e)
+}
+
+// Videos and unknown extensions can't be edited
+fn image_format(path: &Path) -> io::Result<ImageFormat> {
+    ImageFormat::from_path(path)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Only images can be edited"))
+}
+
+// The EXIF orientation goes into the pixels. Rewritten files carry no EXIF,
+// so it is never applied twice.
+pub fn load_upright(path: &Path) -> io::Result<DynamicImage> {
+    let mut decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()
+        .map_err(image_error)?;
+    let orientation = decoder.orientation().map_err(image_error)?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(image_error)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+// Encodes into a temporary file beside `target`, which the caller moves into
+// place, so a failed write never leaves a truncated image behind
+pub fn encode(
+    image: &DynamicImage,
+    target: &Path,
+    format: ImageFormat,
+) -> io::Result<NamedTempFile> {
+    let dir = target
+        .parent()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid file path"))?;
+    let mut file = NamedTempFile::new_in(dir)?;
+    let mut writer = io::BufWriter::new(file.as_file_mut());
+    match format {
+        // JPEG has no alpha channel
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut writer, JPEG_QUALITY)),
+        _ => image.write_to(&mut writer, format),
+    }
+    .map_err(image_error)?;
+    writer.flush()?;
+    drop(writer);
+    Ok(file)
+}
+
+// Turns the image a quarter clockwise or counter-clockwise, rewriting it in
+// its own format
+pub fn rotate(path: &Path, clockwise: bool) -> io::Result<()> {
+    let format = image_format(path)?;
+    let image = load_upright(path)?;
+    let image = if clockwise {
+        image.rotate90()
+    } else {
+        image.rotate270()
+    };
+
+    let file = encode(&image, path, format)?;
+    fs::set_permissions(file.path(), fs::metadata(path)?.permissions())?;
+    file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+// Writes the image as `extension` under the same name and moves the original
+// to the trash. Returns the new path.
+pub fn convert(path: &Path, extension: &str) -> io::Result<PathBuf> {
+    let extension = extension.trim().trim_start_matches('.').to_lowercase();
+    if !CONVERT_FORMATS.contains(&extension.as_str()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Can't write {} files", extension),
+        ));
+    }
+    let format = ImageFormat::from_extension(&extension)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Unknown format"))?;
+    if image_format(path)? == format {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("It already is a {} file", extension),
+        ));
+    }
+    let target = path.with_extension(&extension);
+    if target.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", file_name(&target)),
+        ));
+    }
+
+    let image = load_upright(path)?;
+    let file = encode(&image, &target, format)?;
+    fs::set_permissions(file.path(), fs::metadata(path)?.permissions())?;
+    file.persist_noclobber(&target).map_err(|e| e.error)?;
+
+    // Only one of the two files stays
+    if let Err(e) = trash::delete(path) {
+        let _ = fs::remove_file(&target);
+        return Err(io::Error::other(e));
+    }
+    Ok(target)
+}
+
+// On case-insensitive filesystems `foo.PNG` and `foo.png` are the same file,
+// so renaming between them is a case change and not a clash
+fn case_variant(a: &Path, b: &Path) -> bool {
+    let a_name = a.to_string_lossy().to_lowercase();
+    let b_name = b.to_string_lossy().to_lowercase();
+    a_name == b_name
+        && matches!(
+            (fs::canonicalize(a), fs::canonicalize(b)),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(dir: &Path, name: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, b"x").unwrap();
+        path
+    }
+
+    #[test]
+    fn check_name_rejects_empty_names() {
+        for name in ["", " ", "\t  "] {
+            assert_eq!(
+                check_name(name).unwrap_err().kind(),
+                io::ErrorKind::InvalidInput
+            );
+        }
+    }
+
+    #[test]
+    fn check_name_rejects_separators_and_dot_names() {
+        for name in ["a/b", "../up", "a\\b", "/abs", ".", ".."] {
+            assert!(check_name(name).is_err(), "{}", name);
+        }
+        assert!(check_name("new\nline").is_err());
+        assert!(check_name(&"a".repeat(NAME_MAX + 1)).is_err());
+    }
+
+    #[test]
+    fn check_name_accepts_plain_names() {
+        for name in ["sunset", "sunset.png", "..hidden", "a b", "ünïcode", "x.."] {
+            assert!(check_name(name).is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn rename_refuses_to_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = touch(dir.path(), "a.png");
+        touch(dir.path(), "b.png");
+        let taken = rename(&a, "b").err().unwrap();
+        assert_eq!(taken.kind(), io::ErrorKind::AlreadyExists);
+        assert!(a.exists());
+        let same = rename(&a, "a").err().unwrap();
+        assert_eq!(same.kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn rename_keeps_the_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = touch(dir.path(), "a.png");
+        let op = rename(&a, "c").unwrap();
+        assert_eq!(op.new_path(), dir.path().join("c.png"));
+        assert!(op.new_path().exists());
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn batch_rename_needs_a_counter_for_several_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = vec![touch(dir.path(), "a.png"), touch(dir.path(), "b.png")];
+        for pattern in ["wall", "{name}-x", "{ext}"] {
+            let e = batch_rename(&paths, pattern).err().unwrap();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput, "{}", pattern);
+        }
+        assert_eq!(names(dir.path()), ["a.png", "b.png"]);
+
+        // One file doesn't need numbering
+        let (op, failed) = batch_rename(&paths[..1], "wall").unwrap();
+        assert!(failed.is_empty());
+        assert_eq!(op.renamed().len(), 1);
+        assert_eq!(names(dir.path()), ["b.png", "wall.png"]);
+    }
+
+    #[test]
+    fn batch_rename_reports_failures_per_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = vec![
+            touch(dir.path(), "a.png"),
+            touch(dir.path(), "b.png"),
+            touch(dir.path(), "c.png"),
+        ];
+        // Taken by a file outside the batch
+        touch(dir.path(), "w2.png");
+
+        let (op, failed) = batch_rename(&paths, "w{n}").unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, paths[1]);
+        assert_eq!(op.renamed().len(), 2);
+        assert_eq!(names(dir.path()), ["b.png", "w1.png", "w2.png", "w3.png"]);
+
+        op.undo().unwrap();
+        assert_eq!(names(dir.path()), ["a.png", "b.png", "c.png", "w2.png"]);
+    }
+
+    #[test]
+    fn expand_pattern_fills_the_placeholders() {
+        let path = Path::new("/w/sunset.png");
+        assert_eq!(expand_pattern("wall-{n}", 0, path).unwrap(), "wall-1");
+        assert_eq!(expand_pattern("{n:03}", 11, path).unwrap(), "012");
+        // Too wide a counter isn't cut
+        assert_eq!(expand_pattern("{n:2}", 122, path).unwrap(), "123");
+        assert_eq!(
+            expand_pattern("{name}_{n}.{ext}", 1, path).unwrap(),
+            "sunset_2.png"
+        );
+        assert_eq!(expand_pattern("plain", 4, path).unwrap(), "plain");
+        // No extension expands to nothing
+        assert_eq!(
+            expand_pattern("{name}{ext}", 0, Path::new("README")).unwrap(),
+            "README"
+        );
+    }
+
+    #[test]
+    fn expand_pattern_rejects_bad_placeholders() {
+        let path = Path::new("a.png");
+        for pattern in ["{n", "a{", "{x}", "{n:}", "{n:-1}", "{N}"] {
+            let e = expand_pattern(pattern, 0, path).err().unwrap();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput, "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn batch_rename_swaps_and_renumbers() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, contents) in [("w1.png", "one"), ("w2.png", "two"), ("w3.png", "three")] {
+            fs::write(dir.path().join(name), contents).unwrap();
+        }
+        let contents = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+
+        // Reversed: w3 becomes w1, w2 keeps its name, w1 becomes w3
+        let order: Vec<PathBuf> = ["w3.png", "w2.png", "w1.png"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        let (op, failed) = batch_rename(&order, "w{n}").unwrap();
+        assert!(failed.is_empty(), "{:?}", failed);
+        assert_eq!(op.renamed().len(), 2);
+        assert_eq!(names(dir.path()), ["w1.png", "w2.png", "w3.png"]);
+        assert_eq!(
+            [contents("w1.png"), contents("w2.png"), contents("w3.png")],
+            ["three", "two", "one"]
+        );
+
+        // Undoing swaps them back the same way
+        op.undo().unwrap();
+        assert_eq!(names(dir.path()), ["w1.png", "w2.png", "w3.png"]);
+        assert_eq!(contents("w1.png"), "one");
+        assert_eq!(contents("w3.png"), "three");
+    }
+
+    #[test]
+    fn batch_rename_shifts_onto_taken_names() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["w1.png", "w2.png", "w3.png"] {
+            fs::write(dir.path().join(name), name).unwrap();
+        }
+
+        // Each file takes the name of the next one, the last wraps around
+        let shifted: Vec<PathBuf> = ["w3.png", "w1.png", "w2.png"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        let (op, failed) = batch_rename(&shifted, "w{n}").unwrap();
+        assert!(failed.is_empty(), "{:?}", failed);
+        assert_eq!(op.renamed().len(), 3);
+        assert_eq!(names(dir.path()), ["w1.png", "w2.png", "w3.png"]);
+        for (name, was) in [
+            ("w1.png", "w3.png"),
+            ("w2.png", "w1.png"),
+            ("w3.png", "w2.png"),
+        ] {
+            assert_eq!(fs::read_to_string(dir.path().join(name)).unwrap(), was);
+        }
+    }
+
+    #[test]
+    fn case_variant_needs_the_same_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let lower = touch(dir.path(), "a.png");
+        let upper = dir.path().join("a.PNG");
+        assert!(case_variant(&lower, &lower));
+        // A different name that doesn't exist, or another file, isn't one
+        assert!(!case_variant(&lower, &dir.path().join("b.png")));
+        if !upper.exists() {
+            assert!(!case_variant(&lower, &upper));
+            touch(dir.path(), "a.PNG");
+            assert!(!case_variant(&lower, &upper));
+        } else {
+            // Case-insensitive filesystem
+            assert!(case_variant(&lower, &upper));
+        }
+    }
+}
@@ -1,25 +1,615 @@
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use toml::{Table, Value};
 
 // ------------------------
 // Persistence helpers
 // ------------------------
-pub fn load_list(name: &str) -> Vec<PathBuf> {
-    let path = dirs::home_dir().unwrap().join(".config/wallrs").join(name);
-    if let Ok(data) = fs::read_to_string(path) {
-        data.lines().map(PathBuf::from).collect()
+
+#[derive(Debug, Clone)]
+pub struct ListEntry {
+    pub path: PathBuf,
+    // Seconds since the Unix epoch
+    pub added_at: u64,
+}
+
+fn data_dir() -> PathBuf {
+    dirs::home_dir().unwrap_or_default().join(".config/wallrs")
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn load_entries(name: &str) -> Vec<ListEntry> {
+    load_entries_in(&data_dir(), name)
+}
+
+fn load_entries_in(dir: &Path, name: &str) -> Vec<ListEntry> {
+    let path = dir.join(format!("{}.toml", name));
+    if let Ok(data) = fs::read_to_string(&path) {
+        let Ok(table) = data.parse::<Table>() else {
+            return Vec::new();
+        };
+        return table
+            .get("entries")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|item| {
+                        let path = item.get("path")?.as_str()?;
+                        let added_at = item
+                            .get("added_at")
+                            .and_then(|v| v.as_integer())
+                            .unwrap_or(0);
+                        Some(ListEntry {
+                            path: PathBuf::from(path),
+                            added_at: added_at.max(0) as u64,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+    }
+
+    migrate_legacy(dir, name)
+}
+
+// One-time migration from the old newline-separated `<name>.txt` format. The
+// old file is kept as `<name>.txt.bak` once the list is saved, an existing
+// `<name>.toml` is never replaced.
+fn migrate_legacy(dir: &Path, name: &str) -> Vec<ListEntry> {
+    let legacy = dir.join(format!("{}.txt", name));
+    if dir.join(format!("{}.toml", name)).exists() {
+        return Vec::new();
+    }
+    let Ok(data) = fs::read_to_string(&legacy) else {
+        return Vec::new();
+    };
+
+    let added_at = fs::metadata(&legacy)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_else(unix_now);
+
+    let entries: Vec<ListEntry> = data
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(|l| ListEntry {
+            path: PathBuf::from(l),
+            added_at,
+        })
+        .collect();
+
+    if write_entries(dir, name, &entries).is_ok() {
+        let _ = fs::rename(&legacy, legacy.with_extension("txt.bak"));
+    }
+    entries
+}
+
+pub fn save_entries(name: &str, entries: &[ListEntry]) {
+    let _ = write_entries(&data_dir(), name, entries);
+}
+
+fn write_entries(dir: &Path, name: &str, entries: &[ListEntry]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let records: Vec<Value> = entries
+        .iter()
+        .map(|e| {
+            let mut record = Table::new();
+            record.insert(
+                "path".into(),
+                Value::String(e.path.to_string_lossy().into_owned()),
+            );
+            record.insert("added_at".into(), Value::Integer(e.added_at as i64));
+            Value::Table(record)
+        })
+        .collect();
+
+    let mut table = Table::new();
+    table.insert("entries".into(), Value::Array(records));
+
+    let data = toml::to_string(&table).map_err(io::Error::other)?;
+    fs::write(dir.join(format!("{}.toml", name)), data)
+}
+
+// List holding the favorites of a collection, the plain favorites for ""
+pub fn collection_list(collection: &str) -> String {
+    if collection.is_empty() {
+        "favorites".into()
     } else {
-        Vec::new()
+        format!("favorites_{}", collection)
+    }
+}
+
+// Named collections that have been saved, sorted
+pub fn list_collections() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(data_dir()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let stem = file_name
+                .strip_suffix(".toml")
+                .or_else(|| file_name.strip_suffix(".txt"))?;
+            stem.strip_prefix("favorites_")
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+        })
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+// Lists of the plain favorites and of every named collection
+pub fn favorites_lists() -> Vec<String> {
+    std::iter::once(String::new())
+        .chain(list_collections())
+        .map(|collection| collection_list(&collection))
+        .collect()
+}
+
+// Old and new path of each renamed file, `added_at` stays
+pub fn rename_in_list(name: &str, renamed: &[(PathBuf, PathBuf)]) {
+    let mut entries = load_entries(name);
+    let mut changed = false;
+    for entry in &mut entries {
+        if let Some((_, new_path)) = renamed.iter().find(|(old_path, _)| *old_path == entry.path) {
+            entry.path = new_path.clone();
+            changed = true;
+        }
+    }
+    if changed {
+        save_entries(name, &entries);
     }
 }
 
+// Where the entry was and the entry itself, for insert_into_list
+pub fn remove_from_list(name: &str, path: &Path) -> Option<(usize, ListEntry)> {
+    let mut entries = load_entries(name);
+    let index = entries.iter().position(|e| e.path == path)?;
+    let entry = entries.remove(index);
+    save_entries(name, &entries);
+    Some((index, entry))
+}
+
+pub fn insert_into_list(name: &str, index: usize, entry: ListEntry) {
+    let mut entries = load_entries(name);
+    entries.retain(|e| e.path != entry.path);
+    entries.insert(index.min(entries.len()), entry);
+    save_entries(name, &entries);
+}
+
+pub fn load_list(name: &str) -> Vec<PathBuf> {
+    load_entries(name).into_iter().map(|e| e.path).collect()
+}
+
+// Keeps the original `added_at` of paths already in the list
 pub fn save_list(name: &str, list: &[PathBuf]) {
-    let path = dirs::home_dir().unwrap().join(".config/wallrs").join(name);
-    let _ = fs::write(
-        path,
-        list.iter()
-            .map(|p| p.to_string_lossy())
-            .collect::<Vec<_>>()
-            .join("\n"),
+    let known: HashMap<PathBuf, u64> = load_entries(name)
+        .into_iter()
+        .map(|e| (e.path, e.added_at))
+        .collect();
+    let now = unix_now();
+
+    let entries: Vec<ListEntry> = list
+        .iter()
+        .map(|p| ListEntry {
+            path: p.clone(),
+            added_at: known.get(p).copied().unwrap_or(now),
+        })
+        .collect();
+
+    save_entries(name, &entries);
+}
+
+/// The lists wallrs keeps between runs, newest entry first.
+#[derive(Debug, Clone, Copy)]
+pub enum SavedList {
+    History,
+    /// The collection last open in the Favorites tab
+    Favorites,
+    Queue,
+    Blacklist,
+}
+
+impl SavedList {
+    fn name(self) -> String {
+        match self {
+            SavedList::History => "history".into(),
+            SavedList::Favorites => collection_list(&load_state("collection").unwrap_or_default()),
+            SavedList::Queue => "queue".into(),
+            SavedList::Blacklist => "blacklist".into(),
+        }
+    }
+
+    pub fn load(self) -> Vec<PathBuf> {
+        load_list(&self.name())
+    }
+
+    pub fn save(self, list: &[PathBuf]) {
+        save_list(&self.name(), list)
+    }
+}
+
+// ------------------------
+// UI state
+// ------------------------
+
+// Small values remembered between runs, kept in state.toml
+pub fn load_state(key: &str) -> Option<String> {
+    let data = fs::read_to_string(data_dir().join("state.toml")).ok()?;
+    let table = data.parse::<Table>().ok()?;
+    table.get(key)?.as_str().map(String::from)
+}
+
+pub fn save_state(key: &str, value: &str) {
+    let dir = data_dir();
+    let _ = fs::create_dir_all(&dir);
+    let path = dir.join("state.toml");
+
+    let mut table = fs::read_to_string(&path)
+        .ok()
+        .and_then(|data| data.parse::<Table>().ok())
+        .unwrap_or_default();
+    table.insert(key.into(), Value::String(value.into()));
+
+    if let Ok(data) = toml::to_string(&table) {
+        let _ = fs::write(path, data);
+    }
+}
+
+// ------------------------
+// Applied outputs
+// ------------------------
+
+/// Last wallpaper applied to each output, as `--restore-all` replays them.
+pub fn load_outputs() -> Vec<(String, PathBuf)> {
+    let Ok(data) = fs::read_to_string(data_dir().join("outputs.toml")) else {
+        return Vec::new();
+    };
+    let Ok(table) = data.parse::<Table>() else {
+        return Vec::new();
+    };
+
+    table
+        .iter()
+        .filter_map(|(output, path)| Some((output.clone(), PathBuf::from(path.as_str()?))))
+        .collect()
+}
+
+pub fn save_output(output: &str, path: &Path) {
+    let dir = data_dir();
+    let _ = fs::create_dir_all(&dir);
+    let file = dir.join("outputs.toml");
+
+    let mut table = fs::read_to_string(&file)
+        .ok()
+        .and_then(|data| data.parse::<Table>().ok())
+        .unwrap_or_default();
+    table.insert(
+        output.into(),
+        Value::String(path.to_string_lossy().into_owned()),
+    );
+
+    if let Ok(data) = toml::to_string(&table) {
+        let _ = fs::write(file, data);
+    }
+}
+
+// ------------------------
+// Recent wallpaper dirs
+// ------------------------
+
+// Most recently used wallpaper dirs beyond this are forgotten
+const RECENT_DIRS: usize = 20;
+
+/// Wallpaper dirs used before, most recent first.
+pub fn load_recent_dirs() -> Vec<PathBuf> {
+    fs::read_to_string(data_dir().join("recent_dirs.txt"))
+        .map(|data| {
+            data.lines()
+                .filter(|line| !line.is_empty())
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Moves `used` to the top of the recent dirs, keeping their order.
+pub fn record_recent_dirs(used: &[PathBuf]) {
+    let mut dirs = used.to_vec();
+    dirs.extend(
+        load_recent_dirs()
+            .into_iter()
+            .filter(|dir| !used.contains(dir)),
     );
+    dirs.truncate(RECENT_DIRS);
+
+    let dir = data_dir();
+    let _ = fs::create_dir_all(&dir);
+    let data: String = dirs.iter().map(|d| format!("{}\n", d.display())).collect();
+    let _ = fs::write(dir.join("recent_dirs.txt"), data);
+}
+
+// ------------------------
+// Search history
+// ------------------------
+
+// Submitted searches beyond this are forgotten
+const SEARCH_HISTORY: usize = 50;
+
+// One query per line in search_history.txt, oldest first
+pub fn load_search_history() -> Vec<String> {
+    fs::read_to_string(data_dir().join("search_history.txt"))
+        .map(|data| {
+            data.lines()
+                .filter(|line| !line.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Keeps the newest SEARCH_HISTORY queries
+pub fn save_search_history(queries: &mut Vec<String>) {
+    let excess = queries.len().saturating_sub(SEARCH_HISTORY);
+    queries.drain(..excess);
+
+    let dir = data_dir();
+    let _ = fs::create_dir_all(&dir);
+    let data: String = queries.iter().map(|q| format!("{}\n", q)).collect();
+    let _ = fs::write(dir.join("search_history.txt"), data);
+}
+
+// ------------------------
+// Image validation cache
+// ------------------------
+
+fn cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join("wallrs")
+}
+
+// path -> (mtime, readable)
+pub fn load_validation_cache() -> HashMap<PathBuf, (u64, bool)> {
+    let Ok(data) = fs::read_to_string(cache_dir().join("validation.toml")) else {
+        return HashMap::new();
+    };
+    let Ok(table) = data.parse::<Table>() else {
+        return HashMap::new();
+    };
+
+    table
+        .get("entries")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|item| {
+                    let path = item.get("path")?.as_str()?;
+                    let mtime = item.get("mtime")?.as_integer()?;
+                    let ok = item.get("ok")?.as_bool()?;
+                    Some((PathBuf::from(path), (mtime.max(0) as u64, ok)))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn save_validation_cache(results: &[(PathBuf, u64, bool)]) {
+    let dir = cache_dir();
+    let _ = fs::create_dir_all(&dir);
+
+    let records: Vec<Value> = results
+        .iter()
+        .map(|(path, mtime, ok)| {
+            let mut record = Table::new();
+            record.insert(
+                "path".into(),
+                Value::String(path.to_string_lossy().into_owned()),
+            );
+            record.insert("mtime".into(), Value::Integer(*mtime as i64));
+            record.insert("ok".into(), Value::Boolean(*ok));
+            Value::Table(record)
+        })
+        .collect();
+
+    let mut table = Table::new();
+    table.insert("entries".into(), Value::Array(records));
+
+    if let Ok(data) = toml::to_string(&table) {
+        let _ = fs::write(dir.join("validation.toml"), data);
+    }
+}
+
+// ------------------------
+// Thumbnail cache
+// ------------------------
+
+// Keyed by path and mtime so edited files get a fresh thumbnail
+pub fn thumbnail_path(path: &Path, modified: Option<SystemTime>) -> PathBuf {
+    cache_dir()
+        .join("thumbnails")
+        .join(format!("{:016x}.png", cache_key(path, modified)))
+}
+
+// Frame extracted from a video wallpaper, kept at full size
+pub fn video_frame_path(path: &Path, modified: Option<SystemTime>) -> PathBuf {
+    cache_dir()
+        .join("frames")
+        .join(format!("{:016x}.jpg", cache_key(path, modified)))
+}
+
+// Copy cut and scaled to a monitor's resolution by pre_scale
+pub fn scaled_path(
+    path: &Path,
+    modified: Option<SystemTime>,
+    (width, height): (u32, u32),
+) -> PathBuf {
+    cache_dir().join("scaled").join(format!(
+        "{:016x}-{}x{}.png",
+        cache_key(path, modified),
+        width,
+        height
+    ))
+}
+
+// Scaled copies kept, the ones used longest ago go first
+const SCALED_COPIES: usize = 32;
+
+// Drops all but the SCALED_COPIES most recently used scaled copies
+pub fn prune_scaled() {
+    prune_oldest(&cache_dir().join("scaled"), SCALED_COPIES);
+}
+
+fn prune_oldest(dir: &Path, keep: usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<(SystemTime, PathBuf)> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+        .filter_map(|path| Some((fs::metadata(&path).ok()?.modified().ok()?, path)))
+        .collect();
+    files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    for (_, path) in files.into_iter().skip(keep) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+fn cache_key(path: &Path, modified: Option<SystemTime>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    modified
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn entry(path: &str, added_at: u64) -> ListEntry {
+        ListEntry {
+            path: PathBuf::from(path),
+            added_at,
+        }
+    }
+
+    fn listed(entries: &[ListEntry]) -> Vec<(PathBuf, u64)> {
+        entries
+            .iter()
+            .map(|e| (e.path.clone(), e.added_at))
+            .collect()
+    }
+
+    #[test]
+    fn lists_round_trip_with_their_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let entries = [
+            entry("/walls/dune.png", 1_700_000_000),
+            entry("/walls/with \"quotes\" and ü.jpg", 42),
+        ];
+        write_entries(dir.path(), "favorites_night", &entries).unwrap();
+        assert_eq!(
+            listed(&load_entries_in(dir.path(), "favorites_night")),
+            listed(&entries)
+        );
+        assert!(load_entries_in(dir.path(), "queue").is_empty());
+    }
+
+    #[test]
+    fn legacy_lists_are_migrated_once() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("history.txt"),
+            "/walls/a.png\n\n/walls/b.png\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("favorites.txt"), "/walls/c.png\n").unwrap();
+
+        for name in ["history", "favorites"] {
+            let migrated = load_entries_in(dir.path(), name);
+            assert!(migrated.iter().all(|e| e.added_at > 0));
+            assert!(dir.path().join(format!("{}.toml", name)).is_file());
+            assert!(!dir.path().join(format!("{}.txt", name)).exists());
+            assert!(dir.path().join(format!("{}.txt.bak", name)).is_file());
+            // Read back from the TOML file from now on
+            assert_eq!(
+                listed(&load_entries_in(dir.path(), name)),
+                listed(&migrated)
+            );
+        }
+        let paths: Vec<PathBuf> = load_entries_in(dir.path(), "history")
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(
+            paths,
+            [Path::new("/walls/a.png"), Path::new("/walls/b.png")]
+        );
+    }
+
+    #[test]
+    fn migration_keeps_an_existing_toml_list() {
+        let dir = tempfile::tempdir().unwrap();
+        write_entries(dir.path(), "history", &[entry("/walls/new.png", 7)]).unwrap();
+        fs::write(dir.path().join("history.txt"), "/walls/old.png\n").unwrap();
+
+        assert!(migrate_legacy(dir.path(), "history").is_empty());
+        assert_eq!(
+            listed(&load_entries_in(dir.path(), "history")),
+            [(PathBuf::from("/walls/new.png"), 7)]
+        );
+        assert!(dir.path().join("history.txt").is_file());
+    }
+
+    #[test]
+    fn pruning_keeps_the_most_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        for (i, name) in ["old.png", "new.png", "mid.png", "notes.txt"]
+            .iter()
+            .enumerate()
+        {
+            let path = dir.path().join(name);
+            fs::write(&path, b"").unwrap();
+            let age = Duration::from_secs(match i {
+                0 => 300,
+                1 => 0,
+                _ => 100,
+            });
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(SystemTime::now() - age)
+                .unwrap();
+        }
+
+        prune_oldest(dir.path(), 2);
+        let mut left: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["mid.png", "new.png", "notes.txt"]);
+    }
 }
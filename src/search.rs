@@ -0,0 +1,97 @@
+// ------------------------
+// Search matching
+// ------------------------
+
+// Case-insensitive subsequence match. Consecutive runs and matches at the
+// start of a word score higher; `None` means the query doesn't match.
+pub fn fuzzy_score(candidate: &str, query: &str) -> Option<i64> {
+    fuzzy_match(candidate, query).map(|(score, _)| score)
+}
+
+// Same as `fuzzy_score`, also returning the matched char positions
+pub fn fuzzy_match(candidate: &str, query: &str) -> Option<(i64, Vec<usize>)> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last_match: Option<usize> = None;
+    let mut positions = Vec::new();
+
+    for qc in query.chars() {
+        let found = candidate[pos..].iter().position(|&c| same_char(c, qc))? + pos;
+
+        score += 1;
+        if last_match.is_some_and(|last| last + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || matches!(candidate[found - 1], ' ' | '-' | '_' | '.' | '/') {
+            score += 3;
+        }
+        score -= (found - pos).min(5) as i64;
+
+        last_match = Some(found);
+        positions.push(found);
+        pos = found + 1;
+    }
+
+    Some((score, positions))
+}
+
+// Plain case-insensitive substring match with the same result shape
+pub fn substring_match(candidate: &str, query: &str) -> Option<(i64, Vec<usize>)> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let query: Vec<char> = query.chars().collect();
+    let first = (0..=candidate.len().checked_sub(query.len())?).find(|&start| {
+        candidate[start..]
+            .iter()
+            .zip(&query)
+            .all(|(&c, &qc)| same_char(c, qc))
+    })?;
+    Some((0, (first..first + query.len()).collect()))
+}
+
+// Compared char by char rather than on lowercased strings, whose lengths can
+// differ from the original and would shift the positions
+fn same_char(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_starts_and_runs_rank_first() {
+        let ranked = |candidate| fuzzy_score(candidate, "mnt").unwrap();
+        assert!(ranked("mnt_lake.png") > ranked("mountain.png"));
+        assert!(ranked("blue-mnt.png") > ranked("dim_night.png"));
+        assert_eq!(fuzzy_score("forest.png", "mnt"), None);
+    }
+
+    #[test]
+    fn substring_positions_count_chars() {
+        assert_eq!(
+            substring_match("Sunset.jpg", "SET"),
+            Some((0, vec![3, 4, 5]))
+        );
+        assert_eq!(substring_match("Sunset.jpg", "dawn"), None);
+    }
+
+    #[test]
+    fn positions_index_the_original_name() {
+        // "İ" lowercases to two chars, which used to push the positions one
+        // char too far
+        let name = "İstanbul_Gece.png";
+        let chars: Vec<char> = name.chars().collect();
+        let (_, positions) = fuzzy_match(name, "gece").unwrap();
+        assert_eq!(
+            positions.iter().map(|&i| chars[i]).collect::<String>(),
+            "Gece"
+        );
+        let (_, positions) = substring_match(name, "GECE").unwrap();
+        assert_eq!(
+            positions.iter().map(|&i| chars[i]).collect::<String>(),
+            "Gece"
+        );
+        assert_eq!(substring_match("ÄÖ", "äö"), Some((0, vec![0, 1])));
+    }
+}
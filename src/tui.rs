@@ -1,69 +1,421 @@
-use crate::config::Config as AppConfig;
-use crate::input::{Input, handle_input};
-use crate::mouse::{MouseInput, handle_mouse};
-use crate::persistence::{load_list, save_list};
-use crossterm::event::KeyCode;
-use crossterm::event::{self, EnableMouseCapture};
-use crossterm::execute;
-use image::DynamicImage;
+use crate::apply::{
+    apply_wallpaper, connected_outputs, generate_colors, reveal_in_file_manager,
+    run_reload_command, targets_outputs,
+};
+use crate::colors::dominant_colors;
+use crate::config::{Config as AppConfig, expand_path};
+use crate::input::{
+    Action, Input, SearchHistory, Selection, handle_input, record_history, switch_tab,
+};
+use crate::line_edit::LineEdit;
+use crate::mouse::{MouseAreas, MouseInput, entry_at, handle_mouse};
+use crate::ops::{self, FileOp, Undone};
+use crate::persistence::{
+    ListEntry, collection_list, favorites_lists, insert_into_list, list_collections, load_list,
+    load_state, remove_from_list, rename_in_list, save_list, save_state, thumbnail_path,
+    video_frame_path,
+};
+use crate::search::{fuzzy_match, fuzzy_score, substring_match};
+use crate::wallpapers::{ScanEvent, Wallpaper, find_broken, paths, sort_key};
+use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture};
+use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::terminal::{
+    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+};
+use crossterm::{cursor, execute};
+use image::{DynamicImage, ImageDecoder};
+use lru::LruCache;
 use ratatui::{
     Frame, Terminal,
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout, Margin, Rect},
-    style::{Color, Style},
-    text::Text,
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs, Wrap},
 };
-use ratatui_image::{Resize, StatefulImage, picker::Picker, protocol::StatefulProtocol};
-use std::collections::HashMap;
+use ratatui_image::{
+    Resize, StatefulImage,
+    picker::{Picker, ProtocolType},
+    protocol::StatefulProtocol,
+};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io;
+use std::panic;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Once};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use strum_macros::Display;
-use tempfile::NamedTempFile;
-use tokio::sync::mpsc;
+use tokio::sync::{Semaphore, mpsc, oneshot};
+use unicode_width::UnicodeWidthStr;
 // ---------------------------
 // Image Cache
 // ---------------------------
 struct ImageCache {
-    cache: HashMap<PathBuf, CachedImage>,
+    cache: LruCache<PathBuf, CachedImage>,
+    // Protocols of previews shown before, so a revisit doesn't re-encode
+    protocols: HashMap<PathBuf, CachedProtocol>,
     max_size: usize,
+    // Budget for decoded pixel data and protocols, on top of the entry limit
+    max_bytes: Option<usize>,
+    bytes: usize,
+}
+
+// A preview's protocol, only good for the pane and resize mode it was made for
+struct CachedProtocol {
+    cut: (Rect, PreviewResize),
+    // Size of the image it was given
+    size: (u32, u32),
+    protocol: StatefulProtocol,
+}
+
+impl CachedProtocol {
+    fn byte_size(&self) -> usize {
+        self.size.0 as usize * self.size.1 as usize * 4
+    }
 }
 
 impl ImageCache {
-    fn new(max_size: usize) -> Self {
+    fn new(max_size: usize, max_bytes: Option<usize>) -> Self {
         Self {
-            cache: HashMap::with_capacity(max_size),
+            cache: LruCache::unbounded(),
+            protocols: HashMap::new(),
             max_size,
+            max_bytes,
+            bytes: 0,
         }
     }
 
+    // Looks up without touching the recency order
+    fn peek(&self, path: &Path) -> Option<&CachedImage> {
+        self.cache.peek(path)
+    }
+
+    // Marks the entry as most recently used
     fn get(&mut self, path: &PathBuf) -> Option<&CachedImage> {
         self.cache.get(path)
     }
 
     fn insert(&mut self, path: PathBuf, image: CachedImage) {
-        // Simple LRU-like eviction: remove oldest entries if cache is full
-        if self.cache.len() >= self.max_size
-            && let Some(key) = self.cache.keys().next().cloned()
+        // A protocol made from the old image is stale
+        self.drop_protocol(&path);
+        self.bytes += image.byte_size();
+        if let Some(old) = self.cache.put(path, image) {
+            self.bytes -= old.byte_size();
+        }
+        self.evict();
+    }
+
+    // Evict least recently used entries, always keeping the newest one
+    fn evict(&mut self) {
+        while self.cache.len() > 1
+            && (self.cache.len() > self.max_size
+                || self.max_bytes.is_some_and(|max| self.bytes > max))
         {
-            self.cache.remove(&key);
+            if let Some((path, evicted)) = self.cache.pop_lru() {
+                self.bytes -= evicted.byte_size();
+                self.drop_protocol(&path);
+            }
+        }
+    }
+
+    fn remove(&mut self, path: &Path) {
+        if let Some(image) = self.cache.pop(path) {
+            self.bytes -= image.byte_size();
+        }
+        self.drop_protocol(path);
+    }
+
+    // Entries are all taken out before they're put back, so files may swap
+    // names
+    fn rename(&mut self, renamed: &[(PathBuf, PathBuf)]) {
+        let mut moved = Vec::new();
+        for (old_path, new_path) in renamed {
+            let image = self.cache.pop(old_path);
+            let protocol = self.protocols.remove(old_path);
+            moved.push((new_path, image, protocol));
+        }
+        for (new_path, image, protocol) in moved {
+            if let Some(image) = image
+                && let Some(old) = self.cache.put(new_path.clone(), image)
+            {
+                self.bytes -= old.byte_size();
+            }
+            if let Some(protocol) = protocol
+                && let Some(old) = self.protocols.insert(new_path.clone(), protocol)
+            {
+                self.bytes -= old.byte_size();
+            }
+        }
+    }
+
+    // Kept only while the image itself is cached
+    fn keep_protocol(&mut self, path: PathBuf, protocol: CachedProtocol) {
+        if !self.cache.contains(&path) {
+            return;
+        }
+        self.drop_protocol(&path);
+        self.bytes += protocol.byte_size();
+        self.protocols.insert(path, protocol);
+        self.evict();
+    }
+
+    // The protocol for `path` if it was made for this `cut`
+    fn take_protocol(&mut self, path: &Path, cut: (Rect, PreviewResize)) -> Option<CachedProtocol> {
+        let protocol = self.protocols.remove(path)?;
+        self.bytes -= protocol.byte_size();
+        (protocol.cut == cut).then_some(protocol)
+    }
+
+    fn drop_protocol(&mut self, path: &Path) {
+        if let Some(protocol) = self.protocols.remove(path) {
+            self.bytes -= protocol.byte_size();
+        }
+    }
+
+    // The font size may have changed with the terminal
+    fn clear_protocols(&mut self) {
+        for (_, protocol) in self.protocols.drain() {
+            self.bytes -= protocol.byte_size();
+        }
+    }
+}
+// ---------------------------
+// Theme
+// ---------------------------
+
+// UI colors, all dropped when NO_COLOR is set. The preview, thumbnails and
+// swatches are content and keep theirs.
+#[derive(Clone, Copy)]
+struct Theme {
+    color: bool,
+}
+
+impl Theme {
+    fn style(self, style: Style) -> Style {
+        if self.color { style } else { Style::default() }
+    }
+
+    fn fg(self, color: Color) -> Style {
+        self.style(Style::default().fg(color))
+    }
+
+    // The highlighted entry is shown in reverse video without colors
+    fn highlight(self, style: Style) -> Style {
+        if self.color {
+            style
+        } else {
+            Style::default().add_modifier(Modifier::REVERSED)
         }
+    }
+}
+
+// ---------------------------
+// Preview
+// ---------------------------
+
+// Dominant color panel beside the preview, as many as pywal's base colors
+// Title of the Wallpapers list while typing a search
+const SEARCH_LABEL: &str = "Search: ";
+
+const SWATCH_COUNT: usize = 8;
+const SWATCH_WIDTH: u16 = 10;
+
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const SPINNER_INTERVAL_MS: u128 = 80;
+// Preview zoom doubles per step up to this
+const MAX_ZOOM: u32 = 16;
+
+// A preview decode only starts once the selection has rested this long
+const PREVIEW_DEBOUNCE: Duration = Duration::from_millis(80);
+// Decodes running at once, scrolling past rows shouldn't pile them up
+const PREVIEW_DECODES: usize = 2;
+// Entries on either side of the selection decoded ahead of time
+const PRELOAD_RADIUS: usize = 5;
+
+// None when the request was abandoned because the selection moved on
+type PreviewMessage = (
+    PathBuf,
+    Option<Result<CachedImage, Box<dyn std::error::Error + Send + Sync>>>,
+);
+
+// One request_preview, decoded by spawn_preview
+struct PreviewJob {
+    path: Option<PathBuf>,
+    neighbours: Vec<PathBuf>,
+    generation: u64,
+    max_edge: u32,
+}
+
+// Waits out the debounce and a decode slot, then decodes unless `current` moved
+// past the job meanwhile. A decode that was already running stops at its next
+// stage, either way the path comes back with None.
+fn spawn_preview(
+    job: PreviewJob,
+    current: PreviewGeneration,
+    decodes: Arc<Semaphore>,
+    tx: mpsc::Sender<PreviewMessage>,
+) {
+    let PreviewJob {
+        path,
+        neighbours,
+        generation,
+        max_edge,
+    } = job;
+    tokio::spawn(async move {
+        tokio::time::sleep(PREVIEW_DEBOUNCE).await;
+        let permit = match decodes.acquire_owned().await {
+            Ok(permit) if current.is_current(generation) => permit,
+            _ => {
+                if let Some(path) = path {
+                    let _ = tx.send((path, None)).await;
+                }
+                return;
+            }
+        };
+
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let wanted = || current.is_current(generation);
+            for path in path.into_iter().chain(neighbours) {
+                let result = catch_decode(|| CachedImage::load(&path, max_edge, &wanted));
+                // Failing because it was abandoned doesn't make the file broken
+                let result = (result.is_ok() || wanted()).then_some(result);
+                let abandoned = result.is_none();
+                if tx.blocking_send((path, result)).is_err() || abandoned {
+                    break;
+                }
+            }
+        });
+    });
+}
+
+// A decoder panic fails the entry instead of leaving it loading forever
+fn catch_decode(
+    decode: impl FnOnce() -> Result<CachedImage, Box<dyn std::error::Error + Send + Sync>>,
+) -> Result<CachedImage, Box<dyn std::error::Error + Send + Sync>> {
+    panic::catch_unwind(panic::AssertUnwindSafe(decode))
+        .unwrap_or_else(|_| Err("the decoder panicked".into()))
+}
+
+// Bumped per preview request, older requests give up when they see it moved
+#[derive(Clone, Default)]
+struct PreviewGeneration(Arc<AtomicU64>);
+
+impl PreviewGeneration {
+    fn next(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        self.0.load(Ordering::SeqCst) == generation
+    }
+}
+
+#[derive(Clone)]
+enum PreviewStatus {
+    Loading,
+    Ready,
+    Failed(String),
+}
+
+// ---------------------------
+// Grid thumbnails
+// ---------------------------
+const THUMBNAIL_SIZE: u32 = 256;
+const GRID_CELL_WIDTH: u16 = 24;
+const GRID_CELL_HEIGHT: u16 = 12;
+
+enum Thumbnail {
+    Pending,
+    Ready(StatefulProtocol),
+    Failed,
+}
+
+// Thumbnails are kept on disk so reopening the grid doesn't decode every image again
+fn load_thumbnail(path: &PathBuf, modified: Option<SystemTime>) -> Option<DynamicImage> {
+    let cached = thumbnail_path(path, modified);
+    if let Ok(image) = image::open(&cached) {
+        return Some(image);
+    }
 
-        self.cache.insert(path, image);
+    let image = CachedImage::new(path, THUMBNAIL_SIZE).ok()?.image;
+    if let Some(dir) = cached.parent()
+        && fs::create_dir_all(dir).is_ok()
+    {
+        let _ = image.save(&cached);
     }
+    Some(Arc::unwrap_or_clone(image))
 }
+
 #[derive(Clone)]
 struct CachedImage {
     image: Arc<DynamicImage>,
     is_video: bool,
+    // Size of the source before downscaling
+    dimensions: Option<(u32, u32)>,
+    // Dominant colors, extracted along with the decode
+    palette: Vec<[u8; 3]>,
 }
 
 impl CachedImage {
-    fn new(path: &PathBuf) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+    fn byte_size(&self) -> usize {
+        self.image.as_bytes().len()
+    }
+
+    // Like decode, but a video whose frame can't be extracted still gets an entry
+    fn load(
+        path: &PathBuf,
+        max_edge: u32,
+        wanted: &dyn Fn() -> bool,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let extension = path
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        match Self::decode(path, max_edge, wanted) {
+            // Cached as well, so the extraction isn't retried
+            Err(_)
+                if wanted()
+                    && ["mp4", "avi", "mov", "mkv", "webm"].contains(&extension.as_str()) =>
+            {
+                Ok(CachedImage {
+                    image: Arc::new(DynamicImage::new_rgba8(1, 1)),
+                    is_video: true,
+                    dimensions: None,
+                    palette: Vec::new(),
+                })
+            }
+            result => result,
+        }
+    }
+
+    // Decodes a preview no larger than `max_edge` on its long side
+    fn new(
+        path: &PathBuf,
+        max_edge: u32,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Self::decode(path, max_edge, &|| true)
+    }
+
+    // Like new, giving up between the decode, the downscale and the palette
+    // once `wanted` turns false
+    fn decode(
+        path: &PathBuf,
+        max_edge: u32,
+        wanted: &dyn Fn() -> bool,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let abandoned = || -> Box<dyn std::error::Error + Send + Sync> { "abandoned".into() };
+        if !wanted() {
+            return Err(abandoned());
+        }
         let extension = path
             .extension()
             .and_then(|s| s.to_str())
@@ -74,67 +426,92 @@ impl CachedImage {
             // Extract thumbnail from video
             Self::extract_video_thumbnail(path)?
         } else {
-            // Load regular image
-            image::ImageReader::open(path)?
+            // Load regular image (animated formats decode to their first frame),
+            // rotated upright according to its EXIF orientation
+            let mut decoder = image::ImageReader::open(path)?
                 .with_guessed_format()?
-                .decode()?
+                .into_decoder()?;
+            let orientation = decoder.orientation()?;
+            let mut image = DynamicImage::from_decoder(decoder)?;
+            image.apply_orientation(orientation);
+            image
+        };
+        if !wanted() {
+            return Err(abandoned());
+        }
+        let dimensions = Some((image.width(), image.height()));
+        let image = if image.width() > max_edge || image.height() > max_edge {
+            image.thumbnail(max_edge, max_edge)
+        } else {
+            image
         };
 
+        if !wanted() {
+            return Err(abandoned());
+        }
+
         Ok(Self {
+            palette: dominant_colors(&image, SWATCH_COUNT),
             image: Arc::new(image),
             is_video: ["mp4", "avi", "mov", "mkv", "webm"].contains(&extension.as_str()),
+            dimensions,
         })
     }
 
+    // Frames are cached on disk, a missing ffmpeg is remembered instead of
+    // being spawned again for every video
     fn extract_video_thumbnail(
         path: &PathBuf,
     ) -> Result<DynamicImage, Box<dyn std::error::Error + Send + Sync>> {
-        // Create a temporary file for the thumbnail
-        let temp_file = NamedTempFile::new()?;
-        let temp_path = temp_file.path().with_extension("jpg");
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        let frame = video_frame_path(path, modified);
+        if let Ok(image) = image::open(&frame) {
+            return Ok(image);
+        }
+        if FFMPEG_MISSING.load(Ordering::Relaxed) {
+            return Err("ffmpeg not found".into());
+        }
+        if let Some(dir) = frame.parent() {
+            fs::create_dir_all(dir)?;
+        }
 
-        // Use ffmpeg to extract a frame from the video (at 1 second)
+        // Seeking before the input skips decoding up to the timestamp
         let output = Command::new("ffmpeg")
-            .args([
-                "-i",
-                path.to_str().unwrap(),
-                "-ss",
-                "00:00:01", // Seek to 1 second
-                "-vframes",
-                "1", // Extract 1 frame
-                "-q:v",
-                "2", // High quality
-                temp_path.to_str().unwrap(),
-                "-y", // Overwrite output file
-            ])
-            .output()?;
+            .arg("-ss")
+            .arg("1")
+            .arg("-i")
+            .arg(path)
+            .args(["-frames:v", "1", "-q:v", "2", "-y"])
+            .arg(&frame)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> {
+                if e.kind() == io::ErrorKind::NotFound {
+                    FFMPEG_MISSING.store(true, Ordering::Relaxed);
+                    "ffmpeg not found".into()
+                } else {
+                    e.into()
+                }
+            })?;
 
         if !output.status.success() {
+            let _ = fs::remove_file(&frame);
             return Err(
                 format!("FFmpeg failed: {}", String::from_utf8_lossy(&output.stderr)).into(),
             );
         }
 
-        // Load the extracted frame as an image
-        let image = image::open(&temp_path)?;
-
-        // Clean up the temporary file (ignore errors)
-        let _ = std::fs::remove_file(&temp_path);
-
-        Ok(image)
+        Ok(image::open(&frame)?)
     }
 
-    fn create_video_placeholder() -> DynamicImage {
-        // Create a placeholder image for videos when thumbnail extraction fails
-        DynamicImage::ImageRgba8(image::RgbaImage::from_fn(100, 100, |x, y| {
-            if (x / 10 + y / 10) % 2 == 0 {
-                image::Rgba([70, 70, 70, 255]) // Dark gray
-            } else {
-                image::Rgba([50, 50, 50, 255]) // Darker gray
-            }
-        }))
+    // Videos without an extracted frame show a text placeholder instead
+    fn has_frame(&self) -> bool {
+        !self.is_video || self.dimensions.is_some()
     }
 }
+
+static FFMPEG_MISSING: AtomicBool = AtomicBool::new(false);
+
 // ---------------------------
 // Tab Enum
 // ---------------------------
@@ -147,6 +524,9 @@ pub enum Tab {
     History,
     #[strum(serialize = "Favorites")]
     Favorites,
+    // Wallpapers picked for a slideshow, in the order they'll play
+    #[strum(serialize = "Queue")]
+    Queue,
 }
 
 impl Tab {
@@ -159,6 +539,7 @@ impl Tab {
             "wallpapers" | "wallpaper" | "wall" => Some(Tab::Wallpapers),
             "history" | "recent" | "recents" => Some(Tab::History),
             "favorites" | "favourites" | "favorite" | "favourite" | "favs" => Some(Tab::Favorites),
+            "queue" | "playlist" => Some(Tab::Queue),
             _ => None,
         }
     }
@@ -171,139 +552,920 @@ impl FromStr for Tab {
     }
 }
 
+// ---------------------------
+// Preview Resize Mode
+// ---------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewResize {
+    Fit,
+    Crop,
+    // Scaled to the pane's width or height, cutting off what sticks out
+    // the other way
+    Width,
+    Height,
+}
+
+impl PreviewResize {
+    pub fn from_name(s: &str) -> Self {
+        match s.trim().to_lowercase().as_str() {
+            "crop" | "fill" => PreviewResize::Crop,
+            "width" => PreviewResize::Width,
+            "height" => PreviewResize::Height,
+            _ => PreviewResize::Fit,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            PreviewResize::Fit => PreviewResize::Crop,
+            PreviewResize::Crop => PreviewResize::Width,
+            PreviewResize::Width => PreviewResize::Height,
+            PreviewResize::Height => PreviewResize::Fit,
+        }
+    }
+
+    fn resize(self) -> Resize {
+        match self {
+            PreviewResize::Fit => Resize::Fit(None),
+            PreviewResize::Crop => Resize::Crop(None),
+            // The image was already cut to the pane's shape
+            PreviewResize::Width | PreviewResize::Height => Resize::Scale(None),
+        }
+    }
+
+    // Width over height of the part of a `width`x`height` image to show in
+    // a pane shaped `pane`, None to show all of it
+    fn view_aspect(self, width: f32, height: f32, pane: f32) -> Option<f32> {
+        match self {
+            PreviewResize::Width if width / height < pane => Some(pane),
+            PreviewResize::Height if width / height > pane => Some(pane),
+            _ => None,
+        }
+    }
+}
+
+// Cells an image of `size` pixels takes when fitted into `area`, centered
+// there. `upscale` lets it grow past its own size like Resize::Scale.
+fn centered(area: Rect, size: (u32, u32), font: (u16, u16), upscale: bool) -> Rect {
+    let (font_width, font_height) = (font.0.max(1) as f32, font.1.max(1) as f32);
+    let (width, height) = (size.0.max(1) as f32, size.1.max(1) as f32);
+    let mut scale =
+        (area.width as f32 * font_width / width).min(area.height as f32 * font_height / height);
+    if !upscale {
+        scale = scale.min(1.0);
+    }
+    let columns = ((width * scale / font_width).ceil() as u16).min(area.width);
+    let rows = ((height * scale / font_height).ceil() as u16).min(area.height);
+    Rect {
+        x: area.x + (area.width - columns) / 2,
+        y: area.y + (area.height - rows) / 2,
+        width: columns,
+        height: rows,
+    }
+}
+
+// ---------------------------
+// Wallpaper Sort Mode
+// ---------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    Name,
+    Newest,
+    Size,
+    Random,
+}
+
+impl SortMode {
+    pub fn from_name(s: &str) -> Self {
+        match s.trim().to_lowercase().as_str() {
+            "mtime" => SortMode::Newest,
+            "size" => SortMode::Size,
+            "random" => SortMode::Random,
+            _ => SortMode::Name,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SortMode::Name => "name",
+            SortMode::Newest => "mtime",
+            SortMode::Size => "size",
+            SortMode::Random => "random",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            SortMode::Name => SortMode::Newest,
+            SortMode::Newest => SortMode::Size,
+            SortMode::Size => SortMode::Random,
+            SortMode::Random => SortMode::Name,
+        }
+    }
+
+    // Shown in the list title
+    fn label(self) -> &'static str {
+        match self {
+            SortMode::Name => "name",
+            SortMode::Newest => "mtime↓",
+            SortMode::Size => "size↓",
+            SortMode::Random => "random",
+        }
+    }
+}
+
+fn shuffle_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
 // ---------------------------
 // Rename State
 // ---------------------------
 
+// The copy destination, batch rename pattern and convert format prompts
+// share the rename dialog and its input handling
+#[derive(Clone, Copy, PartialEq)]
+pub enum PromptAction {
+    Rename,
+    Copy,
+    BatchRename,
+    Convert,
+    // Name of a new favorites collection
+    Collection,
+}
+
+// New names of the first batch files shown while typing the pattern
+const BATCH_PREVIEW: usize = 3;
+
 pub struct RenameState {
     pub original_path: PathBuf,
-    pub current_input: String,
+    pub input: LineEdit,
     pub error: Option<String>,
+    pub action: PromptAction,
+    // Multi-select picks in list order for a batch rename, empty otherwise
+    pub batch: Vec<PathBuf>,
 }
 
 // ---------------------------
-// TUI Application
+// Confirmation State
 // ---------------------------
 
-pub struct TuiApp<'a> {
-    terminal: Terminal<CrosstermBackend<io::Stdout>>,
-    config: &'a AppConfig,
-    wallpapers: Vec<PathBuf>,
-    history: Vec<PathBuf>,
-    favorites: Vec<PathBuf>,
-    selected: usize,
-    list_state: ListState,
-    search_query: String,
-    in_search: bool,
-    current_tab: Tab,
-    last_preview: Option<PathBuf>,
-    multi_select: bool,
-    selected_items: Vec<usize>,
-    dirty: bool,
-    // Image rendering
-    picker: Picker,
-    preview_state: Option<StatefulProtocol>,
-    image_cache: ImageCache,
-    preview_tx: mpsc::Sender<(
-        PathBuf,
-        Result<CachedImage, Box<dyn std::error::Error + Send + Sync>>,
-    )>,
-    preview_rx: mpsc::Receiver<(
-        PathBuf,
-        Result<CachedImage, Box<dyn std::error::Error + Send + Sync>>,
-    )>,
-    rename_state: Option<RenameState>,
+// Actions that ask before running
+pub enum Confirm {
+    Trash(Vec<PathBuf>),
+    // Empties History or Favorites, the files stay
+    ClearList(Tab),
 }
 
-impl<'a> TuiApp<'a> {
-    pub fn new(
-        wallpapers: &[PathBuf],
-        config: &'a AppConfig,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
-        if config.mouse_support {
-            execute!(io::stdout(), EnableMouseCapture)?;
-        }
+pub struct ConfirmState {
+    pub action: Confirm,
+    pub error: Option<String>,
+}
 
-        let stdout = io::stdout();
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend)?;
-        terminal.clear()?;
+// ---------------------------
+// Quick Switcher State
+// ---------------------------
 
-        let first_tab = config
-            .tabs
-            .iter()
-            .find(|t| t.enabled)
-            .map(|t| t.tab)
-            .unwrap_or(Tab::Wallpapers);
+const SWITCHER_RESULTS: usize = 8;
 
-        let picker = Picker::from_query_stdio()?;
+#[derive(Default)]
+pub struct SwitcherState {
+    pub query: LineEdit,
+    pub selected: usize,
+}
 
-        // Initialize image cache with reasonable default size
-        let cache_size = config.image_cache_size.unwrap_or(50);
-        let image_cache = ImageCache::new(cache_size);
-        let (preview_tx, preview_rx) = mpsc::channel(10);
+// ---------------------------
+// Context Menu State
+// ---------------------------
 
-        Ok(Self {
-            terminal,
-            config,
-            wallpapers: wallpapers.to_vec(),
-            history: load_list("history.txt"),
-            favorites: load_list("favorites.txt"),
-            selected: 0,
-            list_state: {
-                let mut s = ListState::default();
-                s.select(Some(0));
-                s
-            },
-            search_query: String::new(),
-            in_search: false,
-            current_tab: first_tab,
-            last_preview: None,
-            multi_select: false,
-            selected_items: Vec::new(),
-            dirty: true,
-            picker,
-            preview_state: None,
-            image_cache,
-            preview_tx,
-            preview_rx,
-            rename_state: None,
-        })
+pub struct MenuState {
+    // Pointer position of the right-click that opened it
+    pub anchor: (u16, u16),
+    pub selected: usize,
+    pub items: Vec<(String, Action)>,
+}
+
+// Opens down and to the right of the anchor, shifted back inside `area`
+// near the right and bottom edges
+fn menu_rect(area: Rect, anchor: (u16, u16), items: &[(String, Action)]) -> Rect {
+    let widest = items
+        .iter()
+        .map(|(label, _)| label.width())
+        .max()
+        .unwrap_or(0);
+    let width = (widest as u16 + 4).min(area.width);
+    let height = (items.len() as u16 + 2).min(area.height);
+    let x = anchor.0.min(area.right() - width).max(area.x);
+    let y = anchor.1.min(area.bottom() - height).max(area.y);
+    Rect::new(x, y, width, height)
+}
+
+// The main area split between the list and the preview, in that order, as
+// list_position puts them. Anything unknown is "left".
+pub(crate) fn split_main(area: Rect, list_position: &str) -> (Rect, Rect) {
+    let position = list_position.to_lowercase();
+    let direction = match position.as_str() {
+        "top" | "bottom" => Direction::Vertical,
+        _ => Direction::Horizontal,
+    };
+    let halves = Layout::default()
+        .direction(direction)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    match position.as_str() {
+        "right" | "bottom" => (halves[1], halves[0]),
+        _ => (halves[0], halves[1]),
     }
+}
 
-    pub async fn run(&mut self) -> Result<PathBuf, Box<dyn std::error::Error>> {
-        // Preload images
-        let filtered = self.filter_items();
-        let preload_paths: Vec<PathBuf> = filtered.iter().take(10).cloned().collect();
-        self.preload_images(&preload_paths);
+// Search results for one query, reused until the query or the list changes
+struct SearchCache {
+    query: String,
+    results: Vec<Arc<Wallpaper>>,
+    // Matched char positions in the path relative to its wallpaper_dir
+    positions: HashMap<PathBuf, Vec<usize>>,
+}
 
-        loop {
-            // Check for completed previews asynchronously
-            while let Ok((path, result)) = self.preview_rx.try_recv() {
-                if let Ok(cached_image) = result {
-                    self.image_cache.insert(path.clone(), cached_image.clone());
-
-                    if Some(&path) == self.filter_items().get(self.selected) {
-                        self.preview_state = Some(
-                            self.picker
-                                .new_resize_protocol(cached_image.image.as_ref().clone()),
-                        );
-                        self.dirty = true;
-                    }
-                }
-            }
+// Bare file names, with as many parent folders as needed prepended where the
+// same name appears more than once (`nature/wallpaper.png`)
+fn disambiguate_labels(paths: &[&Path]) -> Vec<String> {
+    let label = |path: &Path, depth: usize| -> String {
+        let skip = path.components().count().saturating_sub(depth + 1);
+        path.components()
+            .skip(skip)
+            .collect::<PathBuf>()
+            .to_string_lossy()
+            .to_string()
+    };
 
-            let filtered = self.filter_items();
-            self.adjust_selection(&filtered);
+    let mut depths = vec![0; paths.len()];
+    loop {
+        let labels: Vec<String> = paths
+            .iter()
+            .zip(&depths)
+            .map(|(p, &d)| label(p, d))
+            .collect();
 
-            if self.dirty {
-                self.draw_ui(&filtered)?;
-                self.dirty = false;
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for l in &labels {
+            *counts.entry(l.as_str()).or_default() += 1;
+        }
+
+        let mut changed = false;
+        for (i, l) in labels.iter().enumerate() {
+            if counts[l.as_str()] > 1 && depths[i] + 1 < paths[i].components().count() {
+                depths[i] += 1;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return labels;
+        }
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+// Keeps the end of `text`, which holds the file name, when it doesn't fit
+fn truncate_left(text: &str, width: usize) -> String {
+    let count = text.chars().count();
+    if count <= width {
+        return text.to_string();
+    }
+    let tail: String = text.chars().skip(count + 1 - width.max(1)).collect();
+    format!("…{}", tail)
+}
+
+fn key_label(key: char) -> String {
+    match key {
+        ' ' => "Space".into(),
+        c => c.to_string(),
+    }
+}
+
+// Joins as many hints as fit in `width` columns, cutting between hints
+// rather than through one
+fn fit_hints(hints: &[(String, &str)], width: usize) -> String {
+    let mut line = String::new();
+    for (i, (key, action)) in hints.iter().enumerate() {
+        let hint = format!("{}{} {}", if i == 0 { "" } else { " • " }, key, action);
+        // Leave room for the ellipsis unless this is the last hint
+        let reserve = if i + 1 < hints.len() { 2 } else { 0 };
+        if line.width() + hint.width() + reserve > width {
+            if i > 0 && line.width() + 2 <= width {
+                line.push_str(" …");
+            }
+            break;
+        }
+        line.push_str(&hint);
+    }
+    line
+}
+
+fn broken_message(path: &Path) -> String {
+    format!(
+        " Cannot apply unreadable image: {} ",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    )
+}
+
+// Appends the position within the list, 0/0 when it is empty, and the number
+// of picked entries while multi-selecting
+fn counted_title(title: &str, selected: usize, len: usize, picked: Option<usize>) -> String {
+    let position = if len == 0 { 0 } else { selected + 1 };
+    match picked {
+        Some(picked) => format!("{} ({}/{}, {} selected)", title, position, len, picked),
+        None => format!("{} ({}/{})", title, position, len),
+    }
+}
+
+// ---------------------------
+// Terminal setup
+// ---------------------------
+
+// Raw mode, alternate screen and mouse capture last as long as this guard,
+// early errors and panics restore the terminal too
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new(mouse_support: bool) -> io::Result<Self> {
+        install_panic_hook();
+        enable_raw_mode()?;
+        let guard = Self;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        if mouse_support {
+            execute!(io::stdout(), EnableMouseCapture)?;
+        }
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Leaves the alternate screen and raw mode. Safe to call more than once or
+/// when the terminal was never set up.
+pub fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        io::stdout(),
+        DisableMouseCapture,
+        LeaveAlternateScreen,
+        cursor::Show
+    );
+}
+
+type PanicHook = Box<dyn Fn(&panic::PanicHookInfo<'_>) + Send + Sync + 'static>;
+
+// The terminal is restored before the panic message so it stays readable
+fn install_panic_hook() {
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| {
+        panic::set_hook(restoring_hook(restore_terminal, panic::take_hook()));
+    });
+}
+
+// Runs `restore` ahead of the previous `hook`
+fn restoring_hook(restore: impl Fn() + Send + Sync + 'static, hook: PanicHook) -> PanicHook {
+    Box::new(move |info| {
+        restore();
+        hook(info);
+    })
+}
+
+// Cell size in pixels when the terminal doesn't answer the query
+const FALLBACK_FONT_SIZE: (u16, u16) = (8, 16);
+
+// `image_protocol` from the config, None means auto-detect or off
+fn forced_protocol(name: &str) -> Option<ProtocolType> {
+    match name {
+        "sixel" => Some(ProtocolType::Sixel),
+        "kitty" => Some(ProtocolType::Kitty),
+        "iterm2" => Some(ProtocolType::Iterm2),
+        "halfblocks" => Some(ProtocolType::Halfblocks),
+        _ => None,
+    }
+}
+
+// Detection can pick the wrong protocol under multiplexers, a forced one
+// replaces the detected one. The query still provides the cell size; when a
+// multiplexer swallows it the forced protocol goes on with a guessed size and
+// auto-detection falls back to halfblocks, which any terminal can draw.
+// None for "off", previews are then described in text.
+fn build_picker(image_protocol: &str) -> Option<Picker> {
+    if image_protocol == "off" {
+        return None;
+    }
+    let protocol = forced_protocol(image_protocol).unwrap_or(ProtocolType::Halfblocks);
+    match Picker::from_query_stdio() {
+        Ok(picker) if image_protocol == "auto" => Some(picker),
+        Ok(mut picker) => {
+            picker.set_protocol_type(protocol);
+            Some(picker)
+        }
+        Err(_) => {
+            let mut picker = Picker::from_fontsize(FALLBACK_FONT_SIZE);
+            picker.set_protocol_type(protocol);
+            Some(picker)
+        }
+    }
+}
+
+/// The graphics protocol previews would use under `image_protocol` and the
+/// terminal cell size in pixels, `None` when previews are text only.
+pub fn graphics_protocol(image_protocol: &str) -> Option<(String, (u16, u16))> {
+    build_picker(image_protocol)
+        .map(|picker| (format!("{:?}", picker.protocol_type()), picker.font_size()))
+}
+
+// ---------------------------
+// TUI Application
+// ---------------------------
+
+// Oldest renames and deletes are forgotten past this
+const UNDO_DEPTH: usize = 20;
+
+/// The interactive picker. `run` returns each wallpaper chosen with Enter and
+/// can be called again to keep browsing, or `None` once the user quits. The
+/// terminal is set up by `new` and left to the caller to restore, see
+/// [`restore_terminal`].
+pub struct TuiApp<'a> {
+    terminal: Terminal<CrosstermBackend<io::Stdout>>,
+    // Declared after `terminal` so it is dropped after it
+    _guard: TerminalGuard,
+    config: &'a AppConfig,
+    wallpapers: Vec<Arc<Wallpaper>>,
+    history: Vec<Arc<Wallpaper>>,
+    favorites: Vec<Arc<Wallpaper>>,
+    queue: Vec<Arc<Wallpaper>>,
+    // Set when leaving with the queue for the caller to print
+    print_queue: bool,
+    // Never listed in the Wallpapers tab
+    blacklist: HashSet<PathBuf>,
+    // Monitor picked with ask_monitor for the rest of the session, the
+    // outputs the menu offered and the apply waiting on it
+    output: Option<String>,
+    outputs: Vec<String>,
+    pending_apply: Option<Action>,
+    monitor_chosen: bool,
+    // Collection shown in the Favorites tab, "" for the plain favorites, and
+    // the named ones the switcher offered
+    collection: String,
+    collections: Vec<String>,
+    selected: usize,
+    list_state: ListState,
+    search_query: LineEdit,
+    search_history: SearchHistory,
+    in_search: bool,
+    current_tab: Tab,
+    last_preview: Option<PathBuf>,
+    multi_select: bool,
+    selection: Selection,
+    dirty: bool,
+    // Set by the quit key, ends `run`
+    quit: bool,
+    // Image rendering
+    // None in text-only mode
+    picker: Option<Picker>,
+    preview_state: Option<StatefulProtocol>,
+    // Entry shown by preview_state, a spinner replaces it while they differ
+    preview_path: Option<PathBuf>,
+    loading_since: Instant,
+    // Failures are kept so broken files aren't decoded on every visit
+    preview_status: HashMap<PathBuf, PreviewStatus>,
+    spinner_frame: usize,
+    image_cache: ImageCache,
+    preview_tx: mpsc::Sender<PreviewMessage>,
+    preview_rx: mpsc::Receiver<PreviewMessage>,
+    preview_generation: PreviewGeneration,
+    preview_decodes: Arc<Semaphore>,
+    rename_state: Option<RenameState>,
+    confirm_state: Option<ConfirmState>,
+    switcher_state: Option<SwitcherState>,
+    menu_state: Option<MenuState>,
+    skip_colors: bool,
+    // pywal theme for the next applies, starts from the config
+    light: bool,
+    preview_resize: PreviewResize,
+    // Thumbnail grid, columns and first visible row are set while drawing
+    grid: bool,
+    // Display order only, favorites are saved most recent first
+    favorites_by_name: bool,
+    // Wallpapers tab narrowed to favorites for this session, with the entry
+    // highlighted when it was turned on
+    favorites_only: Option<Option<PathBuf>>,
+    // Starts from group_by_dir, toggled for the session
+    group_by_dir: bool,
+    // Dominant colors of the previewed wallpaper, computed when first shown
+    show_swatches: bool,
+    // Order of the Wallpapers tab, restored from the last run
+    sort_mode: SortMode,
+    shuffle_seed: u64,
+    search_cache: RefCell<Option<SearchCache>>,
+    // Labels of the list drawn last, by a hash of the paths and label mode
+    list_labels: (u64, Vec<String>),
+    grid_columns: usize,
+    // Visible rows of the list, set while drawing
+    list_height: usize,
+    mouse_areas: MouseAreas,
+    last_click: Option<(usize, Instant)>,
+    // Preview zoom factor and the center of the visible part, reset with
+    // every new preview
+    zoom: u32,
+    pan: (f32, f32),
+    // Pixel size of what the preview protocol was given, and the pane and
+    // mode it was cut for
+    preview_size: (u32, u32),
+    preview_cut: (Rect, PreviewResize),
+    grid_top: usize,
+    thumbnails: HashMap<PathBuf, Thumbnail>,
+    thumbnail_tx: mpsc::Sender<(PathBuf, Option<DynamicImage>)>,
+    thumbnail_rx: mpsc::Receiver<(PathBuf, Option<DynamicImage>)>,
+    // Files that failed validation, filled in by a background task
+    broken: HashSet<PathBuf>,
+    validation_rx: Option<oneshot::Receiver<HashSet<PathBuf>>>,
+    // Status line of a task running in the background, see run_in_background
+    task_rx: Option<oneshot::Receiver<String>>,
+    // Pending results while the initial scan is still running
+    scan_rx: Option<mpsc::Receiver<ScanEvent>>,
+    status: Option<String>,
+    // Renames and deletes of this session, most recent last
+    undo_stack: Vec<Box<dyn FileOp>>,
+}
+
+impl<'a> TuiApp<'a> {
+    pub fn new(
+        wallpapers: Vec<Wallpaper>,
+        scan_rx: Option<mpsc::Receiver<ScanEvent>>,
+        config: &'a AppConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let guard = TerminalGuard::new(config.mouse_support)?;
+
+        let stdout = io::stdout();
+        let backend = CrosstermBackend::new(stdout);
+        let mut terminal = Terminal::new(backend)?;
+        terminal.clear()?;
+
+        let start_tab = config.start_tab();
+
+        let picker = build_picker(&config.image_protocol);
+
+        // Initialize image cache with reasonable default size
+        let cache_size = config.image_cache_size.unwrap_or(50);
+        let image_cache =
+            ImageCache::new(cache_size, config.image_cache_mb.map(|mb| mb * 1024 * 1024));
+        let (preview_tx, preview_rx) = mpsc::channel(10);
+        let (thumbnail_tx, thumbnail_rx) = mpsc::channel(64);
+
+        // History and favorites share the scanned entries where possible
+        let wallpapers: Vec<Arc<Wallpaper>> = wallpapers.into_iter().map(Arc::new).collect();
+        let by_path: HashMap<&Path, &Arc<Wallpaper>> =
+            wallpapers.iter().map(|w| (w.path.as_path(), w)).collect();
+        let resolve = |name: &str| -> Vec<Arc<Wallpaper>> {
+            load_list(name)
+                .into_iter()
+                .map(|p| match by_path.get(p.as_path()) {
+                    Some(w) => Arc::clone(w),
+                    None => Arc::new(Wallpaper::from_path(p)),
+                })
+                .collect()
+        };
+        let history = resolve("history");
+        let collection = load_state("collection").unwrap_or_default();
+        let favorites = resolve(&collection_list(&collection));
+        let queue = resolve("queue");
+
+        let mut app = Self {
+            terminal,
+            _guard: guard,
+            config,
+            wallpapers,
+            history,
+            favorites,
+            queue,
+            print_queue: false,
+            blacklist: load_list("blacklist").into_iter().collect(),
+            output: config.output.clone(),
+            outputs: Vec::new(),
+            pending_apply: None,
+            monitor_chosen: false,
+            collection,
+            collections: Vec::new(),
+            selected: 0,
+            list_state: {
+                let mut s = ListState::default();
+                s.select(Some(0));
+                s
+            },
+            search_query: LineEdit::default(),
+            search_history: SearchHistory::load(),
+            in_search: false,
+            current_tab: start_tab,
+            last_preview: None,
+            multi_select: false,
+            selection: Selection::default(),
+            dirty: true,
+            quit: false,
+            picker,
+            preview_state: None,
+            preview_path: None,
+            loading_since: Instant::now(),
+            preview_status: HashMap::new(),
+            spinner_frame: 0,
+            image_cache,
+            preview_tx,
+            preview_rx,
+            preview_generation: PreviewGeneration::default(),
+            preview_decodes: Arc::new(Semaphore::new(PREVIEW_DECODES)),
+            rename_state: None,
+            confirm_state: None,
+            switcher_state: None,
+            menu_state: None,
+            skip_colors: false,
+            light: config.pywal_light,
+            preview_resize: PreviewResize::from_name(&config.preview_resize),
+            grid: false,
+            favorites_by_name: config.favorites_sort == "name",
+            favorites_only: None,
+            group_by_dir: config.group_by_dir,
+            show_swatches: config.show_palette,
+            sort_mode: load_state("sort")
+                .map(|s| SortMode::from_name(&s))
+                .unwrap_or(SortMode::Name),
+            shuffle_seed: shuffle_seed(),
+            search_cache: RefCell::new(None),
+            list_labels: (0, Vec::new()),
+            grid_columns: 1,
+            list_height: 1,
+            mouse_areas: MouseAreas::default(),
+            last_click: None,
+            zoom: 1,
+            pan: (0.5, 0.5),
+            preview_size: (0, 0),
+            preview_cut: (Rect::default(), PreviewResize::Fit),
+            grid_top: 0,
+            thumbnails: HashMap::new(),
+            thumbnail_tx,
+            thumbnail_rx,
+            broken: HashSet::new(),
+            validation_rx: None,
+            task_rx: None,
+            scan_rx,
+            status: None,
+            undo_stack: Vec::new(),
+        };
+        app.sort_wallpapers();
+        // A streamed scan starts validation once it's done
+        if app.scan_rx.is_none() {
+            app.start_validation();
+        }
+        Ok(app)
+    }
+
+    fn start_validation(&mut self) {
+        if !self.config.validate {
+            return;
+        }
+        let entries: Vec<_> = self
+            .wallpapers
+            .iter()
+            .map(|w| (w.path.clone(), w.modified))
+            .collect();
+        let (tx, rx) = oneshot::channel();
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(find_broken(entries));
+        });
+        self.validation_rx = Some(rx);
+    }
+
+    // Search keeps this order among equally good matches
+    fn sort_wallpapers(&mut self) {
+        match self.sort_mode {
+            SortMode::Name => self.wallpapers.sort_by_cached_key(|w| sort_key(w)),
+            SortMode::Newest => self
+                .wallpapers
+                .sort_by_key(|w| std::cmp::Reverse(w.modified)),
+            SortMode::Size => self
+                .wallpapers
+                .sort_by_key(|w| std::cmp::Reverse(w.file_size)),
+            SortMode::Random => self.wallpapers.sort_by_cached_key(|w| {
+                let mut hasher = DefaultHasher::new();
+                (self.shuffle_seed, &w.path).hash(&mut hasher);
+                hasher.finish()
+            }),
+        }
+        // Sections stay together, each in the order above
+        if self.group_by_dir {
+            let roots = &self.config.wallpaper_dirs;
+            self.wallpapers
+                .sort_by_cached_key(|w| w.relative_dir(roots));
+        }
+        *self.search_cache.get_mut() = None;
+    }
+
+    // Headers only go in the list, search results aren't grouped
+    fn grouped(&self) -> bool {
+        self.group_by_dir
+            && self.current_tab == Tab::Wallpapers
+            && self.search_query.is_empty()
+            && !self.grid
+    }
+
+    // Merges streamed scan results, keeping the highlighted entry in place
+    fn drain_scan(&mut self) {
+        let Some(rx) = self.scan_rx.as_mut() else {
+            return;
+        };
+        let mut batches = Vec::new();
+        let mut done = None;
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                ScanEvent::Batch(batch) => batches.push(batch),
+                ScanEvent::Done(result) => done = Some(result),
+            }
+        }
+        if batches.is_empty() && done.is_none() {
+            return;
+        }
+
+        if !batches.is_empty() {
+            let highlighted = self
+                .filter_items()
+                .get(self.selected)
+                .map(|w| w.path.clone());
+
+            let added: Vec<Arc<Wallpaper>> = batches.into_iter().flatten().map(Arc::new).collect();
+            // Point history, favorites and the queue at the scanned entries
+            let by_path: HashMap<&Path, &Arc<Wallpaper>> =
+                added.iter().map(|w| (w.path.as_path(), w)).collect();
+            for entry in self
+                .history
+                .iter_mut()
+                .chain(self.favorites.iter_mut())
+                .chain(self.queue.iter_mut())
+            {
+                if let Some(w) = by_path.get(entry.path.as_path()) {
+                    *entry = Arc::clone(w);
+                }
+            }
+            self.wallpapers.extend(added);
+            self.sort_wallpapers();
+
+            if self.current_tab == Tab::Wallpapers
+                && !self.multi_select
+                && let Some(path) = highlighted
+                && let Some(pos) = self.filter_items().iter().position(|w| w.path == path)
+            {
+                self.selected = pos;
+                self.list_state.select(Some(pos));
+            }
+        }
+
+        if let Some(result) = done {
+            self.scan_rx = None;
+            match result {
+                Ok(stats) => {
+                    let mut skipped = Vec::new();
+                    if stats.below_resolution > 0 {
+                        skipped.push(format!(
+                            "{} below minimum resolution",
+                            stats.below_resolution
+                        ));
+                    }
+                    if stats.wrong_aspect > 0 {
+                        skipped.push(format!(
+                            "{} not matching the aspect ratio",
+                            stats.wrong_aspect
+                        ));
+                    }
+                    if self.wallpapers.is_empty() {
+                        self.status = Some(format!(
+                            "No wallpapers found in {}",
+                            self.config.wallpaper_dirs_display()
+                        ));
+                    } else if !skipped.is_empty() {
+                        self.status = Some(format!("Skipped {}", skipped.join(", ")));
+                    }
+                }
+                Err(e) => self.status = Some(format!("Scan failed: {}", e)),
+            }
+            self.start_validation();
+        }
+
+        self.dirty = true;
+    }
+
+    /// The queue, in order, when the user left by asking to print it.
+    pub fn queue_to_print(&self) -> Option<Vec<PathBuf>> {
+        self.print_queue.then(|| paths(&self.queue))
+    }
+
+    pub async fn run(&mut self) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+        self.quit = false;
+        self.print_queue = false;
+
+        loop {
+            // Check for completed previews asynchronously
+            while let Ok((path, result)) = self.preview_rx.try_recv() {
+                match result {
+                    // Visiting it again asks anew
+                    None => {
+                        if matches!(self.preview_status.get(&path), Some(PreviewStatus::Loading)) {
+                            self.preview_status.remove(&path);
+                        }
+                    }
+                    Some(Ok(cached_image)) => {
+                        self.image_cache.insert(path.clone(), cached_image.clone());
+                        self.preview_status
+                            .insert(path.clone(), PreviewStatus::Ready);
+
+                        if self
+                            .filter_items()
+                            .get(self.selected)
+                            .is_some_and(|w| w.path == path)
+                        {
+                            self.show_preview(path, &cached_image);
+                        }
+                    }
+                    // Undecodable files can't be applied either
+                    Some(Err(e)) => {
+                        self.broken.insert(path.clone());
+                        self.preview_status
+                            .insert(path, PreviewStatus::Failed(e.to_string()));
+                        self.dirty = true;
+                    }
+                }
+            }
+
+            // Keep the spinner turning while a preview decodes
+            if self.preview_loading() && self.current_spinner_frame() != self.spinner_frame {
+                self.dirty = true;
+            }
+
+            while let Ok((path, image)) = self.thumbnail_rx.try_recv() {
+                let thumbnail = match (image, &mut self.picker) {
+                    (Some(image), Some(picker)) => {
+                        Thumbnail::Ready(picker.new_resize_protocol(image))
+                    }
+                    _ => Thumbnail::Failed,
+                };
+                self.thumbnails.insert(path, thumbnail);
+                self.dirty = true;
+            }
+
+            self.drain_scan();
+
+            if let Some(rx) = self.validation_rx.as_mut()
+                && let Ok(broken) = rx.try_recv()
+            {
+                self.broken.extend(broken);
+                self.validation_rx = None;
+                self.dirty = true;
+            }
+            if let Some(rx) = self.task_rx.as_mut()
+                && let Ok(message) = rx.try_recv()
+            {
+                self.status = Some(message);
+                self.task_rx = None;
+                self.dirty = true;
+            }
+
+            let filtered = self.filter_items();
+            self.adjust_selection(&filtered);
+
+            if self.dirty {
+                self.draw_ui(&filtered)?;
+                self.dirty = false;
             }
 
             if event::poll(std::time::Duration::from_millis(16))? {
                 if let Some(selected) = self.handle_event(&filtered)? {
-                    return Ok(selected);
+                    return Ok(Some(selected));
+                }
+                if self.quit {
+                    return Ok(None);
                 }
 
                 self.dirty = true;
@@ -312,59 +1474,248 @@ impl<'a> TuiApp<'a> {
             tokio::task::yield_now().await;
         }
     }
-    fn request_preview(&self, path: PathBuf) {
-        let tx = self.preview_tx.clone();
-        let path_clone = path.clone();
+    fn show_preview(&mut self, path: PathBuf, cached_image: &CachedImage) {
+        self.stash_preview();
+        self.zoom = 1;
+        self.pan = (0.5, 0.5);
+        match self.image_cache.take_protocol(&path, self.preview_cut) {
+            Some(cached) => {
+                self.preview_size = cached.size;
+                self.preview_state = Some(cached.protocol);
+                self.preview_path = Some(path);
+                self.dirty = true;
+            }
+            None => {
+                self.preview_path = Some(path);
+                self.refresh_preview(&cached_image.image);
+            }
+        }
+    }
 
-        tokio::spawn(async move {
-            let result = tokio::task::spawn_blocking(move || {
-                let extension = path_clone
-                    .extension()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("")
-                    .to_lowercase();
+    // Hands the shown protocol back to the cache, zoomed views aren't kept
+    fn stash_preview(&mut self) {
+        if self.zoom != 1 {
+            return;
+        }
+        if let Some(path) = self.preview_path.clone()
+            && let Some(protocol) = self.preview_state.take()
+        {
+            self.image_cache.keep_protocol(
+                path,
+                CachedProtocol {
+                    cut: self.preview_cut,
+                    size: self.preview_size,
+                    protocol,
+                },
+            );
+        }
+    }
 
-                // Check if it's a video file
-                if ["mp4", "avi", "mov", "mkv", "webm"].contains(&extension.as_str()) {
-                    match CachedImage::new(&path_clone) {
-                        Ok(cached_image) => Ok(cached_image),
-                        Err(_) => {
-                            // Fallback to video placeholder if extraction fails
-                            Ok(CachedImage {
-                                image: Arc::new(CachedImage::create_video_placeholder()),
-                                is_video: true,
-                            })
-                        }
-                    }
-                } else {
-                    // Regular image file
-                    CachedImage::new(&path_clone)
+    // Zoom keys, and arrows while zoomed in. False leaves the key to the list.
+    fn zoom_key(&mut self, key: &KeyEvent) -> bool {
+        if self.in_search
+            || self.grid
+            || self.picker.is_none()
+            || !matches!(self.preview_status(), Some(PreviewStatus::Ready))
+        {
+            return false;
+        }
+        let k = &self.config.keybindings;
+        // A quarter of the visible part per arrow press
+        let step = 0.25 / self.zoom as f32;
+        match key.code {
+            KeyCode::Char(c) if c == k.zoom_in => self.zoom = (self.zoom * 2).min(MAX_ZOOM),
+            KeyCode::Char(c) if c == k.zoom_out => self.zoom = (self.zoom / 2).max(1),
+            _ if self.zoom == 1 => return false,
+            KeyCode::Left => self.pan.0 -= step,
+            KeyCode::Right => self.pan.0 += step,
+            KeyCode::Up => self.pan.1 -= step,
+            KeyCode::Down => self.pan.1 += step,
+            _ => return false,
+        }
+        self.reload_preview();
+        true
+    }
+
+    // Shows the cached preview again after the zoom, the pan or the pane
+    // changed
+    fn reload_preview(&mut self) {
+        let Some(image) = self
+            .preview_path
+            .as_ref()
+            .and_then(|path| self.image_cache.peek(path))
+            .map(|cached| Arc::clone(&cached.image))
+        else {
+            return;
+        };
+        self.refresh_preview(&image);
+    }
+
+    // Feeds the visible part of `image` to a new protocol: the zoomed-in part
+    // around the pan, which is the center of it as a fraction of the image,
+    // then cut to the pane's shape when filling its width or height
+    fn refresh_preview(&mut self, image: &DynamicImage) {
+        let Some(picker) = self.picker.as_mut() else {
+            return;
+        };
+        let half = 0.5 / self.zoom as f32;
+        self.pan = (
+            self.pan.0.clamp(half, 1.0 - half),
+            self.pan.1.clamp(half, 1.0 - half),
+        );
+
+        let (width, height) = (image.width(), image.height());
+        let mut crop_width = (width / self.zoom).max(1);
+        let mut crop_height = (height / self.zoom).max(1);
+        let (font_width, font_height) = picker.font_size();
+        let pane = self.preview_cut.0;
+        if pane.width > 0 && pane.height > 0 {
+            let pane_aspect = (pane.width * font_width) as f32 / (pane.height * font_height) as f32;
+            match self.preview_resize.view_aspect(
+                crop_width as f32,
+                crop_height as f32,
+                pane_aspect,
+            ) {
+                Some(aspect) if self.preview_resize == PreviewResize::Width => {
+                    crop_height = ((crop_width as f32 / aspect) as u32).max(1);
                 }
-            })
-            .await
-            .unwrap_or_else(|e| Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>));
+                Some(aspect) => crop_width = ((crop_height as f32 * aspect) as u32).max(1),
+                None => {}
+            }
+        }
+
+        let x = (self.pan.0 * width as f32 - crop_width as f32 / 2.0).max(0.0) as u32;
+        let y = (self.pan.1 * height as f32 - crop_height as f32 / 2.0).max(0.0) as u32;
+        let crop = if (crop_width, crop_height) == (width, height) {
+            image.clone()
+        } else {
+            image.crop_imm(
+                x.min(width - crop_width),
+                y.min(height - crop_height),
+                crop_width,
+                crop_height,
+            )
+        };
+        self.preview_size = (crop_width, crop_height);
+        self.preview_state = Some(picker.new_resize_protocol(crop));
+        self.dirty = true;
+    }
+
+    // Status of the highlighted entry, Ready once its image is on screen
+    fn preview_status(&self) -> Option<PreviewStatus> {
+        let path = self.last_preview.as_ref()?;
+        if self.preview_path.as_ref() == Some(path) {
+            return Some(PreviewStatus::Ready);
+        }
+        match self.preview_status.get(path) {
+            Some(PreviewStatus::Failed(reason)) => Some(PreviewStatus::Failed(reason.clone())),
+            _ => Some(PreviewStatus::Loading),
+        }
+    }
+
+    fn preview_loading(&self) -> bool {
+        !self.grid && matches!(self.preview_status(), Some(PreviewStatus::Loading))
+    }
+
+    fn current_spinner_frame(&self) -> usize {
+        (self.loading_since.elapsed().as_millis() / SPINNER_INTERVAL_MS) as usize % SPINNER.len()
+    }
 
-            let _ = tx.send((path, result)).await;
+    // Whether the color generation step was switched off from the TUI
+    pub fn skip_colors(&self) -> bool {
+        self.skip_colors
+    }
+
+    pub fn light(&self) -> bool {
+        self.light
+    }
+
+    // Monitor to apply to, --output or the last ask_monitor pick
+    pub fn output(&self) -> Option<String> {
+        self.output.clone()
+    }
+
+    // Shown on the status line at the next draw
+    pub fn set_status(&mut self, message: String) {
+        self.status = Some(message);
+    }
+
+    /// Shows `message` while `task` runs on a blocking thread, then the
+    /// status line it returns.
+    pub fn run_in_background(
+        &mut self,
+        message: String,
+        task: impl FnOnce() -> String + Send + 'static,
+    ) {
+        let (tx, rx) = oneshot::channel();
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(task());
+        });
+        self.status = Some(message);
+        self.task_rx = Some(rx);
+    }
+
+    // Decodes `path`, then preloads `neighbours` in order. Only the latest
+    // request does either, earlier ones give up at the next step.
+    fn request_preview(&self, path: Option<PathBuf>, neighbours: Vec<PathBuf>) {
+        let generation = self.preview_generation.next();
+        if path.is_none() && neighbours.is_empty() {
+            return;
+        }
+        spawn_preview(
+            PreviewJob {
+                path,
+                neighbours,
+                generation,
+                max_edge: self.config.preview_max_size,
+            },
+            self.preview_generation.clone(),
+            self.preview_decodes.clone(),
+            self.preview_tx.clone(),
+        );
+    }
+
+    // Entries around the highlighted one that aren't decoded yet, nearest
+    // first. Fewer than the cache holds, so they don't push out the selection.
+    fn preload_window(&self, filtered: &[Arc<Wallpaper>]) -> Vec<PathBuf> {
+        let mut neighbours = Vec::new();
+        for distance in 1..=PRELOAD_RADIUS {
+            let below = self.selected.checked_add(distance);
+            let above = self.selected.checked_sub(distance);
+            for index in [below, above].into_iter().flatten() {
+                if let Some(w) = filtered.get(index)
+                    && self.image_cache.peek(&w.path).is_none()
+                    && !matches!(
+                        self.preview_status.get(&w.path),
+                        Some(PreviewStatus::Failed(_))
+                    )
+                {
+                    neighbours.push(w.path.clone());
+                }
+            }
+        }
+        neighbours.truncate(self.image_cache.max_size.saturating_sub(1));
+        neighbours
+    }
+    fn request_thumbnail(&mut self, wallpaper: &Wallpaper) {
+        self.thumbnails
+            .insert(wallpaper.path.clone(), Thumbnail::Pending);
+
+        let tx = self.thumbnail_tx.clone();
+        let path = wallpaper.path.clone();
+        let modified = wallpaper.modified;
+        tokio::task::spawn_blocking(move || {
+            let image = load_thumbnail(&path, modified);
+            let _ = tx.blocking_send((path, image));
         });
     }
+
     // --------------------
     // Tab management
     // --------------------
 
     fn active_tabs(&self) -> Vec<Tab> {
-        if !self.config.tabs.is_empty() {
-            let out: Vec<Tab> = self
-                .config
-                .tabs
-                .iter()
-                .filter(|t| t.enabled)
-                .map(|t| t.tab)
-                .collect();
-            if !out.is_empty() {
-                return out;
-            }
-        }
-        vec![Tab::Wallpapers, Tab::History, Tab::Favorites]
+        self.config.active_tabs()
     }
 
     fn current_tab_index(&self) -> usize {
@@ -378,32 +1729,127 @@ impl<'a> TuiApp<'a> {
     // Filtering & selection
     // --------------------
 
-    fn filter_items(&self) -> Vec<PathBuf> {
+    fn filter_items(&self) -> Vec<Arc<Wallpaper>> {
         match self.current_tab {
             Tab::Wallpapers => {
-                if self.search_query.is_empty() {
+                let mut items = if self.search_query.is_empty() {
                     self.wallpapers.clone()
                 } else {
-                    let q = self.search_query.to_lowercase();
-                    self.wallpapers
-                        .iter()
-                        .filter(|p| {
-                            p.file_name()
-                                .unwrap()
-                                .to_string_lossy()
-                                .to_lowercase()
-                                .contains(&q)
-                        })
-                        .cloned()
-                        .collect()
+                    let mut cache = self.search_cache.borrow_mut();
+                    if cache
+                        .as_ref()
+                        .is_none_or(|c| c.query != self.search_query.text())
+                    {
+                        *cache = Some(self.search());
+                    }
+                    cache
+                        .as_ref()
+                        .map(|c| c.results.clone())
+                        .unwrap_or_default()
+                };
+                if !self.blacklist.is_empty() {
+                    items.retain(|w| !self.blacklist.contains(&w.path));
+                }
+                if self.favorites_only.is_some() {
+                    let favorites: HashSet<&Path> =
+                        self.favorites.iter().map(|w| w.path.as_path()).collect();
+                    items.retain(|w| favorites.contains(w.path.as_path()));
                 }
+                items
             }
             Tab::History => self.history.clone(),
-            Tab::Favorites => self.favorites.clone(),
+            Tab::Favorites => {
+                let mut favorites = self.favorites.clone();
+                if self.favorites_by_name {
+                    favorites.sort_by_cached_key(|w| sort_key(w));
+                }
+                favorites
+            }
+            Tab::Queue => self.queue.clone(),
         }
     }
 
-    fn adjust_selection(&mut self, filtered: &[PathBuf]) {
+    fn search(&self) -> SearchCache {
+        let matcher = if self.config.search_mode == "substring" {
+            substring_match
+        } else {
+            fuzzy_match
+        };
+        let mut scored: Vec<(i64, Vec<usize>, &Arc<Wallpaper>)> = self
+            .wallpapers
+            .iter()
+            .filter_map(|w| {
+                matcher(
+                    &w.relative_name(&self.config.wallpaper_dirs),
+                    self.search_query.text(),
+                )
+                .map(|(score, positions)| (score, positions, w))
+            })
+            .collect();
+
+        // Best matches first, directory order among equal scores
+        scored.sort_by_key(|(score, _, _)| std::cmp::Reverse(*score));
+
+        let mut results = Vec::with_capacity(scored.len());
+        let mut positions = HashMap::with_capacity(scored.len());
+        for (_, matched, w) in scored {
+            positions.insert(w.path.clone(), matched);
+            results.push(Arc::clone(w));
+        }
+        SearchCache {
+            query: self.search_query.text().to_string(),
+            results,
+            positions,
+        }
+    }
+
+    fn switcher_results(&self, query: &str) -> Vec<Arc<Wallpaper>> {
+        let mut scored: Vec<(i64, &Arc<Wallpaper>)> = self
+            .wallpapers
+            .iter()
+            .filter(|w| !self.blacklist.contains(&w.path))
+            .filter_map(|w| fuzzy_score(&w.file_name(), query).map(|score| (score, w)))
+            .collect();
+
+        // Stable sort keeps directory order among equal scores
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored
+            .into_iter()
+            .take(SWITCHER_RESULTS)
+            .map(|(_, w)| Arc::clone(w))
+            .collect()
+    }
+
+    // Highlights `pick` in the Wallpapers tab, without a search or the
+    // favorites-only view hiding it, and selects it like Enter there
+    fn select_in_wallpapers(
+        &mut self,
+        pick: &Arc<Wallpaper>,
+    ) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+        if let Some(action) = switch_tab(
+            Tab::Wallpapers,
+            &mut self.current_tab,
+            &mut self.selected,
+            &mut self.list_state,
+            &mut self.multi_select,
+            &mut self.selection,
+        ) {
+            self.perform(action, &[])?;
+        }
+        self.in_search = false;
+        self.search_query.clear();
+        self.favorites_only = None;
+
+        let filtered = self.filter_items();
+        let Some(index) = filtered.iter().position(|w| w.path == pick.path) else {
+            return Ok(None);
+        };
+        self.selected = index;
+        self.list_state.select(Some(index));
+        self.perform(Action::Select, &filtered)
+    }
+
+    fn adjust_selection(&mut self, filtered: &[Arc<Wallpaper>]) {
         if filtered.is_empty() {
             self.selected = 0;
             self.list_state.select(None);
@@ -420,68 +1866,727 @@ impl<'a> TuiApp<'a> {
     // --------------------
 
     fn rename_wallpaper(&mut self, old_path: &Path, new_name: &str) -> io::Result<PathBuf> {
-        let parent_dir = old_path
-            .parent()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid file path"))?;
+        let op = ops::rename(old_path, new_name)?;
+        let new_path = op.new_path().to_path_buf();
+
+        // Update all references to the old path
+        self.update_path_references(&[(old_path.to_path_buf(), new_path.clone())]);
+        self.push_undo(Box::new(op));
+
+        Ok(new_path)
+    }
+
+    // Every reference follows its file, undone as a whole. Returns how many
+    // files got a new name and the ones that couldn't be renamed.
+    fn batch_rename(
+        &mut self,
+        paths: &[PathBuf],
+        pattern: &str,
+    ) -> io::Result<(usize, Vec<(PathBuf, String)>)> {
+        let (op, failed) = ops::batch_rename(paths, pattern)?;
+        let renamed = op.renamed();
+        self.update_path_references(&renamed);
+        if !renamed.is_empty() {
+            self.push_undo(Box::new(op));
+        }
+        Ok((renamed.len(), failed))
+    }
+
+    // Applies without leaving the TUI, the terminal is handed back to the
+    // backends while they run so their output can't mess up the screen
+    fn apply_in_place(&mut self, path: &Path) -> io::Result<()> {
+        if self.broken.contains(path) {
+            self.status = Some(broken_message(path));
+            return Ok(());
+        }
+
+        let mut config = self.config.clone();
+        config.pywal_light = self.light;
+        config.output = self.output.clone();
+        if self.skip_colors {
+            config.pywal = false;
+            config.hellwal = false;
+        }
+
+        let result = self.outside(|| apply_wallpaper(path, config.output.as_deref(), &config))?;
 
-        let mut new_path = parent_dir.join(new_name);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.status = Some(match result {
+            Ok(()) => format!(" Applied {} ", name),
+            Err(e) => format!(" Failed to apply {}: {} ", name, e),
+        });
+        Ok(())
+    }
+
+    // Colors follow the light toggle but not skip_colors, asking for them is the point
+    fn recolor(&mut self) -> io::Result<()> {
+        let Some(path) = load_state("current").map(PathBuf::from) else {
+            self.status = Some(" No wallpaper applied yet ".into());
+            return Ok(());
+        };
+        if !self.config.pywal && !self.config.hellwal {
+            self.status = Some(" pywal and hellwal are both off in the config ".into());
+            return Ok(());
+        }
+
+        let mut config = self.config.clone();
+        config.pywal_light = self.light;
+        let result = self.outside(|| {
+            generate_colors(&path, &config)?;
+            run_reload_command(&config);
+            Ok::<_, Box<dyn std::error::Error>>(())
+        })?;
+
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.status = Some(match result {
+            Ok(()) => format!(" Regenerated colors from {} ", name),
+            Err(e) => format!(" Failed to regenerate colors: {} ", e),
+        });
+        Ok(())
+    }
+
+    // Runs `f` with the terminal restored, pywal and friends print to it
+    fn outside<T>(&mut self, f: impl FnOnce() -> T) -> io::Result<T> {
+        restore_terminal();
+        let result = f();
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        if self.config.mouse_support {
+            execute!(io::stdout(), EnableMouseCapture)?;
+        }
+        self.terminal.clear()?;
+        Ok(result)
+    }
 
-        // Add file extension if missing
-        if let Some(ext) = old_path.extension()
-            && new_path.extension().is_none()
+    // The plain favorites, then every named collection and a way to add one
+    fn open_collections(&mut self) {
+        self.collections = list_collections();
+        if !self.collection.is_empty() && !self.collections.contains(&self.collection) {
+            self.collections.push(self.collection.clone());
+            self.collections.sort();
+        }
+
+        let mut items = vec![("Favorites".to_string(), Action::Collection(0))];
+        items.extend(
+            self.collections
+                .iter()
+                .enumerate()
+                .map(|(i, name)| (name.clone(), Action::Collection(i + 1))),
+        );
+        items.push(("New collection…".to_string(), Action::NewCollection));
+        let selected = self
+            .collections
+            .iter()
+            .position(|name| *name == self.collection)
+            .map_or(0, |i| i + 1);
+        self.menu_state = Some(MenuState {
+            anchor: self.entry_anchor(),
+            selected,
+            items,
+        });
+    }
+
+    // Loads the collection into the Favorites tab, remembered for next time
+    // A saved list, sharing the scanned entries where possible
+    fn resolve_list(&self, name: &str) -> Vec<Arc<Wallpaper>> {
+        let by_path: HashMap<&Path, &Arc<Wallpaper>> = self
+            .wallpapers
+            .iter()
+            .map(|w| (w.path.as_path(), w))
+            .collect();
+        load_list(name)
+            .into_iter()
+            .map(|p| match by_path.get(p.as_path()) {
+                Some(w) => Arc::clone(w),
+                None => Arc::new(Wallpaper::from_path(p)),
+            })
+            .collect()
+    }
+
+    fn switch_collection(&mut self, collection: String) {
+        self.favorites = self.resolve_list(&collection_list(&collection));
+        save_state("collection", &collection);
+        self.collection = collection;
+        self.selection.clear();
+        self.selected = 0;
+        self.list_state.select(Some(0));
+        self.status = Some(format!(" Collection: {} ", self.collection_label()));
+    }
+
+    fn collection_label(&self) -> &str {
+        if self.collection.is_empty() {
+            "Favorites"
+        } else {
+            &self.collection
+        }
+    }
+
+    // Turning it off goes back to the wallpaper highlighted before it was on
+    fn toggle_favorites_only(&mut self, filtered: &[Arc<Wallpaper>]) {
+        let highlighted = filtered.get(self.selected).map(|w| w.path.clone());
+        let target = match self.favorites_only.take() {
+            Some(previous) => previous,
+            None => {
+                self.favorites_only = Some(highlighted.clone());
+                highlighted
+            }
+        };
+        let position =
+            target.and_then(|path| self.filter_items().iter().position(|w| w.path == path));
+        self.selected = position.unwrap_or(0);
+        self.list_state.select(Some(self.selected));
+        self.dirty = true;
+    }
+
+    // Opens the monitor menu for Select or Apply when ask_monitor is on and
+    // more than one output is found, false to apply right away. The last
+    // pick is highlighted.
+    fn ask_monitor(&mut self, action: Action) -> bool {
+        if !self.config.ask_monitor || self.config.output.is_some() || !targets_outputs(self.config)
         {
-            new_path.set_extension(ext);
+            return false;
+        }
+        let outputs = connected_outputs(self.config).unwrap_or_default();
+        if outputs.len() < 2 {
+            return false;
+        }
+
+        let mut items = vec![("All monitors".to_string(), Action::Output(0))];
+        items.extend(
+            outputs
+                .iter()
+                .enumerate()
+                .map(|(i, name)| (name.clone(), Action::Output(i + 1))),
+        );
+        let selected = self
+            .output
+            .as_ref()
+            .and_then(|output| outputs.iter().position(|name| name == output))
+            .map_or(0, |i| i + 1);
+        self.outputs = outputs;
+        self.pending_apply = Some(action);
+        self.menu_state = Some(MenuState {
+            anchor: self.entry_anchor(),
+            selected,
+            items,
+        });
+        true
+    }
+
+    // Runs an action on the highlighted entry, or on the multi-select picks
+    // where that makes sense. Returns the wallpaper to apply on Select.
+    fn perform(
+        &mut self,
+        action: Action,
+        filtered: &[Arc<Wallpaper>],
+    ) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+        if action == Action::Quit {
+            self.quit = true;
+            return Ok(None);
+        }
+        if action == Action::FavoritesOnly {
+            self.toggle_favorites_only(filtered);
+            return Ok(None);
+        }
+        if action == Action::TabSwitched {
+            self.favorites_only = None;
+            return Ok(None);
+        }
+        if action == Action::GroupByDir {
+            let highlighted = filtered.get(self.selected).map(|w| w.path.clone());
+            self.group_by_dir = !self.group_by_dir;
+            self.sort_wallpapers();
+            if let Some(pos) =
+                highlighted.and_then(|path| self.filter_items().iter().position(|w| w.path == path))
+            {
+                self.selected = pos;
+                self.list_state.select(Some(pos));
+            }
+            return Ok(None);
+        }
+        if action == Action::Recolor {
+            self.recolor()?;
+            return Ok(None);
+        }
+        if action == Action::PrintQueue {
+            self.quit = true;
+            self.print_queue = true;
+            return Ok(None);
+        }
+        // Collections work on an empty Favorites tab too
+        match action {
+            Action::Collections => {
+                self.open_collections();
+                return Ok(None);
+            }
+            Action::Collection(index) => {
+                let collection = index
+                    .checked_sub(1)
+                    .and_then(|i| self.collections.get(i).cloned())
+                    .unwrap_or_default();
+                self.switch_collection(collection);
+                return Ok(None);
+            }
+            Action::NewCollection => {
+                self.rename_state = Some(RenameState {
+                    original_path: PathBuf::new(),
+                    input: LineEdit::default(),
+                    error: None,
+                    action: PromptAction::Collection,
+                    batch: Vec::new(),
+                });
+                return Ok(None);
+            }
+            _ => {}
+        }
+        let Some(highlighted) = filtered.get(self.selected).cloned() else {
+            // Only these make sense on an empty list
+            match action {
+                Action::Undo => self.undo(),
+                Action::Switcher => {
+                    self.switcher_state = Some(SwitcherState::default());
+                }
+                _ => {}
+            }
+            return Ok(None);
+        };
+
+        match action {
+            Action::Select | Action::Apply => {
+                // Comes back through Action::Output once a monitor is picked
+                if !std::mem::take(&mut self.monitor_chosen) && self.ask_monitor(action) {
+                    return Ok(None);
+                }
+                if self.config.enable_history {
+                    record_history(
+                        &mut self.history,
+                        &highlighted,
+                        self.current_tab,
+                        &self.broken,
+                    );
+                }
+                if self.broken.contains(&highlighted.path) {
+                    self.status = Some(broken_message(&highlighted.path));
+                } else if action == Action::Select {
+                    return Ok(Some(highlighted.path.clone()));
+                } else {
+                    self.apply_in_place(&highlighted.path)?;
+                }
+            }
+            Action::Output(index) => {
+                self.output = index
+                    .checked_sub(1)
+                    .and_then(|i| self.outputs.get(i).cloned());
+                if let Some(pending) = self.pending_apply.take() {
+                    self.monitor_chosen = true;
+                    return self.perform(pending, filtered);
+                }
+            }
+            Action::Favorite => {
+                let picked = self.selection.in_list(filtered);
+                let targets: Vec<Arc<Wallpaper>> = if self.multi_select && !picked.is_empty() {
+                    picked.into_iter().cloned().collect()
+                } else {
+                    vec![highlighted]
+                };
+                for item in targets {
+                    if self.favorites.contains(&item) {
+                        self.favorites.retain(|p| p != &item);
+                    } else {
+                        self.favorites.insert(0, item);
+                    }
+                }
+                save_list(&collection_list(&self.collection), &paths(&self.favorites));
+            }
+            Action::Rename | Action::Copy | Action::Convert => {
+                let picked = self.selection.in_list(filtered);
+                let batch: Vec<PathBuf> =
+                    if action == Action::Rename && self.multi_select && !picked.is_empty() {
+                        picked.iter().map(|w| w.path.clone()).collect()
+                    } else {
+                        Vec::new()
+                    };
+                self.rename_state = Some(RenameState {
+                    original_path: highlighted.path.clone(),
+                    input: LineEdit::default(),
+                    error: None,
+                    action: match action {
+                        Action::Copy => PromptAction::Copy,
+                        Action::Convert => PromptAction::Convert,
+                        _ if !batch.is_empty() => PromptAction::BatchRename,
+                        _ => PromptAction::Rename,
+                    },
+                    batch,
+                });
+            }
+            Action::Delete => {
+                let picked = self.selection.in_list(filtered);
+                let paths = if self.multi_select && !picked.is_empty() {
+                    picked.iter().map(|w| w.path.clone()).collect()
+                } else {
+                    vec![highlighted.path.clone()]
+                };
+                self.confirm_state = Some(ConfirmState {
+                    action: Confirm::Trash(paths),
+                    error: None,
+                });
+            }
+            Action::Blacklist => {
+                let picked = self.selection.in_list(filtered);
+                let targets: Vec<Arc<Wallpaper>> = if self.multi_select && !picked.is_empty() {
+                    picked.into_iter().cloned().collect()
+                } else {
+                    vec![highlighted]
+                };
+                // Selected entries stay picked but drop out of the list
+                self.blacklist
+                    .extend(targets.iter().map(|w| w.path.clone()));
+                let mut blacklist: Vec<PathBuf> = self.blacklist.iter().cloned().collect();
+                blacklist.sort();
+                save_list("blacklist", &blacklist);
+                self.status = Some(format!(
+                    " Hid {}, wallrs --show-blacklist lists them ",
+                    targets.len()
+                ));
+            }
+            Action::Enqueue => {
+                let picked = self.selection.in_list(filtered);
+                let targets: Vec<Arc<Wallpaper>> = if self.multi_select && !picked.is_empty() {
+                    picked.into_iter().cloned().collect()
+                } else {
+                    vec![highlighted]
+                };
+                let mut added = 0;
+                for item in targets {
+                    if !self.queue.contains(&item) {
+                        self.queue.push(item);
+                        added += 1;
+                    }
+                }
+                save_list("queue", &paths(&self.queue));
+                self.status = Some(format!(
+                    " Queued {} ({} in the queue) ",
+                    added,
+                    self.queue.len()
+                ));
+            }
+            Action::Edit => {
+                self.menu_state = Some(MenuState {
+                    anchor: self.entry_anchor(),
+                    selected: 0,
+                    items: vec![
+                        ("Rotate clockwise".into(), Action::RotateClockwise),
+                        (
+                            "Rotate counter-clockwise".into(),
+                            Action::RotateCounterClockwise,
+                        ),
+                        ("Convert format…".into(), Action::Convert),
+                    ],
+                });
+            }
+            Action::RotateClockwise | Action::RotateCounterClockwise => {
+                let path = &highlighted.path;
+                match ops::rotate(path, action == Action::RotateClockwise) {
+                    Ok(()) => {
+                        self.reload_wallpaper(path);
+                        self.status = Some(format!(" Rotated {} ", highlighted.file_name()));
+                    }
+                    Err(e) => self.status = Some(format!(" Can't rotate: {} ", e)),
+                }
+            }
+            Action::Reveal => {
+                if let Err(e) = reveal_in_file_manager(&highlighted.path, self.config) {
+                    self.status = Some(format!("Can't open file manager: {}", e));
+                }
+            }
+            Action::Sort => {
+                self.sort_mode = self.sort_mode.next();
+                if self.sort_mode == SortMode::Random {
+                    self.shuffle_seed = shuffle_seed();
+                }
+                self.sort_wallpapers();
+                save_state("sort", self.sort_mode.name());
+
+                if let Some(pos) = self
+                    .filter_items()
+                    .iter()
+                    .position(|w| w.path == highlighted.path)
+                {
+                    self.selected = pos;
+                    self.list_state.select(Some(pos));
+                }
+            }
+            Action::Undo => self.undo(),
+            Action::ClearList => {
+                self.confirm_state = Some(ConfirmState {
+                    action: Confirm::ClearList(self.current_tab),
+                    error: None,
+                });
+            }
+            Action::Switcher => {
+                self.switcher_state = Some(SwitcherState::default());
+            }
+            Action::Quit
+            | Action::PrintQueue
+            | Action::FavoritesOnly
+            | Action::TabSwitched
+            | Action::GroupByDir
+            | Action::Recolor
+            | Action::Collections
+            | Action::Collection(_)
+            | Action::NewCollection => {}
+        }
+        Ok(None)
+    }
+
+    fn clear_list(&mut self, tab: Tab) {
+        match tab {
+            Tab::History => {
+                self.history.clear();
+                save_list("history", &[]);
+            }
+            Tab::Favorites => {
+                self.favorites.clear();
+                save_list(&collection_list(&self.collection), &[]);
+            }
+            Tab::Queue => {
+                self.queue.clear();
+                save_list("queue", &[]);
+            }
+            Tab::Wallpapers => return,
         }
+        self.selection.clear();
+        self.selected = 0;
+        self.list_state.select(Some(0));
+    }
+
+    fn push_undo(&mut self, op: Box<dyn FileOp>) {
+        if self.undo_stack.len() == UNDO_DEPTH {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(op);
+    }
+
+    // Operations that can't be undone yet, e.g. because the old name is
+    // taken, stay on the stack
+    fn undo(&mut self) {
+        let Some(op) = self.undo_stack.pop() else {
+            self.status = Some(" Nothing to undo ".into());
+            return;
+        };
+
+        match op.undo() {
+            Ok(Undone::Renamed(renamed)) => {
+                self.update_path_references(&renamed);
+            }
+            Ok(Undone::Restored(paths, lists)) => {
+                for path in paths {
+                    if self
+                        .config
+                        .wallpaper_dirs
+                        .iter()
+                        .any(|root| path.starts_with(root))
+                    {
+                        self.wallpapers.push(Arc::new(Wallpaper::from_path(path)));
+                    }
+                }
+                self.sort_wallpapers();
+
+                // Back where they were in history, favorites and the queue
+                for (list, index, entry) in lists.into_iter().rev() {
+                    insert_into_list(&list, index, entry);
+                }
+                self.history = self.resolve_list("history");
+                self.favorites = self.resolve_list(&collection_list(&self.collection));
+                self.queue = self.resolve_list("queue");
+            }
+            Err(e) => {
+                self.status = Some(format!(" Can't undo {}: {} ", op.describe(), e));
+                self.undo_stack.push(op);
+                return;
+            }
+        }
+        self.status = Some(format!(" Undid {} ", op.describe()));
+    }
 
-        // Check if new name already exists
-        if new_path.exists() {
+    // Copies into `folder`, relative to the wallpaper's root, keeping the original.
+    // Nothing in the lists changes.
+    fn copy_wallpaper(&self, path: &Path, folder: &str) -> io::Result<PathBuf> {
+        let root = self
+            .config
+            .wallpaper_dirs
+            .iter()
+            .find(|root| path.starts_with(root))
+            .or(self.config.wallpaper_dirs.first())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No wallpaper directory"))?;
+        let dir = expand_path(folder, root);
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid file path"))?;
+        let target = dir.join(file_name);
+
+        if target.exists() {
             return Err(io::Error::new(
                 io::ErrorKind::AlreadyExists,
-                "File with that name already exists",
+                "File already exists in that folder",
             ));
         }
 
-        fs::rename(old_path, &new_path)?;
-
-        // Update all references to the old path
-        self.update_path_references(old_path, &new_path);
-
-        Ok(new_path)
+        fs::create_dir_all(&dir)?;
+        fs::copy(path, &target)?;
+        Ok(target)
     }
 
-    fn update_path_references(&mut self, old_path: &Path, new_path: &PathBuf) {
+    // Old and new path of each renamed file, all followed at once so files
+    // may swap names
+    fn update_path_references(&mut self, renamed: &[(PathBuf, PathBuf)]) {
+        let new_paths: HashMap<&Path, &Path> = renamed
+            .iter()
+            .map(|(old_path, new_path)| (old_path.as_path(), new_path.as_path()))
+            .collect();
+        // Whether anything in `list` was renamed
+        let follow = |list: &mut Vec<Arc<Wallpaper>>| -> bool {
+            let mut changed = false;
+            for w in list.iter_mut() {
+                if let Some(new_path) = new_paths.get(w.path.as_path()) {
+                    *w = Arc::new(w.renamed(new_path.to_path_buf()));
+                    changed = true;
+                }
+            }
+            changed
+        };
+
         // Update wallpapers list
-        if let Some(pos) = self.wallpapers.iter().position(|p| p == old_path) {
-            self.wallpapers[pos] = new_path.clone();
+        if follow(&mut self.wallpapers) {
+            *self.search_cache.get_mut() = None;
         }
 
         // Update history
-        if let Some(pos) = self.history.iter().position(|p| p == old_path) {
-            self.history[pos] = new_path.clone();
+        if follow(&mut self.history) {
+            save_list("history", &paths(&self.history));
+        }
+
+        // Update favorites, in every collection and not just the shown one
+        follow(&mut self.favorites);
+        for list in favorites_lists() {
+            rename_in_list(&list, renamed);
         }
 
-        // Update favorites
-        if let Some(pos) = self.favorites.iter().position(|p| p == old_path) {
-            self.favorites[pos] = new_path.clone();
-            save_list("favorites.txt", &self.favorites);
+        // Update queue
+        if follow(&mut self.queue) {
+            save_list("queue", &paths(&self.queue));
         }
 
+        self.selection.rename(renamed);
+
         // Update image cache
-        if let Some(image) = self.image_cache.cache.remove(old_path) {
-            self.image_cache.cache.insert(new_path.clone(), image);
+        self.image_cache.rename(renamed);
+        let statuses: Vec<(PathBuf, Option<PreviewStatus>)> = renamed
+            .iter()
+            .map(|(old_path, new_path)| {
+                self.thumbnails.remove(old_path);
+                (new_path.clone(), self.preview_status.remove(old_path))
+            })
+            .collect();
+        for (new_path, status) in statuses {
+            if let Some(status) = status {
+                self.preview_status.insert(new_path, status);
+            }
         }
 
         // Update last_preview if it was the renamed file
-        if self.last_preview.as_ref() == Some(&PathBuf::from(old_path)) {
-            self.last_preview = Some(new_path.clone());
+        let follow_path = |path: &mut Option<PathBuf>| {
+            if let Some(new_path) = path.as_deref().and_then(|p| new_paths.get(p)) {
+                *path = Some(new_path.to_path_buf());
+            }
+        };
+        follow_path(&mut self.last_preview);
+        follow_path(&mut self.preview_path);
+    }
+
+    // The file changed on disk: fresh size, date and dimensions, and the
+    // cached preview, thumbnail and colors are dropped so they're redone
+    fn reload_wallpaper(&mut self, path: &Path) {
+        let fresh = Arc::new(Wallpaper::from_path(path.to_path_buf()));
+        for list in [
+            &mut self.wallpapers,
+            &mut self.history,
+            &mut self.favorites,
+            &mut self.queue,
+        ] {
+            if let Some(pos) = list.iter().position(|w| **w == *path) {
+                list[pos] = Arc::clone(&fresh);
+            }
+        }
+        *self.search_cache.get_mut() = None;
+
+        self.image_cache.remove(path);
+        self.thumbnails.remove(path);
+        self.preview_status.remove(path);
+        self.broken.remove(path);
+        if self.last_preview.as_deref() == Some(path) {
+            self.last_preview = None;
+            self.preview_state = None;
+            self.preview_path = None;
+        }
+    }
+
+    // Moves files to the trash, failures are reported per file
+    fn delete_wallpapers(&mut self, paths: &[PathBuf]) -> Vec<(PathBuf, String)> {
+        let (mut op, failed) = ops::delete(paths);
+        let mut lists = Vec::new();
+        for path in paths {
+            if !failed.iter().any(|(p, _)| p == path) {
+                lists.extend(self.remove_path_references(path));
+            }
+        }
+        op.remember(lists);
+        if op.can_undo() {
+            self.push_undo(Box::new(op));
         }
+        failed
+    }
+
+    // Returns the list entries taken out, by list name and position
+    fn remove_path_references(&mut self, path: &Path) -> Vec<(String, usize, ListEntry)> {
+        self.wallpapers.retain(|w| **w != *path);
+        *self.search_cache.get_mut() = None;
+
+        self.history.retain(|w| **w != *path);
+        self.favorites.retain(|w| **w != *path);
+        self.queue.retain(|w| **w != *path);
+        // Every collection, not just the shown one
+        let mut removed = Vec::new();
+        for list in ["history".to_string(), "queue".to_string()]
+            .into_iter()
+            .chain(favorites_lists())
+        {
+            if let Some((index, entry)) = remove_from_list(&list, path) {
+                removed.push((list, index, entry));
+            }
+        }
+
+        self.image_cache.remove(path);
+        self.thumbnails.remove(path);
+        self.broken.remove(path);
+        self.preview_status.remove(path);
+
+        if self.last_preview.as_deref() == Some(path) {
+            self.last_preview = None;
+            self.preview_state = None;
+            self.preview_path = None;
+        }
+        removed
     }
 
     // --------------------
     // UI Rendering
     // --------------------
 
-    fn draw_ui(&mut self, filtered: &[PathBuf]) -> Result<(), Box<dyn std::error::Error>> {
+    fn draw_ui(&mut self, filtered: &[Arc<Wallpaper>]) -> Result<(), Box<dyn std::error::Error>> {
+        let theme = Theme {
+            color: self.config.color_enabled,
+        };
         let size = self.terminal.size()?;
         let area_rect = Rect {
             x: 0,
@@ -494,28 +2599,88 @@ impl<'a> TuiApp<'a> {
         let active_tabs = self.active_tabs();
         let tab_titles: Vec<String> = active_tabs.iter().map(|t| t.title()).collect();
         let selected_index = self.current_tab_index();
+        let colors_title = if self.config.pywal || self.config.hellwal {
+            if self.skip_colors {
+                " colors: off "
+            } else if self.config.pywal && self.light {
+                " colors: on (light) "
+            } else {
+                " colors: on "
+            }
+        } else {
+            ""
+        };
 
+        let star = if self.favorites_only.is_some() {
+            " ★"
+        } else {
+            ""
+        };
         let title = match self.current_tab {
             Tab::Wallpapers => {
                 if self.in_search {
-                    format!("Search: {} ", self.search_query)
+                    // Filled in once the list width is known
+                    String::new()
+                } else if self.scan_rx.is_some() {
+                    format!(
+                        "Wallpapers{} [{}] (scanning... {})",
+                        star,
+                        self.sort_mode.label(),
+                        self.wallpapers.len()
+                    )
                 } else {
-                    "Wallpapers".into()
+                    format!("Wallpapers{} [{}]", star, self.sort_mode.label())
                 }
             }
             Tab::History => "History".into(),
-            Tab::Favorites => "Favorites".into(),
+            Tab::Favorites if self.collection.is_empty() => {
+                if self.favorites_by_name {
+                    "Favorites (by name)".into()
+                } else {
+                    "Favorites".into()
+                }
+            }
+            Tab::Favorites if self.favorites_by_name => {
+                format!("Favorites: {} (by name)", self.collection)
+            }
+            Tab::Favorites => format!("Favorites: {}", self.collection),
+            Tab::Queue => "Queue".into(),
         };
+        let picked = self
+            .multi_select
+            .then(|| self.selection.in_list(filtered).len());
+        let mut title = counted_title(&title, self.selected, filtered.len(), picked);
 
         // List items
 
-        let items: Vec<ListItem> = filtered
-            .iter()
-            .enumerate()
-            .map(|(i, p)| {
-                let mut name = p.file_name().unwrap().to_string_lossy().to_string();
+        let grouped = self.grouped();
+        let mut hasher = DefaultHasher::new();
+        (grouped, self.config.show_relative_paths).hash(&mut hasher);
+        filtered.iter().for_each(|w| w.path.hash(&mut hasher));
+        let key = hasher.finish();
+        if self.list_labels.0 != key || self.list_labels.1.len() != filtered.len() {
+            let labels = if grouped {
+                // The section header has the folder
+                filtered.iter().map(|w| w.file_name()).collect()
+            } else if self.config.show_relative_paths {
+                filtered
+                    .iter()
+                    .map(|w| w.relative_name(&self.config.wallpaper_dirs))
+                    .collect()
+            } else {
+                let paths: Vec<&Path> = filtered.iter().map(|w| w.path.as_path()).collect();
+                disambiguate_labels(&paths)
+            };
+            self.list_labels = (key, labels);
+        }
+        let labels = &self.list_labels.1;
 
-                let extension = p
+        let names: Vec<String> = filtered
+            .iter()
+            .zip(labels.iter().cloned())
+            .map(|(w, mut name)| {
+                let extension = w
+                    .path
                     .extension()
                     .and_then(|s| s.to_str())
                     .unwrap_or("")
@@ -524,266 +2689,1217 @@ impl<'a> TuiApp<'a> {
                     name.push_str(" 🎥");
                 }
 
-                if self.favorites.contains(p) {
+                if self.favorites.contains(w) {
                     name.push_str(" ★");
                 }
-                if self.multi_select && self.selected_items.contains(&i) {
+                if self.current_tab != Tab::Queue && self.queue.contains(w) {
+                    name.push_str(" ↻");
+                }
+                if self.broken.contains(&w.path) {
+                    name.push_str(" ⚠");
+                }
+                if self.multi_select && self.selection.contains(&w.path) {
                     name = format!("[x] {}", name);
                 }
-                ListItem::new(name)
+                name
+            })
+            .collect();
+        // Matched characters are highlighted while searching
+        let search_cache = self.search_cache.borrow();
+        let positions = search_cache
+            .as_ref()
+            .filter(|_| self.current_tab == Tab::Wallpapers && !self.search_query.is_empty())
+            .map(|c| &c.positions);
+        let items: Vec<ListItem> = filtered
+            .iter()
+            .zip(labels.iter().zip(&names))
+            .map(|(w, (label, name))| {
+                let Some(matched) = positions.and_then(|p| p.get(&w.path)) else {
+                    return ListItem::new(name.as_str());
+                };
+
+                // Labels and relative names both end with the file name
+                let label_len = label.chars().count() as isize;
+                let shift = label_len
+                    - w.relative_name(&self.config.wallpaper_dirs).chars().count() as isize;
+                let hits: HashSet<usize> = matched
+                    .iter()
+                    .filter_map(|&p| usize::try_from(p as isize + shift).ok())
+                    .collect();
+
+                let start = name.find(label.as_str()).unwrap_or(0);
+                let mut spans = vec![Span::raw(&name[..start])];
+                spans.extend(label.chars().enumerate().map(|(i, c)| {
+                    if hits.contains(&i) {
+                        Span::styled(
+                            c.to_string(),
+                            theme.fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                        )
+                    } else {
+                        Span::raw(c.to_string())
+                    }
+                }));
+                spans.push(Span::raw(&name[start + label.len()..]));
+                ListItem::new(Line::from(spans))
             })
             .collect();
+        drop(search_cache);
+
+        // A header row goes before each section, `rows` maps rows to entries
+        let mut rows = Vec::with_capacity(items.len());
+        let mut list_items = Vec::with_capacity(items.len());
+        let mut section = None;
+        for (i, item) in items.into_iter().enumerate() {
+            if grouped {
+                let dir = filtered[i].relative_dir(&self.config.wallpaper_dirs);
+                if section.as_ref() != Some(&dir) {
+                    let header = if dir.is_empty() {
+                        "./".into()
+                    } else {
+                        format!("{}/", dir)
+                    };
+                    list_items.push(
+                        ListItem::new(header)
+                            .style(theme.fg(Color::Blue).add_modifier(Modifier::BOLD)),
+                    );
+                    rows.push(None);
+                    section = Some(dir);
+                }
+            }
+            list_items.push(item);
+            rows.push(Some(i));
+        }
 
         // Split screen vertically for tabs + main area
+        let statusbar_height = if self.config.show_statusbar { 1 } else { 0 };
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(statusbar_height),
+                Constraint::Length(if self.config.show_hints { 1 } else { 0 }),
+            ])
             .split(area_rect);
 
+        let hints = fit_hints(
+            &self.key_hints(),
+            chunks[3].width.saturating_sub(1) as usize,
+        );
+
+        // Dimensions, size, format and path of the highlighted wallpaper
+        let statusbar = match filtered.get(self.selected) {
+            Some(w) if self.config.show_statusbar => {
+                let dimensions = self
+                    .image_cache
+                    .peek(&w.path)
+                    .and_then(|c| c.dimensions)
+                    .or_else(|| w.dimensions())
+                    .map(|(width, height)| format!("{}x{}", width, height))
+                    .unwrap_or_else(|| "-".into());
+                let format = w
+                    .path
+                    .extension()
+                    .map(|e| e.to_string_lossy().to_uppercase())
+                    .unwrap_or_default();
+                let info = format!(
+                    " {} | {} | {} | ",
+                    dimensions,
+                    format_size(w.file_size),
+                    format
+                );
+                let room = (chunks[2].width as usize).saturating_sub(info.chars().count());
+                format!("{}{}", info, truncate_left(&w.path.to_string_lossy(), room))
+            }
+            _ => String::new(),
+        };
+
         // Determine list and preview layout based on config
-        let (list_area, preview_area) = match self.config.list_position.to_lowercase().as_str() {
-            "right" => {
-                let halves = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-                    .split(chunks[1]);
-                (halves[1], halves[0])
-            }
-            "top" => {
-                let halves = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-                    .split(chunks[1]);
-                (halves[0], halves[1])
-            }
-            "bottom" => {
-                let halves = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-                    .split(chunks[1]);
-                (halves[1], halves[0])
+        let (list_area, preview_area) = split_main(chunks[1], &self.config.list_position);
+
+        // The query scrolls within the title, with the cursor in it. The list
+        // block starts a column in, after the scrollbar.
+        let mut search_cursor = None;
+        if self.current_tab == Tab::Wallpapers && self.in_search {
+            let block = if self.grid {
+                chunks[1]
+            } else {
+                Rect {
+                    x: list_area.x + 1,
+                    width: list_area.width.saturating_sub(1),
+                    ..list_area
+                }
+            };
+            let count = counted_title("", self.selected, filtered.len(), picked);
+            let (shown, cursor) = self.search_query.visible(
+                block
+                    .width
+                    .saturating_sub(SEARCH_LABEL.len() as u16 + count.width() as u16 + 3),
+            );
+            title = counted_title(
+                &format!("{}{} ", SEARCH_LABEL, shown),
+                self.selected,
+                filtered.len(),
+                picked,
+            );
+            search_cursor = Some(ratatui::prelude::Position::new(
+                block.x + 1 + SEARCH_LABEL.len() as u16 + cursor,
+                block.y,
+            ));
+        }
+
+        // The grid fills the whole main area with cells of roughly fixed size
+        let grid_block = Block::default().title(title.clone()).borders(Borders::ALL);
+        let grid_inner = grid_block.inner(chunks[1]);
+        let mut grid_cells: Vec<(usize, Rect)> = Vec::new();
+        if self.grid {
+            let columns = (grid_inner.width / GRID_CELL_WIDTH).max(1);
+            let rows = (grid_inner.height / GRID_CELL_HEIGHT).max(1);
+            let cell_width = grid_inner.width / columns;
+            self.grid_columns = columns as usize;
+
+            // Scroll just enough to keep the selected row visible
+            let selected_row = self.selected / self.grid_columns;
+            if selected_row < self.grid_top {
+                self.grid_top = selected_row;
+            } else if selected_row >= self.grid_top + rows as usize {
+                self.grid_top = selected_row + 1 - rows as usize;
             }
-            _ => {
-                // default "left"
-                let halves = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-                    .split(chunks[1]);
-                (halves[0], halves[1])
+
+            let first = self.grid_top * self.grid_columns;
+            let last = (first + self.grid_columns * rows as usize).min(filtered.len());
+            for (offset, w) in filtered[first..last].iter().enumerate() {
+                let column = (offset % self.grid_columns) as u16;
+                let row = (offset / self.grid_columns) as u16;
+                let cell = Rect::new(
+                    grid_inner.x + column * cell_width,
+                    grid_inner.y + row * GRID_CELL_HEIGHT,
+                    cell_width,
+                    GRID_CELL_HEIGHT,
+                );
+                grid_cells.push((first + offset, cell));
+                if self.picker.is_some() && !self.thumbnails.contains_key(&w.path) {
+                    self.request_thumbnail(w);
+                }
             }
-        };
+
+            // Drop thumbnails that scrolled far out of view
+            if self.thumbnails.len() > grid_cells.len() * 4 {
+                let visible: HashSet<&Path> = filtered[first..last]
+                    .iter()
+                    .map(|w| w.path.as_path())
+                    .collect();
+                self.thumbnails
+                    .retain(|path, _| visible.contains(path.as_path()));
+            }
+        }
 
         // Update preview if selection changed
-        if !filtered.is_empty() && Some(&filtered[self.selected]) != self.last_preview.as_ref() {
-            let path = filtered[self.selected].clone();
+        if !self.grid
+            && !filtered.is_empty()
+            && Some(&filtered[self.selected].path) != self.last_preview.as_ref()
+        {
+            let path = filtered[self.selected].path.clone();
             self.last_preview = Some(path.clone());
-            self.request_preview(path);
+            self.zoom = 1;
+            let decode = match self.image_cache.get(&path).cloned() {
+                Some(cached_image) => {
+                    self.show_preview(path, &cached_image);
+                    None
+                }
+                // Known to fail. A request still loading is asked again, the
+                // newer one wins and the older gives up if it hasn't started
+                None if matches!(
+                    self.preview_status.get(&path),
+                    Some(PreviewStatus::Failed(_))
+                ) =>
+                {
+                    None
+                }
+                None => {
+                    self.preview_status
+                        .insert(path.clone(), PreviewStatus::Loading);
+                    self.loading_since = Instant::now();
+                    Some(path)
+                }
+            };
+            self.request_preview(decode, self.preload_window(filtered));
+        }
+        self.spinner_frame = self.current_spinner_frame();
+
+        // Shown once the preview image is cached
+        let swatches = self
+            .last_preview
+            .as_ref()
+            .filter(|_| self.show_swatches)
+            .and_then(|path| self.image_cache.peek(path))
+            .filter(|cached| cached.has_frame())
+            .map(|cached| cached.palette.clone());
+        let (preview_area, swatch_area) = match swatches {
+            Some(_) if !self.grid => {
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Min(0), Constraint::Length(SWATCH_WIDTH)])
+                    .split(preview_area);
+                (columns[0], Some(columns[1]))
+            }
+            _ => (preview_area, None),
+        };
+
+        // Frame titled with the previewed file, the image goes inside it
+        let preview_frame = self.config.preview_border && !self.grid;
+        let preview_title = filtered
+            .get(self.selected)
+            .filter(|_| preview_frame)
+            .map(|w| match w.dimensions() {
+                Some((width, height)) => format!(" {} ({}x{}) ", w.file_name(), width, height),
+                None => format!(" {} ", w.file_name()),
+            });
+        let preview_block = preview_frame.then(|| {
+            Block::default()
+                .title(preview_title.unwrap_or_default())
+                .borders(Borders::ALL)
+        });
+        let (preview_outer, preview_area) = match &preview_block {
+            Some(block) => (preview_area, block.inner(preview_area)),
+            None => (preview_area, preview_area),
+        };
+
+        // Cut the preview again for a new pane size or fill mode
+        if !self.grid && (preview_area, self.preview_resize) != self.preview_cut {
+            self.preview_cut = (preview_area, self.preview_resize);
+            self.reload_preview();
+        }
+        let font_size = self
+            .picker
+            .as_ref()
+            .map(|p| p.font_size())
+            .unwrap_or((1, 1));
+        let image_area = match self.preview_resize {
+            PreviewResize::Crop => preview_area,
+            PreviewResize::Fit => centered(preview_area, self.preview_size, font_size, false),
+            PreviewResize::Width | PreviewResize::Height => {
+                centered(preview_area, self.preview_size, font_size, true)
+            }
+        };
+
+        // Compute scrollbar for list
+        let total = filtered.len() as u16;
+        let height = list_area.height;
+        self.list_height = (height.saturating_sub(2) as usize).max(1);
+
+        // Rows are filled in once the list is drawn and scrolled
+        let tabs_inner = chunks[0].inner(Margin::new(1, 1));
+        self.mouse_areas = if self.grid {
+            MouseAreas {
+                tabs: tabs_inner,
+                cells: grid_cells.clone(),
+                ..MouseAreas::default()
+            }
+        } else {
+            MouseAreas::with_list(list_area, preview_outer, tabs_inner)
+        };
+        let scroll_ratio = (self.selected as f32 / total.max(1) as f32).min(1.0);
+        let scroll_pos = (scroll_ratio * (height - 1) as f32).round() as u16;
+
+        // Store rename_state in a local variable to avoid borrowing issues
+        let rename_state = self.rename_state.as_ref();
+        let confirm_state = self.confirm_state.as_ref();
+        let zoom = self.zoom;
+        let switcher = self
+            .switcher_state
+            .as_ref()
+            .map(|state| (state, self.switcher_results(state.query.text())));
+        let menu_state = self.menu_state.as_ref();
+
+        let status = self.status.as_deref().unwrap_or("");
+        let spinner_frame = self.spinner_frame;
+        let preview_status = self.preview_status();
+
+        // Some(has_frame) when the previewed entry is a video
+        let video_frame = self
+            .last_preview
+            .as_ref()
+            .and_then(|path| self.image_cache.peek(path))
+            .filter(|cached| cached.is_video)
+            .map(|cached| cached.has_frame());
+
+        // Stands in for the image when there is no graphics protocol
+        let text_only = self.picker.is_none();
+        let details = filtered.get(self.selected).filter(|_| text_only).map(|w| {
+            let dimensions = w
+                .dimensions()
+                .map_or("unknown".to_string(), |(w, h)| format!("{}x{}", w, h));
+            let folder = w.path.parent().unwrap_or(&w.path).display().to_string();
+            Text::from(vec![
+                Line::styled(w.file_name(), theme.fg(Color::Yellow)),
+                Line::raw(""),
+                Line::raw(format!("Dimensions: {}", dimensions)),
+                Line::raw(format!("Size:       {}", format_size(w.file_size))),
+                Line::raw(format!("Folder:     {}", folder)),
+            ])
+        });
+
+        // Said in the list instead of leaving it blank
+        let no_matches = filtered.is_empty()
+            && self.current_tab == Tab::Wallpapers
+            && !self.search_query.is_empty();
+        let list_inner = self.mouse_areas.list;
+
+        // The list widget counts rows, headers included, so it gets the row of
+        // the highlighted entry while drawing and the entry back afterwards
+        let highlighted = self.list_state.selected();
+        if let Some(row) = rows.iter().position(|r| *r == Some(self.selected)) {
+            self.list_state.select(Some(row));
+            // Keep the header in view when scrolling up to its first entry
+            if row > 0 && rows[row - 1].is_none() && self.list_state.offset() >= row {
+                *self.list_state.offset_mut() = row - 1;
+            }
+        }
+
+        // Draw UI
+        self.terminal.draw(|f| {
+            if let Some(position) = search_cursor {
+                f.set_cursor_position(position);
+            }
+            // Tabs
+            let tabs = Tabs::new(tab_titles.clone())
+                .select(selected_index)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title_top(Line::from(colors_title).right_aligned())
+                        .title_bottom(Line::styled(status, theme.fg(Color::Red)).right_aligned()),
+                )
+                .highlight_style(theme.highlight(Style::default().fg(Color::Yellow)));
+            f.render_widget(tabs, chunks[0]);
+
+            // Status bar
+            if !statusbar.is_empty() {
+                f.render_widget(
+                    Paragraph::new(statusbar.as_str()).style(theme.fg(Color::Gray)),
+                    chunks[2],
+                );
+            }
+
+            f.render_widget(
+                Paragraph::new(format!(" {}", hints)).style(theme.fg(Color::DarkGray)),
+                chunks[3],
+            );
+
+            if self.grid {
+                f.render_widget(grid_block, chunks[1]);
+                for (i, cell) in grid_cells {
+                    let border = if i == self.selected {
+                        theme.highlight(theme.fg(Color::Yellow))
+                    } else {
+                        Style::default()
+                    };
+                    let block = Block::default()
+                        .title(names[i].as_str())
+                        .borders(Borders::ALL)
+                        .border_style(border);
+                    let inner = block.inner(cell);
+                    f.render_widget(block, cell);
+
+                    match self.thumbnails.get_mut(&filtered[i].path) {
+                        Some(Thumbnail::Ready(state)) => {
+                            f.render_stateful_widget(StatefulImage::new(), inner, state);
+                        }
+                        Some(Thumbnail::Failed) => {
+                            f.render_widget(Paragraph::new("⚠").style(theme.fg(Color::Red)), inner);
+                        }
+                        _ if text_only => {}
+                        _ => {
+                            f.render_widget(
+                                Paragraph::new("...").style(theme.fg(Color::Gray)),
+                                inner,
+                            );
+                        }
+                    }
+                }
+            } else {
+                // Scrollbar
+                for y in 0..height {
+                    let symbol = if y == scroll_pos { "█" } else { "│" };
+                    let p = Paragraph::new(symbol)
+                        .style(theme.fg(Color::Yellow))
+                        .block(Block::default());
+                    f.render_widget(p, Rect::new(list_area.x, list_area.y + y, 1, 1));
+                }
+
+                // List
+                let list = List::new(list_items)
+                    .block(
+                        Block::default()
+                            .title(title)
+                            .borders(Borders::ALL)
+                            .style(Style::default()),
+                    )
+                    .highlight_style(theme.highlight(Style::default().fg(Color::Yellow)))
+                    .highlight_symbol(">> ");
+                f.render_stateful_widget(
+                    list,
+                    Rect {
+                        x: list_area.x + 1,
+                        y: list_area.y,
+                        width: list_area.width - 1,
+                        height: list_area.height,
+                    },
+                    &mut self.list_state,
+                );
+                if no_matches {
+                    let middle = Rect {
+                        y: list_inner.y + list_inner.height / 2,
+                        height: list_inner.height.min(1),
+                        ..list_inner
+                    };
+                    f.render_widget(
+                        Paragraph::new("No matches")
+                            .alignment(Alignment::Center)
+                            .style(theme.fg(Color::Gray)),
+                        middle,
+                    );
+                }
+
+                // Preview
+                if let Some(block) = preview_block {
+                    f.render_widget(block, preview_outer);
+                }
+
+                match preview_status {
+                    Some(PreviewStatus::Failed(reason)) => {
+                        let message = Paragraph::new(format!("⚠ Failed to load: {}", reason))
+                            .style(theme.fg(Color::Red))
+                            .wrap(Wrap { trim: true });
+                        f.render_widget(message, preview_area);
+                    }
+                    Some(PreviewStatus::Loading) => {
+                        let spinner =
+                            Paragraph::new(format!("{} Loading…", SPINNER[spinner_frame]))
+                                .alignment(Alignment::Center)
+                                .style(theme.fg(Color::Gray));
+                        let middle = Rect {
+                            y: preview_area.y + preview_area.height / 2,
+                            height: preview_area.height.min(1),
+                            ..preview_area
+                        };
+                        f.render_widget(spinner, middle);
+                    }
+                    Some(PreviewStatus::Ready) if video_frame == Some(false) => {
+                        let placeholder = Paragraph::new(
+                            "▶ video\n\nNo preview, ffmpeg couldn't extract a frame",
+                        )
+                        .style(theme.fg(Color::Gray));
+                        f.render_widget(placeholder, preview_area);
+                    }
+                    Some(PreviewStatus::Ready) => {
+                        if let Some(state) = &mut self.preview_state {
+                            let widget = StatefulImage::new();
+                            f.render_stateful_widget(
+                                widget.resize(self.preview_resize.resize()),
+                                image_area,
+                                state,
+                            );
+                        } else if let Some(details) = details {
+                            f.render_widget(
+                                Paragraph::new(details).wrap(Wrap { trim: false }),
+                                preview_area,
+                            );
+                        }
+
+                        if zoom > 1 {
+                            let label = format!(" {}x ", zoom);
+                            let width = label.len() as u16;
+                            let overlay_area = Rect::new(
+                                (preview_area.x + preview_area.width).saturating_sub(width + 2),
+                                preview_area.y + 2,
+                                width,
+                                1,
+                            );
+                            f.render_widget(
+                                Paragraph::new(label)
+                                    .style(theme.style(
+                                        Style::default().fg(Color::Yellow).bg(Color::Black),
+                                    )),
+                                overlay_area,
+                            );
+                        }
+
+                        // Overlay video indicator if this is a video
+                        if video_frame.is_some() {
+                            let video_text =
+                                Paragraph::new("▶ video")
+                                    .style(theme.style(
+                                        Style::default().fg(Color::Yellow).bg(Color::Black),
+                                    ));
+                            let overlay_area =
+                                Rect::new(preview_area.x + 2, preview_area.y + 2, 7, 1);
+                            f.render_widget(video_text, overlay_area);
+                        }
+                    }
+                    None => {}
+                }
+
+                if let (Some(area), Some(colors)) = (swatch_area, swatches.as_ref()) {
+                    Self::draw_swatches(f, area, colors);
+                }
+            }
+
+            // Draw rename dialog if active
+            if let Some(rename_state) = rename_state {
+                Self::draw_rename_dialog(f, area_rect, rename_state, theme);
+            }
+
+            // Draw confirmation if active
+            if let Some(confirm_state) = confirm_state {
+                Self::draw_confirm_dialog(f, area_rect, confirm_state, theme);
+            }
+
+            // Draw quick switcher if active
+            if let Some((switcher_state, results)) = &switcher {
+                Self::draw_switcher_dialog(f, area_rect, switcher_state, results, theme);
+            }
+
+            // Draw context menu if open
+            if let Some(menu_state) = menu_state {
+                Self::draw_context_menu(f, area_rect, menu_state, theme);
+            }
+        })?;
+
+        let offset = self.list_state.offset();
+        self.mouse_areas.rows = rows
+            .into_iter()
+            .skip(offset)
+            .take(self.mouse_areas.list.height as usize)
+            .collect();
+        self.list_state.select(highlighted);
+
+        Ok(())
+    }
+
+    // Most useful keys for the open dialog, or the current tab and mode,
+    // shown in the footer
+    fn key_hints(&self) -> Vec<(String, &'static str)> {
+        let k = &self.config.keybindings;
+        if let Some(rename_state) = &self.rename_state {
+            return vec![
+                (
+                    "Enter".into(),
+                    match rename_state.action {
+                        PromptAction::Rename | PromptAction::BatchRename => "rename",
+                        PromptAction::Copy => "copy",
+                        PromptAction::Convert => "convert",
+                        PromptAction::Collection => "create",
+                    },
+                ),
+                ("Esc".into(), "cancel"),
+                ("Ctrl-U".into(), "clear"),
+            ];
+        }
+        if self.confirm_state.is_some() {
+            return vec![("Enter/y".into(), "confirm"), ("Esc/n".into(), "cancel")];
+        }
+        if self.switcher_state.is_some() {
+            return vec![
+                ("Enter".into(), "apply"),
+                ("↑↓".into(), "move"),
+                ("Esc".into(), "cancel"),
+            ];
+        }
+        if self.menu_state.is_some() {
+            return vec![
+                ("Enter".into(), "run"),
+                ("↑↓".into(), "move"),
+                ("Esc".into(), "close"),
+            ];
+        }
+        if self.in_search {
+            return vec![
+                ("Enter".into(), "confirm"),
+                ("Esc".into(), "cancel"),
+                ("↑↓".into(), "history"),
+            ];
+        }
+        if self.multi_select {
+            let mut hints = vec![
+                (key_label(k.toggle_selection), "toggle"),
+                (key_label(k.range_select), "range"),
+                (key_label(k.select_all), "all"),
+                (key_label(k.invert_selection), "invert"),
+                (key_label(k.favorite), "favorite"),
+                (key_label(k.delete), "delete"),
+            ];
+            if self.current_tab == Tab::Wallpapers {
+                hints.push((key_label(k.rename), "rename"));
+            }
+            hints.push((key_label(k.multi_select), "done"));
+            return hints;
+        }
+        if self.zoom > 1 {
+            return vec![
+                ("←↑↓→".into(), "pan"),
+                (key_label(k.zoom_in), "zoom in"),
+                (key_label(k.zoom_out), "zoom out"),
+            ];
+        }
+
+        let mut hints = vec![
+            ("Enter".into(), "apply"),
+            (key_label(k.apply), "apply & stay"),
+            (key_label(k.favorite), "favorite"),
+        ];
+        match self.current_tab {
+            Tab::Wallpapers => hints.extend([
+                (key_label(k.search), "search"),
+                (key_label(k.rename), "rename"),
+                (key_label(k.sort), "sort"),
+                (key_label(k.favorites_only), "★ only"),
+                (key_label(k.grid), "grid"),
+            ]),
+            Tab::Favorites => hints.extend([
+                (key_label(k.remove), "remove"),
+                (key_label(k.sort), "sort"),
+                (key_label(k.collection), "collection"),
+            ]),
+            Tab::History => hints.extend([
+                (key_label(k.remove), "remove"),
+                (key_label(k.clear_list), "clear"),
+            ]),
+            Tab::Queue => hints.extend([
+                ("Alt-Enter".into(), "print queue"),
+                (
+                    format!("{}/{}", key_label(k.move_up), key_label(k.move_down)),
+                    "reorder",
+                ),
+                (key_label(k.remove), "remove"),
+            ]),
+        }
+        if self.current_tab != Tab::Queue {
+            hints.push((key_label(k.queue), "queue"));
+        }
+        hints.extend([
+            (key_label(k.multi_select), "select"),
+            (key_label(k.switcher), "switcher"),
+            ("Tab".into(), "next tab"),
+            (key_label(k.quit), "quit"),
+        ]);
+        hints
+    }
+
+    fn draw_rename_dialog(f: &mut Frame, area: Rect, rename_state: &RenameState, theme: Theme) {
+        // New names of the first files, with a line for "…"
+        let preview_rows = if rename_state.batch.is_empty() {
+            0
+        } else {
+            BATCH_PREVIEW as u16 + 1
+        };
+
+        // Create a centered dialog area
+        let width = 50;
+        let height = 10 + preview_rows;
+        let x = (area.width - width) / 2;
+        let y = (area.height - height) / 2;
+        let dialog_area = Rect::new(x, y, width, height);
+
+        // Dialog background
+        let batch_title = format!(" Rename {} Wallpapers ", rename_state.batch.len());
+        let (title, input_title) = match rename_state.action {
+            PromptAction::Rename => (" Rename Wallpaper ", "New Name"),
+            PromptAction::Copy => (" Copy Wallpaper ", "Destination Folder"),
+            PromptAction::Convert => (
+                " Convert Wallpaper ",
+                "Format: png, jpg, webp, bmp, tiff, gif",
+            ),
+            PromptAction::BatchRename => (batch_title.as_str(), "Pattern: {n} {n:03} {name} {ext}"),
+            PromptAction::Collection => (" New Collection ", "Name: letters, digits, - and _"),
+        };
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(theme.fg(Color::Yellow));
+
+        f.render_widget(Clear, dialog_area);
+        f.render_widget(block, dialog_area);
+
+        // Content area inside the dialog
+        let inner_area = dialog_area.inner(Margin::new(1, 1));
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),            // Original name
+                Constraint::Length(3),            // Input field
+                Constraint::Length(preview_rows), // New names of a batch
+                Constraint::Length(1),            // Error message
+                Constraint::Min(1),               // Spacer
+                Constraint::Length(1),            // Instructions
+            ])
+            .split(inner_area);
+
+        // Original file name
+        let original_name = if rename_state.action == PromptAction::Collection {
+            Text::raw("Starts empty, favorites are added to it")
+        } else if rename_state.batch.is_empty() {
+            Text::raw(format!(
+                "Original: {}",
+                rename_state
+                    .original_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+            ))
+        } else {
+            Text::raw(format!("Selected: {} files", rename_state.batch.len()))
+        };
+        f.render_widget(Paragraph::new(original_name), chunks[0]);
+
+        // Live preview of the pattern
+        if !rename_state.batch.is_empty() && !rename_state.input.text().is_empty() {
+            let mut lines = Vec::new();
+            for (index, path) in rename_state.batch.iter().take(BATCH_PREVIEW).enumerate() {
+                match ops::expand_pattern(rename_state.input.text(), index, path) {
+                    Ok(name) => lines.push(Line::raw(format!(
+                        "{} → {}",
+                        path.file_name().unwrap_or_default().to_string_lossy(),
+                        name
+                    ))),
+                    Err(e) => {
+                        lines = vec![Line::styled(e.to_string(), theme.fg(Color::Red))];
+                        break;
+                    }
+                }
+            }
+            if rename_state.batch.len() > BATCH_PREVIEW && lines.len() == BATCH_PREVIEW {
+                lines.push(Line::styled("…", theme.fg(Color::Gray)));
+            }
+            f.render_widget(
+                Paragraph::new(lines).style(theme.fg(Color::Gray)),
+                chunks[2],
+            );
         }
 
-        // Compute scrollbar for list
-        let total = filtered.len() as u16;
-        let height = list_area.height;
-        let scroll_ratio = (self.selected as f32 / total.max(1) as f32).min(1.0);
-        let scroll_pos = (scroll_ratio * (height - 1) as f32).round() as u16;
+        // Input field
+        let (shown, cursor) = rename_state
+            .input
+            .visible(chunks[1].width.saturating_sub(2));
+        let input = Paragraph::new(shown)
+            .style(theme.fg(Color::Yellow))
+            .block(Block::default().borders(Borders::ALL).title(input_title));
+        f.render_widget(input, chunks[1]);
+
+        // Error message
+        if let Some(error) = &rename_state.error {
+            let error_text = Text::styled(error, theme.fg(Color::Red));
+            f.render_widget(Paragraph::new(error_text), chunks[3]);
+        }
+
+        // Instructions
+        let instructions = Text::raw("Enter: Confirm | Esc: Cancel");
+        f.render_widget(Paragraph::new(instructions), chunks[5]);
+
+        // Set cursor position in input field
+        f.set_cursor_position(ratatui::prelude::Position::new(
+            chunks[1].x + cursor + 1,
+            chunks[1].y + 1,
+        ));
+    }
 
-        // Store rename_state in a local variable to avoid borrowing issues
-        let rename_state = self.rename_state.as_ref();
+    fn draw_swatches(f: &mut Frame, area: Rect, colors: &[[u8; 3]]) {
+        let block = Block::default().borders(Borders::ALL).title("Colors");
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+        if colors.is_empty() {
+            return;
+        }
 
-        // Draw UI
-        self.terminal.draw(|f| {
-            // Tabs
-            let tabs = Tabs::new(tab_titles.clone())
-                .select(selected_index)
-                .block(Block::default().borders(Borders::ALL))
-                .highlight_style(Style::default().fg(Color::Yellow));
-            f.render_widget(tabs, chunks[0]);
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Ratio(1, colors.len() as u32);
+                colors.len()
+            ])
+            .split(inner);
+        for (&[r, g, b], row) in colors.iter().zip(rows.iter()) {
+            // Dark text on light swatches keeps the hex code readable
+            let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+            let fg = if luma > 140.0 {
+                Color::Black
+            } else {
+                Color::White
+            };
+            let swatch = Paragraph::new(format!("#{:02x}{:02x}{:02x}", r, g, b))
+                .style(Style::default().fg(fg).bg(Color::Rgb(r, g, b)));
+            f.render_widget(swatch, *row);
+        }
+    }
 
-            // Scrollbar
-            for y in 0..height {
-                let symbol = if y == scroll_pos { "█" } else { "│" };
-                let p = Paragraph::new(symbol)
-                    .style(Style::default().fg(Color::Yellow))
-                    .block(Block::default());
-                f.render_widget(p, Rect::new(list_area.x, list_area.y + y, 1, 1));
-            }
+    fn draw_confirm_dialog(f: &mut Frame, area: Rect, confirm_state: &ConfirmState, theme: Theme) {
+        // Create a centered dialog area
+        let width = 50.min(area.width);
+        let height = 8.min(area.height);
+        let x = (area.width - width) / 2;
+        let y = (area.height - height) / 2;
+        let dialog_area = Rect::new(x, y, width, height);
 
-            // List
-            let list = List::new(items)
-                .block(
-                    Block::default()
-                        .title(title)
-                        .borders(Borders::ALL)
-                        .style(Style::default()),
-                )
-                .highlight_style(Style::default().fg(Color::Yellow))
-                .highlight_symbol(">> ");
-            f.render_stateful_widget(
-                list,
-                Rect {
-                    x: list_area.x + 1,
-                    y: list_area.y,
-                    width: list_area.width - 1,
-                    height: list_area.height,
+        // Dialog background
+        let (title, question) = match &confirm_state.action {
+            Confirm::Trash(paths) => (
+                " Delete Wallpaper ",
+                match paths.as_slice() {
+                    [path] => format!(
+                        "Move {} to the trash?",
+                        path.file_name().unwrap_or_default().to_string_lossy()
+                    ),
+                    paths => format!("Move {} wallpapers to the trash?", paths.len()),
                 },
-                &mut self.list_state,
-            );
+            ),
+            Confirm::ClearList(tab) => (
+                " Clear List ",
+                format!("Remove every entry from {}? Files are kept.", tab.title()),
+            ),
+        };
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(theme.fg(Color::Red));
 
-            // Preview
+        f.render_widget(Clear, dialog_area);
+        f.render_widget(block, dialog_area);
 
-            if let Some(state) = &mut self.preview_state {
-                let widget = StatefulImage::new();
-                f.render_stateful_widget(widget.resize(Resize::Fit(None)), preview_area, state);
+        let inner_area = dialog_area.inner(Margin::new(1, 1));
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1), // Question
+                Constraint::Min(1),    // Error message
+                Constraint::Length(1), // Instructions
+            ])
+            .split(inner_area);
 
-                // Overlay video indicator if this is a video
-                if let Some(current_path) = self.last_preview.as_ref() {
-                    let extension = current_path
-                        .extension()
-                        .and_then(|s| s.to_str())
-                        .unwrap_or("")
-                        .to_lowercase();
-                    if ["mp4", "avi", "mov", "mkv"].contains(&extension.as_str()) {
-                        let video_text = Paragraph::new("🎥 VIDEO")
-                            .style(Style::default().fg(Color::Yellow).bg(Color::Black));
-                        let overlay_area = Rect::new(preview_area.x + 2, preview_area.y + 2, 10, 1);
-                        f.render_widget(video_text, overlay_area);
-                    }
-                }
-            } else if self.last_preview.is_some() {
-                // Show loading indicator while preview is being generated
-                let loading_text =
-                    Paragraph::new("Loading preview...").style(Style::default().fg(Color::Gray));
-                f.render_widget(loading_text, preview_area);
-            }
+        f.render_widget(Paragraph::new(question), chunks[0]);
 
-            // Draw rename dialog if active
-            if let Some(rename_state) = rename_state {
-                Self::draw_rename_dialog(f, area_rect, rename_state);
-            }
-        })?;
+        // Error message
+        if let Some(error) = &confirm_state.error {
+            let error_text = Text::styled(error, theme.fg(Color::Red));
+            f.render_widget(
+                Paragraph::new(error_text).wrap(ratatui::widgets::Wrap { trim: true }),
+                chunks[1],
+            );
+        }
 
-        Ok(())
+        // Instructions
+        let instructions = Text::raw("Enter/y: Confirm | Esc/n: Cancel");
+        f.render_widget(Paragraph::new(instructions), chunks[2]);
     }
 
-    fn draw_rename_dialog(f: &mut Frame, area: Rect, rename_state: &RenameState) {
+    fn draw_switcher_dialog(
+        f: &mut Frame,
+        area: Rect,
+        switcher_state: &SwitcherState,
+        results: &[Arc<Wallpaper>],
+        theme: Theme,
+    ) {
         // Create a centered dialog area
-        let width = 50;
-        let height = 10;
+        let width = 60.min(area.width);
+        let height = (SWITCHER_RESULTS as u16 + 7).min(area.height);
         let x = (area.width - width) / 2;
         let y = (area.height - height) / 2;
         let dialog_area = Rect::new(x, y, width, height);
 
         // Dialog background
         let block = Block::default()
-            .title(" Rename Wallpaper ")
+            .title(" Quick Switch ")
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Yellow));
+            .border_style(theme.fg(Color::Yellow));
 
         f.render_widget(Clear, dialog_area);
         f.render_widget(block, dialog_area);
 
-        // Content area inside the dialog
         let inner_area = dialog_area.inner(Margin::new(1, 1));
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(1), // Original name
                 Constraint::Length(3), // Input field
-                Constraint::Length(1), // Error message
-                Constraint::Min(1),    // Spacer
+                Constraint::Min(1),    // Results
                 Constraint::Length(1), // Instructions
             ])
             .split(inner_area);
 
-        // Original file name
-        let original_name = Text::raw(format!(
-            "Original: {}",
-            rename_state
-                .original_path
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-        ));
-        f.render_widget(Paragraph::new(original_name), chunks[0]);
-
         // Input field
-        let input = Paragraph::new(rename_state.current_input.as_str())
-            .style(Style::default().fg(Color::Yellow))
-            .block(Block::default().borders(Borders::ALL).title("New Name"));
-        f.render_widget(input, chunks[1]);
+        let (shown, cursor) = switcher_state
+            .query
+            .visible(chunks[0].width.saturating_sub(2));
+        let input = Paragraph::new(shown)
+            .style(theme.fg(Color::Yellow))
+            .block(Block::default().borders(Borders::ALL).title("Wallpaper"));
+        f.render_widget(input, chunks[0]);
 
-        // Error message
-        if let Some(error) = &rename_state.error {
-            let error_text = Text::styled(error, Style::default().fg(Color::Red));
-            f.render_widget(Paragraph::new(error_text), chunks[2]);
+        // Results
+        if results.is_empty() {
+            let empty = Text::styled("No matches", theme.fg(Color::Gray));
+            f.render_widget(Paragraph::new(empty), chunks[1]);
+        } else {
+            let items: Vec<ListItem> = results
+                .iter()
+                .map(|w| ListItem::new(w.file_name()))
+                .collect();
+            let mut state = ListState::default();
+            state.select(Some(switcher_state.selected.min(results.len() - 1)));
+            let list = List::new(items)
+                .highlight_style(theme.highlight(Style::default().fg(Color::Yellow)))
+                .highlight_symbol(">> ");
+            f.render_stateful_widget(list, chunks[1], &mut state);
         }
 
         // Instructions
-        let instructions = Text::raw("Enter: Confirm | Esc: Cancel");
-        f.render_widget(Paragraph::new(instructions), chunks[4]);
+        let instructions = Text::raw("Enter: Apply | Up/Down: Move | Esc: Cancel");
+        f.render_widget(Paragraph::new(instructions), chunks[2]);
 
         // Set cursor position in input field
         f.set_cursor_position(ratatui::prelude::Position::new(
-            chunks[1].x + rename_state.current_input.len() as u16 + 1,
-            chunks[1].y + 1,
+            chunks[0].x + cursor + 1,
+            chunks[0].y + 1,
         ));
     }
 
-    // --------------------
-    // Cache management methods
-    // --------------------
+    fn draw_context_menu(f: &mut Frame, area: Rect, menu_state: &MenuState, theme: Theme) {
+        let menu_area = menu_rect(area, menu_state.anchor, &menu_state.items);
 
-    fn preload_images(&mut self, paths: &[PathBuf]) {
-        for path in paths.iter().take(self.image_cache.max_size) {
-            if self.image_cache.get(path).is_none()
-                && let Ok(cached_image) = CachedImage::new(path)
-            {
-                self.image_cache.insert(path.clone(), cached_image);
-            }
+        let items: Vec<ListItem> = menu_state
+            .items
+            .iter()
+            .map(|(label, _)| ListItem::new(format!(" {} ", label)))
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(menu_state.selected));
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.fg(Color::Yellow)),
+            )
+            .highlight_style(theme.highlight(Style::default().fg(Color::Black).bg(Color::Yellow)));
+
+        f.render_widget(Clear, menu_area);
+        f.render_stateful_widget(list, menu_area, &mut state);
+    }
+
+    // Below the highlighted entry, where a menu opened from the keyboard goes
+    fn entry_anchor(&self) -> (u16, u16) {
+        let areas = &self.mouse_areas;
+        if let Some((_, cell)) = areas.cells.iter().find(|(i, _)| *i == self.selected) {
+            return (cell.x + 1, cell.y + 1);
+        }
+        let row = areas
+            .rows
+            .iter()
+            .position(|r| *r == Some(self.selected))
+            .unwrap_or(0) as u16;
+        (areas.list.x, areas.list.y + row + 1)
+    }
+
+    // Entries for a right-click on the highlighted wallpaper, named after the
+    // keys that do the same
+    fn menu_items(&self, filtered: &[Arc<Wallpaper>]) -> Vec<(String, Action)> {
+        let favorite = filtered
+            .get(self.selected)
+            .is_some_and(|w| self.favorites.contains(w));
+        let mut items = vec![
+            ("Apply", Action::Apply),
+            (
+                if favorite { "Unfavorite" } else { "Favorite" },
+                Action::Favorite,
+            ),
+        ];
+        if self.current_tab == Tab::Wallpapers {
+            items.push(("Rename", Action::Rename));
+        }
+        if self.current_tab != Tab::Queue {
+            items.push(("Add to queue", Action::Enqueue));
         }
+        items.push(("Edit…", Action::Edit));
+        items.push(("Delete", Action::Delete));
+        items
+            .into_iter()
+            .map(|(label, action)| (label.to_string(), action))
+            .collect()
     }
 
+    // --------------------
+    // Cache management methods
+    // --------------------
+
     // --------------------
     // Event Handling
     // --------------------
 
     fn handle_event(
         &mut self,
-        filtered: &[PathBuf],
+        filtered: &[Arc<Wallpaper>],
     ) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
         self.dirty = true;
 
         let event = event::read()?;
+        if let event::Event::Key(_) = event {
+            self.status = None;
+        }
+
+        // Redraw right away, the preview protocol re-encodes for the new area
+        // when it is rendered and open dialogs don't see the event
+        if let event::Event::Resize(..) = event {
+            self.image_cache.clear_protocols();
+            self.terminal.autoresize()?;
+            return Ok(None);
+        }
 
         if self.rename_state.is_some() {
             match event {
                 event::Event::Key(key) => {
                     match key.code {
                         KeyCode::Enter => {
-                            let (original_path, new_name) = {
+                            let (original_path, new_name, action) = {
                                 let rename_state = self.rename_state.as_mut().unwrap();
-                                let new_name = rename_state.current_input.trim().to_string();
+                                let new_name = rename_state.input.text().trim().to_string();
                                 if new_name.is_empty() {
-                                    rename_state.error = Some("Name cannot be empty".to_string());
+                                    rename_state.error = Some(match rename_state.action {
+                                        PromptAction::Rename => "Name cannot be empty".to_string(),
+                                        PromptAction::Copy => "Folder cannot be empty".to_string(),
+                                        PromptAction::BatchRename => {
+                                            "Pattern cannot be empty".to_string()
+                                        }
+                                        PromptAction::Convert => {
+                                            "Format cannot be empty".to_string()
+                                        }
+                                        PromptAction::Collection => {
+                                            "Name cannot be empty".to_string()
+                                        }
+                                    });
                                     return Ok(None);
                                 }
-                                (rename_state.original_path.clone(), new_name)
+                                (
+                                    rename_state.original_path.clone(),
+                                    new_name,
+                                    rename_state.action,
+                                )
                             };
 
+                            if action == PromptAction::Collection {
+                                if new_name
+                                    .chars()
+                                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+                                {
+                                    self.rename_state = None;
+                                    self.switch_collection(new_name);
+                                } else if let Some(rs) = self.rename_state.as_mut() {
+                                    rs.error =
+                                        Some("Only letters, digits, - and _ are allowed".into());
+                                }
+                                return Ok(None);
+                            }
+
+                            if action == PromptAction::Copy {
+                                match self.copy_wallpaper(&original_path, &new_name) {
+                                    Ok(target) => {
+                                        self.rename_state = None;
+                                        self.status = Some(format!(
+                                            " Copied to {} ",
+                                            target.parent().unwrap_or(&target).display()
+                                        ));
+                                    }
+                                    Err(e) => {
+                                        if let Some(rs) = self.rename_state.as_mut() {
+                                            rs.error = Some(e.to_string());
+                                        }
+                                    }
+                                }
+                                return Ok(None);
+                            }
+
+                            if action == PromptAction::Convert {
+                                match ops::convert(&original_path, &new_name) {
+                                    Ok(new_path) => {
+                                        self.rename_state = None;
+                                        self.update_path_references(&[(
+                                            original_path.clone(),
+                                            new_path.clone(),
+                                        )]);
+                                        self.reload_wallpaper(&new_path);
+                                        self.status = Some(format!(
+                                            " Converted to {}, the original is in the trash ",
+                                            new_path
+                                                .file_name()
+                                                .unwrap_or_default()
+                                                .to_string_lossy()
+                                        ));
+                                    }
+                                    Err(e) => {
+                                        if let Some(rs) = self.rename_state.as_mut() {
+                                            rs.error = Some(e.to_string());
+                                        }
+                                    }
+                                }
+                                return Ok(None);
+                            }
+
+                            if action == PromptAction::BatchRename {
+                                let batch = self
+                                    .rename_state
+                                    .as_ref()
+                                    .map(|rs| rs.batch.clone())
+                                    .unwrap_or_default();
+                                match self.batch_rename(&batch, &new_name) {
+                                    Ok((count, failed)) if failed.is_empty() => {
+                                        self.rename_state = None;
+                                        self.status =
+                                            Some(format!(" Renamed {} wallpapers ", count));
+                                    }
+                                    // The rest were renamed, the status says what was left
+                                    Ok((count, failed)) => {
+                                        self.rename_state = None;
+                                        let errors = failed
+                                            .iter()
+                                            .map(|(path, e)| {
+                                                format!(
+                                                    "{}: {}",
+                                                    path.file_name()
+                                                        .unwrap_or_default()
+                                                        .to_string_lossy(),
+                                                    e
+                                                )
+                                            })
+                                            .collect::<Vec<_>>()
+                                            .join("; ");
+                                        self.status = Some(format!(
+                                            " Renamed {} wallpapers, {} failed: {} ",
+                                            count,
+                                            failed.len(),
+                                            errors
+                                        ));
+                                    }
+                                    Err(e) => {
+                                        if let Some(rs) = self.rename_state.as_mut() {
+                                            rs.error = Some(e.to_string());
+                                        }
+                                    }
+                                }
+                                return Ok(None);
+                            }
+
                             match self.rename_wallpaper(&original_path, &new_name) {
                                 Ok(new_path) => {
                                     self.rename_state = None;
 
                                     if self.last_preview.as_ref() == Some(&original_path) {
                                         self.last_preview = Some(new_path.clone());
-                                        self.request_preview(new_path);
+                                        self.request_preview(Some(new_path), Vec::new());
                                     } else {
                                         let current_filtered = self.filter_items();
                                         if let Some(current_selected) =
                                             current_filtered.get(self.selected)
-                                            && current_selected == &new_path
+                                            && current_selected.path == new_path
                                         {
                                             self.last_preview = Some(new_path.clone());
-                                            self.request_preview(new_path);
+                                            self.request_preview(Some(new_path), Vec::new());
                                         }
                                     }
 
@@ -800,72 +3916,220 @@ impl<'a> TuiApp<'a> {
                             self.rename_state = None;
                             return Ok(None);
                         }
-                        KeyCode::Char(c) => {
-                            if let Some(rs) = self.rename_state.as_mut() {
-                                rs.current_input.push(c);
+                        _ => {
+                            if let Some(rs) = self.rename_state.as_mut()
+                                && rs.input.handle_key(&key)
+                            {
                                 rs.error = None;
                             }
                         }
-                        KeyCode::Backspace => {
-                            if let Some(rs) = self.rename_state.as_mut() {
-                                rs.current_input.pop();
-                                rs.error = None;
+                    }
+                    return Ok(None);
+                }
+                _ => {}
+            }
+        } else if let Some(confirm_state) = self.confirm_state.as_ref() {
+            if let event::Event::Key(key) = event {
+                match key.code {
+                    KeyCode::Enter | KeyCode::Char('y') => {
+                        let paths = match &confirm_state.action {
+                            Confirm::Trash(paths) => paths.clone(),
+                            Confirm::ClearList(tab) => {
+                                self.clear_list(*tab);
+                                self.confirm_state = None;
+                                return Ok(None);
+                            }
+                        };
+                        let failed = self.delete_wallpapers(&paths);
+                        self.selection.clear();
+                        self.multi_select = false;
+
+                        // Keep the dialog open on the files that couldn't be removed
+                        if failed.is_empty() {
+                            self.confirm_state = None;
+                        } else {
+                            let error = failed
+                                .iter()
+                                .map(|(path, e)| {
+                                    format!(
+                                        "{}: {}",
+                                        path.file_name().unwrap_or_default().to_string_lossy(),
+                                        e
+                                    )
+                                })
+                                .collect::<Vec<_>>()
+                                .join("; ");
+                            self.confirm_state = Some(ConfirmState {
+                                action: Confirm::Trash(
+                                    failed.into_iter().map(|(path, _)| path).collect(),
+                                ),
+                                error: Some(error),
+                            });
+                        }
+                    }
+                    KeyCode::Esc | KeyCode::Char('n') => {
+                        self.confirm_state = None;
+                    }
+                    _ => {}
+                }
+            }
+        } else if self.menu_state.is_some() {
+            let size = self.terminal.size()?;
+            let screen = Rect::new(0, 0, size.width, size.height);
+            let menu = self.menu_state.as_mut().unwrap();
+            let rect = menu_rect(screen, menu.anchor, &menu.items);
+            match event {
+                event::Event::Key(key) => match key.code {
+                    KeyCode::Up => menu.selected = menu.selected.saturating_sub(1),
+                    KeyCode::Down => {
+                        menu.selected = (menu.selected + 1).min(menu.items.len() - 1);
+                    }
+                    KeyCode::Enter => {
+                        let action = menu.items[menu.selected].1;
+                        self.menu_state = None;
+                        return self.perform(action, filtered);
+                    }
+                    KeyCode::Esc => self.menu_state = None,
+                    _ => {}
+                },
+                event::Event::Mouse(me) => {
+                    let inner = rect.inner(Margin::new(1, 1));
+                    let hit = (me.column >= inner.x
+                        && me.column < inner.right()
+                        && me.row >= inner.y
+                        && me.row < inner.bottom())
+                    .then(|| (me.row - inner.y) as usize)
+                    .filter(|i| *i < menu.items.len());
+                    match me.kind {
+                        event::MouseEventKind::Moved => {
+                            if let Some(i) = hit {
+                                menu.selected = i;
+                            }
+                        }
+                        // A click on an item runs it, anywhere else closes the menu
+                        event::MouseEventKind::Down(_) => {
+                            let action = hit.map(|i| menu.items[i].1);
+                            self.menu_state = None;
+                            if let Some(action) = action
+                                && me.kind == event::MouseEventKind::Down(event::MouseButton::Left)
+                            {
+                                return self.perform(action, filtered);
                             }
                         }
                         _ => {}
                     }
-                    return Ok(None);
                 }
                 _ => {}
             }
+        } else if self.switcher_state.is_some() {
+            if let event::Event::Key(key) = event {
+                match key.code {
+                    KeyCode::Enter => {
+                        let state = self.switcher_state.as_ref().unwrap();
+                        let results = self.switcher_results(state.query.text());
+                        if let Some(pick) = results.get(state.selected).cloned() {
+                            self.switcher_state = None;
+                            return self.select_in_wallpapers(&pick);
+                        }
+                    }
+                    KeyCode::Esc => {
+                        self.switcher_state = None;
+                    }
+                    KeyCode::Up => {
+                        if let Some(ps) = self.switcher_state.as_mut() {
+                            ps.selected = ps.selected.saturating_sub(1);
+                        }
+                    }
+                    KeyCode::Down => {
+                        let state = self.switcher_state.as_ref().unwrap();
+                        let count = self.switcher_results(state.query.text()).len();
+                        if let Some(ps) = self.switcher_state.as_mut()
+                            && ps.selected + 1 < count
+                        {
+                            ps.selected += 1;
+                        }
+                    }
+                    _ => {
+                        if let Some(ps) = self.switcher_state.as_mut()
+                            && ps.query.handle_key(&key)
+                        {
+                            ps.selected = 0;
+                        }
+                    }
+                }
+            }
         } else {
             match event {
+                event::Event::Key(key) if self.zoom_key(&key) => {}
                 event::Event::Key(key) => {
                     let active_tabs = self.active_tabs();
+                    let favorites_list = collection_list(&self.collection);
+                    let grid_columns = self.grid.then_some(self.grid_columns);
                     let mut filtered_vec = filtered.to_vec();
                     let mut input = Input {
                         key: key.code,
+                        modifiers: key.modifiers,
                         current_tab: &mut self.current_tab,
                         in_search: &mut self.in_search,
                         search_query: &mut self.search_query,
+                        search_history: &mut self.search_history,
                         selected: &mut self.selected,
                         list_state: &mut self.list_state,
                         filtered: &mut filtered_vec,
                         history: &mut self.history,
                         favorites: &mut self.favorites,
+                        queue: &mut self.queue,
                         vim_motion: self.config.vim_motion,
-                        mouse_support: self.config.mouse_support,
+                        wrap_navigation: self.config.wrap_navigation,
                         keybindings: &self.config.keybindings,
                         active_tabs: &active_tabs,
+                        skip_colors: &mut self.skip_colors,
+                        light: &mut self.light,
+                        preview_resize: &mut self.preview_resize,
+                        grid: &mut self.grid,
+                        favorites_by_name: &mut self.favorites_by_name,
+                        favorites_list: &favorites_list,
+                        show_swatches: &mut self.show_swatches,
+                        grid_columns,
+                        page_size: self.list_height,
                     };
 
-                    if let Some(sel) =
-                        handle_input(&mut input, &mut self.multi_select, &mut self.selected_items)
+                    if let Some(action) =
+                        handle_input(&mut input, &mut self.multi_select, &mut self.selection)
                     {
-                        if sel == PathBuf::from("__rename__") {
-                            if !filtered.is_empty() {
-                                self.rename_state = Some(RenameState {
-                                    original_path: filtered[self.selected].clone(),
-                                    current_input: String::new(),
-                                    error: None,
-                                });
-                            }
-                            return Ok(None);
-                        }
-                        return Ok(Some(sel));
+                        return self.perform(action, filtered);
                     }
                 }
                 event::Event::Mouse(me) if self.config.mouse_support => {
+                    let tabs = self.active_tabs();
                     let mut mouse_input = MouseInput {
                         me,
                         selected: &mut self.selected,
                         list_state: &mut self.list_state,
                         filtered,
-                        list_area: &Rect::new(0, 3, 40, 20),
-                        tabs_area: &Rect::new(0, 0, 80, 3),
+                        areas: &self.mouse_areas,
+                        tabs: &tabs,
                         current_tab: &mut self.current_tab,
+                        multi_select: &mut self.multi_select,
+                        selection: &mut self.selection,
+                        last_click: &mut self.last_click,
+                        double_click: Duration::from_millis(self.config.double_click_ms),
                     };
-                    handle_mouse(&mut mouse_input);
+                    if let Some(action) = handle_mouse(&mut mouse_input) {
+                        return self.perform(action, filtered);
+                    }
+
+                    // Right-click on an entry opens its menu, the click above
+                    // already highlighted it
+                    if me.kind == event::MouseEventKind::Down(event::MouseButton::Right)
+                        && entry_at(&self.mouse_areas, me.column, me.row).is_some()
+                    {
+                        self.menu_state = Some(MenuState {
+                            anchor: (me.column, me.row),
+                            selected: 0,
+                            items: self.menu_items(filtered),
+                        });
+                    }
                 }
                 _ => {}
             }
@@ -873,3 +4137,386 @@ impl<'a> TuiApp<'a> {
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_image(dir: &Path, name: &str, size: u32) -> PathBuf {
+        let path = dir.join(name);
+        DynamicImage::new_rgb8(size, size).save(&path).unwrap();
+        path
+    }
+
+    // The top row of a list block drawn with the title
+    fn drawn_title(title: &str, selected: usize, len: usize, picked: Option<usize>) -> String {
+        use ratatui::backend::TestBackend;
+
+        let mut terminal = Terminal::new(TestBackend::new(50, 3)).unwrap();
+        terminal
+            .draw(|f| {
+                let block = Block::default()
+                    .title(counted_title(title, selected, len, picked))
+                    .borders(Borders::ALL);
+                f.render_widget(block, f.area());
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.width)
+            .map(|x| buffer[(x, 0)].symbol())
+            .collect::<String>()
+            .trim_matches(|c| c == '┌' || c == '┐' || c == '─')
+            .to_string()
+    }
+
+    #[test]
+    fn menus_fit_labels_by_display_width() {
+        let area = Rect::new(0, 0, 80, 24);
+        let items = |label: &str| vec![(label.to_string(), Action::NewCollection)];
+        // Both labels are 12 bytes, the wide chars take two columns each
+        assert_eq!(menu_rect(area, (0, 0), &items("壁紙一覧")).width, 8 + 4);
+        assert_eq!(menu_rect(area, (0, 0), &items("Éèêëàâ")).width, 6 + 4);
+    }
+
+    #[test]
+    fn list_title_counts_the_position() {
+        assert_eq!(drawn_title("History", 11, 387, None), "History (12/387)");
+        assert_eq!(
+            drawn_title("Search: sky ", 0, 3, None),
+            "Search: sky  (1/3)"
+        );
+    }
+
+    #[test]
+    fn list_title_is_zero_of_zero_when_empty() {
+        assert_eq!(drawn_title("Favorites", 0, 0, None), "Favorites (0/0)");
+    }
+
+    #[test]
+    fn list_title_counts_the_selection() {
+        assert_eq!(
+            drawn_title("History", 11, 387, Some(5)),
+            "History (12/387, 5 selected)"
+        );
+    }
+
+    #[test]
+    fn panic_hook_restores_before_the_message() {
+        use std::sync::Mutex;
+        use std::thread;
+
+        // Other tests may panic meanwhile, only this thread's panic is recorded
+        let test = thread::current().id();
+        let events = Arc::new(Mutex::new(Vec::<String>::new()));
+        let (restored, printed) = (Arc::clone(&events), Arc::clone(&events));
+        let previous = panic::take_hook();
+        panic::set_hook(restoring_hook(
+            move || {
+                if thread::current().id() == test {
+                    restored.lock().unwrap().push("restore".into());
+                }
+            },
+            Box::new(move |info| {
+                if thread::current().id() == test {
+                    let message = info.payload().downcast_ref::<&str>().unwrap();
+                    printed.lock().unwrap().push(message.to_string());
+                }
+            }),
+        ));
+        let result = panic::catch_unwind(|| panic!("mid-run"));
+        panic::set_hook(previous);
+
+        assert!(result.is_err());
+        assert_eq!(*events.lock().unwrap(), ["restore", "mid-run"]);
+    }
+
+    fn labels(paths: &[&str]) -> Vec<String> {
+        let paths: Vec<&Path> = paths.iter().map(Path::new).collect();
+        disambiguate_labels(&paths)
+    }
+
+    #[test]
+    fn unique_names_stay_bare() {
+        assert_eq!(
+            labels(&["/w/nature/a.png", "/w/city/b.png"]),
+            ["a.png", "b.png"]
+        );
+        assert!(labels(&[]).is_empty());
+    }
+
+    #[test]
+    fn duplicate_names_get_their_folder() {
+        assert_eq!(
+            labels(&["/w/nature/a.png", "/w/city/a.png", "/w/b.png"]),
+            ["nature/a.png", "city/a.png", "b.png"]
+        );
+    }
+
+    #[test]
+    fn duplicate_folders_go_up_until_they_differ() {
+        assert_eq!(
+            labels(&["/w/x/dark/a.png", "/w/y/dark/a.png", "/w/y/light/a.png"]),
+            ["x/dark/a.png", "y/dark/a.png", "light/a.png"]
+        );
+    }
+
+    #[test]
+    fn labels_stop_at_the_full_path() {
+        // The same path twice can't be told apart, it ends as the full path
+        assert_eq!(labels(&["a.png", "a.png"]), ["a.png", "a.png"]);
+        assert_eq!(labels(&["/a.png", "/b/a.png"]), ["/a.png", "b/a.png"]);
+    }
+
+    fn cached(size: u32) -> CachedImage {
+        CachedImage {
+            image: Arc::new(DynamicImage::new_rgba8(size, size)),
+            is_video: false,
+            dimensions: Some((size, size)),
+            palette: Vec::new(),
+        }
+    }
+
+    fn protocol(cut: (Rect, PreviewResize), size: u32) -> CachedProtocol {
+        let image = DynamicImage::new_rgba8(size, size);
+        CachedProtocol {
+            cut,
+            size: (size, size),
+            protocol: Picker::from_fontsize((10, 20)).new_resize_protocol(image),
+        }
+    }
+
+    const CUT: (Rect, PreviewResize) = (Rect::new(0, 0, 80, 40), PreviewResize::Fit);
+
+    fn cached_names(cache: &ImageCache) -> Vec<&str> {
+        let mut names: Vec<&str> = cache
+            .cache
+            .iter()
+            .map(|(path, _)| path.to_str().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn eviction_drops_the_least_recently_used() {
+        let mut cache = ImageCache::new(3, None);
+        for name in ["a", "b", "c"] {
+            cache.insert(PathBuf::from(name), cached(1));
+        }
+
+        // Viewing `a` makes `b` the oldest
+        cache.get(&PathBuf::from("a"));
+        cache.insert(PathBuf::from("d"), cached(1));
+        assert_eq!(cached_names(&cache), ["a", "c", "d"]);
+
+        // Peeking (the preload check) leaves the order alone
+        assert!(cache.peek(Path::new("c")).is_some());
+        cache.insert(PathBuf::from("e"), cached(1));
+        assert_eq!(cached_names(&cache), ["a", "d", "e"]);
+
+        // Replacing an entry refreshes it too
+        cache.insert(PathBuf::from("a"), cached(1));
+        cache.insert(PathBuf::from("f"), cached(1));
+        assert_eq!(cached_names(&cache), ["a", "e", "f"]);
+    }
+
+    #[test]
+    fn protocol_bytes_follow_keep_take_evict_and_resize() {
+        let mut cache = ImageCache::new(2, None);
+        let (a, b, c) = (PathBuf::from("a"), PathBuf::from("b"), PathBuf::from("c"));
+        cache.insert(a.clone(), cached(10));
+        cache.insert(b.clone(), cached(10));
+        let images = 2 * 10 * 10 * 4;
+        assert_eq!(cache.bytes, images);
+
+        cache.keep_protocol(a.clone(), protocol(CUT, 20));
+        assert_eq!(cache.bytes, images + 20 * 20 * 4);
+        // Only kept for images that are cached
+        cache.keep_protocol(c.clone(), protocol(CUT, 20));
+        assert_eq!(cache.bytes, images + 20 * 20 * 4);
+
+        assert!(cache.take_protocol(&a, CUT).is_some());
+        assert_eq!(cache.bytes, images);
+
+        // Made for another pane, dropped rather than handed out
+        cache.keep_protocol(a.clone(), protocol(CUT, 20));
+        let other = (Rect::new(0, 0, 40, 40), PreviewResize::Fit);
+        assert!(cache.take_protocol(&a, other).is_none());
+        assert_eq!(cache.bytes, images);
+
+        // Evicting the image takes its protocol along
+        cache.keep_protocol(a.clone(), protocol(CUT, 20));
+        cache.get(&b);
+        cache.insert(c.clone(), cached(10));
+        assert!(cache.peek(&a).is_none());
+        assert!(cache.protocols.is_empty());
+        assert_eq!(cache.bytes, images);
+
+        // A terminal resize drops them all
+        cache.keep_protocol(b.clone(), protocol(CUT, 20));
+        cache.keep_protocol(c.clone(), protocol(CUT, 20));
+        cache.clear_protocols();
+        assert!(cache.protocols.is_empty());
+        assert_eq!(cache.bytes, images);
+    }
+
+    #[test]
+    fn protocols_count_against_the_byte_budget() {
+        let mut cache = ImageCache::new(10, Some(3 * 10 * 10 * 4));
+        for name in ["a", "b", "c"] {
+            cache.insert(PathBuf::from(name), cached(10));
+        }
+        cache.keep_protocol(PathBuf::from("c"), protocol(CUT, 10));
+        assert!(cache.peek(Path::new("a")).is_none());
+        assert!(cache.bytes <= 3 * 10 * 10 * 4);
+    }
+
+    // Revisiting a large wallpaper, with and without a kept protocol. Run with
+    // `cargo test --release -- --ignored --nocapture` to see the timings.
+    #[test]
+    #[ignore]
+    fn bench_revisit_large_preview() {
+        use ratatui::buffer::Buffer;
+        use ratatui::widgets::StatefulWidget;
+
+        let image = DynamicImage::new_rgb8(4000, 3000);
+        let mut picker = Picker::from_fontsize((10, 20));
+        let area = Rect::new(0, 0, 200, 60);
+        let render = |state: &mut StatefulProtocol| {
+            let mut buffer = Buffer::empty(area);
+            StatefulImage::new().render(area, &mut buffer, state);
+        };
+
+        let start = Instant::now();
+        let mut state = picker.new_resize_protocol(image.clone());
+        render(&mut state);
+        let fresh = start.elapsed();
+
+        let path = PathBuf::from("large");
+        let mut cache = ImageCache::new(2, None);
+        cache.insert(path.clone(), cached(1));
+        let cut = (area, PreviewResize::Fit);
+        cache.keep_protocol(
+            path.clone(),
+            CachedProtocol {
+                cut,
+                size: (image.width(), image.height()),
+                protocol: state,
+            },
+        );
+        let start = Instant::now();
+        let mut state = cache.take_protocol(&path, cut).unwrap().protocol;
+        render(&mut state);
+        let kept = start.elapsed();
+
+        eprintln!("new protocol: {:?}, kept protocol: {:?}", fresh, kept);
+        assert!(kept <= fresh);
+    }
+
+    #[tokio::test]
+    async fn stale_preview_request_gives_up_for_the_newer_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = write_image(dir.path(), "old.png", 64);
+        let new = write_image(dir.path(), "new.png", 32);
+
+        // No decode slot until both requests are waiting, so the older one
+        // gets it first and has to notice it was overtaken
+        let current = PreviewGeneration::default();
+        let decodes = Arc::new(Semaphore::new(0));
+        let (tx, mut rx) = mpsc::channel(4);
+        for path in [&old, &new] {
+            let job = PreviewJob {
+                path: Some(path.clone()),
+                neighbours: Vec::new(),
+                generation: current.next(),
+                max_edge: 1200,
+            };
+            spawn_preview(job, current.clone(), decodes.clone(), tx.clone());
+        }
+        tokio::time::sleep(PREVIEW_DEBOUNCE * 2).await;
+        decodes.add_permits(PREVIEW_DECODES);
+
+        let mut results = HashMap::new();
+        for _ in 0..2 {
+            let (path, result) = rx.recv().await.unwrap();
+            results.insert(path, result);
+        }
+        assert!(results[&old].is_none());
+        let shown = results[&new].as_ref().unwrap().as_ref().unwrap();
+        assert_eq!(shown.dimensions, Some((32, 32)));
+    }
+
+    #[test]
+    fn decoded_previews_stay_within_max_edge() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, w, h) in [("wide.png", 1500, 500), ("tall.png", 450, 1200)] {
+            let path = dir.path().join(name);
+            DynamicImage::new_rgb8(w, h).save(&path).unwrap();
+            let image = CachedImage::new(&path, 300).unwrap();
+            assert!(image.image.width() <= 300 && image.image.height() <= 300);
+            assert_eq!(image.image.width().max(image.image.height()), 300);
+            // The source size is still reported
+            assert_eq!(image.dimensions, Some((w, h)));
+            assert!(image.byte_size() <= 300 * 300 * 4);
+        }
+
+        // Smaller images are left alone
+        let path = write_image(dir.path(), "small.png", 100);
+        let image = CachedImage::new(&path, 300).unwrap();
+        assert_eq!((image.image.width(), image.image.height()), (100, 100));
+    }
+
+    #[test]
+    fn decode_applies_the_exif_orientation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("phone.jpg");
+        // Stored landscape, red on the left and blue on the right, tagged as
+        // needing a quarter turn clockwise (orientation 6)
+        let stored = image::RgbImage::from_fn(40, 20, |x, _| {
+            if x < 20 {
+                image::Rgb([255, 0, 0])
+            } else {
+                image::Rgb([0, 0, 255])
+            }
+        });
+        let mut jpeg = Vec::new();
+        DynamicImage::ImageRgb8(stored)
+            .write_to(&mut io::Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+        let exif: &[u8] = &[
+            0xFF, 0xE1, 0x00, 0x22, b'E', b'x', b'i', b'f', 0, 0, // APP1
+            b'M', b'M', 0x00, 0x2A, 0x00, 0x00, 0x00, 0x08, // TIFF header
+            0x00, 0x01, // one entry
+            0x01, 0x12, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x06, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, // no next IFD
+        ];
+        jpeg.splice(2..2, exif.iter().copied());
+        fs::write(&path, jpeg).unwrap();
+
+        let image = CachedImage::new(&path, 1200).unwrap();
+        assert_eq!(image.dimensions, Some((20, 40)));
+        let pixels = image.image.to_rgb8();
+        assert_eq!((pixels.width(), pixels.height()), (20, 40));
+        // The left edge ends up on top
+        let top = pixels.get_pixel(10, 5);
+        let bottom = pixels.get_pixel(10, 35);
+        assert!(top[0] > 200 && top[2] < 60, "top is {:?}", top);
+        assert!(bottom[2] > 200 && bottom[0] < 60, "bottom is {:?}", bottom);
+    }
+
+    #[test]
+    fn decode_stops_once_no_longer_wanted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_image(dir.path(), "big.png", 64);
+
+        // Still wanted when it starts, overtaken once the pixels are decoded
+        let checks = std::cell::Cell::new(0);
+        let wanted = || {
+            checks.set(checks.get() + 1);
+            checks.get() == 1
+        };
+        assert!(CachedImage::load(&path, 1200, &wanted).is_err());
+        assert_eq!(checks.get(), 3);
+        assert!(CachedImage::load(&path, 1200, &|| true).is_ok());
+    }
+}
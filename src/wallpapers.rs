@@ -1,34 +1,753 @@
-use std::path::PathBuf;
-use walkdir::WalkDir;
+use crate::config::Config;
+use crate::persistence::{load_validation_cache, save_validation_cache};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use jwalk::WalkDir;
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
 
+/// An image or video found by a scan.
+#[derive(Debug)]
+pub struct Wallpaper {
+    pub path: PathBuf,
+    pub file_size: u64,
+    pub modified: Option<SystemTime>,
+    // Probed from the file header on first access
+    dimensions: OnceLock<Option<(u32, u32)>>,
+}
+
+impl Wallpaper {
+    pub fn new(path: PathBuf, file_size: u64, modified: Option<SystemTime>) -> Self {
+        Self {
+            path,
+            file_size,
+            modified,
+            dimensions: OnceLock::new(),
+        }
+    }
+
+    // For paths that didn't come from a scan (history, favorites)
+    pub fn from_path(path: PathBuf) -> Self {
+        let metadata = fs::metadata(&path).ok();
+        Self::new(
+            path,
+            metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+            metadata.and_then(|m| m.modified().ok()),
+        )
+    }
+
+    // Same file metadata under a new name
+    pub fn renamed(&self, path: PathBuf) -> Self {
+        Self {
+            path,
+            file_size: self.file_size,
+            modified: self.modified,
+            dimensions: self.dimensions.clone(),
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    }
+
+    // Path below the first root containing it, or the full path for files
+    // outside all of them
+    pub fn relative_name(&self, roots: &[PathBuf]) -> String {
+        roots
+            .iter()
+            .find_map(|root| self.path.strip_prefix(root).ok())
+            .unwrap_or(&self.path)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    // Folder relative to its wallpaper_dir, empty at the top level
+    pub fn relative_dir(&self, roots: &[PathBuf]) -> String {
+        roots
+            .iter()
+            .find_map(|root| self.path.parent()?.strip_prefix(root).ok())
+            .or(self.path.parent())
+            .unwrap_or(Path::new(""))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        *self.dimensions.get_or_init(|| {
+            imagesize::size(&self.path)
+                .ok()
+                .map(|s| (s.width as u32, s.height as u32))
+        })
+    }
+}
+
+// Wallpapers are identified by their path
+impl PartialEq for Wallpaper {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl PartialEq<Path> for Wallpaper {
+    fn eq(&self, other: &Path) -> bool {
+        self.path == other
+    }
+}
+
+/// Filters applied while scanning, usually built with
+/// [`ScanOptions::from_config`].
+pub struct ScanOptions<'a> {
+    pub extensions: &'a [String],
+    pub exclude: &'a [String],
+    pub video: bool,
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+    pub show_hidden: bool,
+    pub min_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub aspect_ratio: Option<f64>,
+    pub aspect_tolerance: f64,
+}
+
+// Entries found on disk but left out of the result
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ScanStats {
+    pub below_resolution: usize,
+    pub wrong_aspect: usize,
+    // Dotfiles and dot-directories, counted once per directory
+    pub hidden: usize,
+    pub empty: usize,
+}
+
+impl<'a> ScanOptions<'a> {
+    pub fn from_config(config: &'a Config) -> Self {
+        Self {
+            extensions: &config.extensions,
+            exclude: &config.exclude,
+            video: config.mpvpaper,
+            max_depth: config.max_depth,
+            follow_symlinks: config.follow_symlinks,
+            show_hidden: config.show_hidden,
+            min_width: config.min_width,
+            min_height: config.min_height,
+            aspect_ratio: config.aspect_ratio,
+            aspect_tolerance: config.aspect_tolerance,
+        }
+    }
+}
+
+// Persisted lists stay path-based
+pub fn paths(list: &[Arc<Wallpaper>]) -> Vec<PathBuf> {
+    list.iter().map(|w| w.path.clone()).collect()
+}
+
+pub enum ScanEvent {
+    Batch(Vec<Wallpaper>),
+    Done(Result<ScanStats, String>),
+}
+
+// Number of files stat'ed together before a batch is handed out
+const SCAN_BATCH: usize = 512;
+
+// Same-named files in different folders are ordered by path
+pub fn sort_key(w: &Wallpaper) -> (String, PathBuf) {
+    (w.file_name().to_lowercase(), w.path.clone())
+}
+
+/// Scans `roots` and returns the wallpapers sorted by name, with counts of
+/// what the filters skipped.
 pub fn load_wallpapers(
-    dir: &PathBuf,
-    video: &bool,
-) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
-    let mut wallpapers: Vec<_> = WalkDir::new(dir)
+    roots: &[PathBuf],
+    options: &ScanOptions,
+) -> Result<(Vec<Wallpaper>, ScanStats), Box<dyn std::error::Error>> {
+    let mut wallpapers = Vec::new();
+    let stats = scan(roots, options, |batch| wallpapers.extend(batch))?;
+
+    wallpapers.sort_by_cached_key(sort_key);
+
+    Ok((wallpapers, stats))
+}
+
+// Scans on a blocking task and streams unsorted batches as they're found,
+// finishing with `ScanEvent::Done`
+pub fn spawn_scan(config: Config) -> mpsc::Receiver<ScanEvent> {
+    let (tx, rx) = mpsc::channel(16);
+
+    tokio::task::spawn_blocking(move || {
+        let options = ScanOptions::from_config(&config);
+        let result = scan(&config.wallpaper_dirs, &options, |batch| {
+            let _ = tx.blocking_send(ScanEvent::Batch(batch));
+        });
+        let _ = tx.blocking_send(ScanEvent::Done(result.map_err(|e| e.to_string())));
+    });
+
+    rx
+}
+
+fn scan(
+    roots: &[PathBuf],
+    options: &ScanOptions,
+    mut on_batch: impl FnMut(Vec<Wallpaper>),
+) -> Result<ScanStats, Box<dyn std::error::Error>> {
+    let (exclude_set, exclude_dirs) = build_exclude_sets(options.exclude)?;
+    // A file reachable from several roots (or links) is listed once
+    let dedup = options.follow_symlinks || roots.len() > 1;
+    let hidden = Arc::new(AtomicUsize::new(0));
+
+    let mut stats = ScanStats::default();
+    let mut seen = HashSet::new();
+    let mut pending = Vec::with_capacity(SCAN_BATCH);
+
+    for root in roots {
+        let walker = walk_root(
+            root,
+            options,
+            exclude_set.clone(),
+            exclude_dirs.clone(),
+            Arc::clone(&hidden),
+        );
+
+        // Symlink loops surface as walk errors and are skipped
+        let candidates = walker
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| {
+                Path::new(&e.file_name)
+                    .extension()
+                    .and_then(|s| s.to_str())
+                    .map(|ext| {
+                        // Include mp4 alongside images when video is true
+                        options
+                            .extensions
+                            .iter()
+                            .any(|e| e.eq_ignore_ascii_case(ext))
+                            || (options.video && ext.eq_ignore_ascii_case("mp4"))
+                    })
+                    .unwrap_or(false)
+            });
+
+        for entry in candidates {
+            pending.push(entry.path());
+            if pending.len() >= SCAN_BATCH {
+                let batch = std::mem::take(&mut pending);
+                on_batch(process_batch(batch, options, dedup, &mut seen, &mut stats));
+            }
+        }
+    }
+    if !pending.is_empty() {
+        on_batch(process_batch(
+            pending, options, dedup, &mut seen, &mut stats,
+        ));
+    }
+
+    stats.hidden = hidden.load(Ordering::Relaxed);
+
+    Ok(stats)
+}
+
+// Directories are read in parallel; hidden and excluded entries are
+// pruned before they're descended into
+fn walk_root(
+    root: &Path,
+    options: &ScanOptions,
+    exclude_set: GlobSet,
+    exclude_dirs: GlobSet,
+    hidden: Arc<AtomicUsize>,
+) -> WalkDir {
+    let base = root.to_path_buf();
+    let show_hidden = options.show_hidden;
+    let mut walker = WalkDir::new(root)
+        .sort(true)
+        .skip_hidden(false)
+        .follow_links(options.follow_symlinks)
+        .process_read_dir(move |depth, _, _, children| {
+            // The root itself comes through here without a depth
+            if depth.is_none() {
+                return;
+            }
+            children.retain(|child| {
+                let Ok(e) = child else {
+                    return true;
+                };
+                if !show_hidden && e.file_name.to_string_lossy().starts_with('.') {
+                    hidden.fetch_add(1, Ordering::Relaxed);
+                    return false;
+                }
+                let path = e.path();
+                let rel = path.strip_prefix(&base).unwrap_or(&path);
+                !(exclude_set.is_match(rel)
+                    || (e.file_type().is_dir() && exclude_dirs.is_match(rel)))
+            });
+        });
+    if let Some(depth) = options.max_depth {
+        walker = walker.max_depth(depth);
+    }
+    walker
+}
+
+fn process_batch(
+    paths: Vec<PathBuf>,
+    options: &ScanOptions,
+    dedup: bool,
+    seen: &mut HashSet<PathBuf>,
+    stats: &mut ScanStats,
+) -> Vec<Wallpaper> {
+    // Metadata (and canonical paths when deduplicating) is gathered in parallel
+    let entries: Vec<(Wallpaper, Option<PathBuf>)> = paths
+        .into_par_iter()
+        .map(|path| {
+            let metadata = fs::metadata(&path).ok();
+            let canonical = dedup.then(|| fs::canonicalize(&path).unwrap_or_else(|_| path.clone()));
+            let wallpaper = Wallpaper::new(
+                path,
+                metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+                metadata.and_then(|m| m.modified().ok()),
+            );
+            (wallpaper, canonical)
+        })
+        .collect();
+
+    // Files reachable through several links are kept once by canonical path,
+    // zero-byte files are always skipped
+    let wallpapers: Vec<Wallpaper> = entries
+        .into_iter()
+        .filter(|(_, canonical)| canonical.as_ref().is_none_or(|c| seen.insert(c.clone())))
+        .filter(|(w, _)| {
+            if w.file_size == 0 {
+                stats.empty += 1;
+            }
+            w.file_size > 0
+        })
+        .map(|(w, _)| w)
+        .collect();
+
+    filter_dimensions(wallpapers, options, stats)
+}
+
+// Cheap header decode; videos aren't checked
+pub fn is_readable_image(path: &Path) -> bool {
+    let is_video = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| {
+            ["mp4", "avi", "mov", "mkv", "webm"]
+                .iter()
+                .any(|v| v.eq_ignore_ascii_case(ext))
+        });
+    if is_video {
+        return true;
+    }
+
+    match image::ImageReader::open(path).and_then(|r| r.with_guessed_format()) {
+        Ok(reader) => reader.into_dimensions().is_ok(),
+        Err(_) => false,
+    }
+}
+
+// Returns the unreadable files. Results are cached by (path, mtime) so
+// unchanged files aren't checked again on the next launch
+pub fn find_broken(entries: Vec<(PathBuf, Option<SystemTime>)>) -> HashSet<PathBuf> {
+    let cache = load_validation_cache();
+
+    let results: Vec<(PathBuf, u64, bool)> = entries
+        .into_par_iter()
+        .map(|(path, modified)| {
+            let mtime = modified
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let ok = match cache.get(&path) {
+                Some(&(cached_mtime, ok)) if cached_mtime == mtime => ok,
+                _ => is_readable_image(&path),
+            };
+            (path, mtime, ok)
+        })
+        .collect();
+
+    save_validation_cache(&results);
+
+    results
         .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter(|e| {
-            e.path()
-                .extension()
-                .and_then(|s| s.to_str())
-                .map(|ext| {
-                    let ext_lower = ext.to_lowercase();
-                    if *video {
-                        // Include both images and mp4 when video is true
-                        ["jpg", "jpeg", "png", "mp4"].contains(&ext_lower.as_str())
-                    } else {
-                        // Only include images when video is false
-                        ["jpg", "jpeg", "png"].contains(&ext_lower.as_str())
-                    }
-                })
-                .unwrap_or(false)
+        .filter(|(_, _, ok)| !ok)
+        .map(|(path, _, _)| path)
+        .collect()
+}
+
+// Header probing runs in parallel; files whose size can't be read are kept
+fn filter_dimensions(
+    wallpapers: Vec<Wallpaper>,
+    options: &ScanOptions,
+    stats: &mut ScanStats,
+) -> Vec<Wallpaper> {
+    enum Verdict {
+        Keep,
+        BelowResolution,
+        WrongAspect,
+    }
+
+    if options.min_width.is_none() && options.min_height.is_none() && options.aspect_ratio.is_none()
+    {
+        return wallpapers;
+    }
+
+    let verdicts: Vec<(Wallpaper, Verdict)> = wallpapers
+        .into_par_iter()
+        .map(|w| {
+            let verdict = match w.dimensions() {
+                Some((width, height))
+                    if width < options.min_width.unwrap_or(0)
+                        || height < options.min_height.unwrap_or(0) =>
+                {
+                    Verdict::BelowResolution
+                }
+                Some((width, height))
+                    if height > 0
+                        && options.aspect_ratio.is_some_and(|target| {
+                            ((width as f64 / height as f64) - target).abs() / target
+                                > options.aspect_tolerance
+                        }) =>
+                {
+                    Verdict::WrongAspect
+                }
+                _ => Verdict::Keep,
+            };
+            (w, verdict)
         })
-        .map(|e| e.path().to_path_buf())
         .collect();
 
-    wallpapers.sort_by_key(|p| p.file_name().unwrap().to_string_lossy().to_lowercase());
+    verdicts
+        .into_iter()
+        .filter_map(|(w, verdict)| match verdict {
+            Verdict::Keep => Some(w),
+            Verdict::BelowResolution => {
+                stats.below_resolution += 1;
+                None
+            }
+            Verdict::WrongAspect => {
+                stats.wrong_aspect += 1;
+                None
+            }
+        })
+        .collect()
+}
+
+// Patterns ending in `/**` also match the directory itself so it can be pruned
+pub(crate) fn build_exclude_sets(
+    patterns: &[String],
+) -> Result<(GlobSet, GlobSet), Box<dyn std::error::Error>> {
+    let mut files = GlobSetBuilder::new();
+    let mut dirs = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| {
+            format!(
+                "Invalid exclude pattern '{}' in config.toml: {}",
+                pattern, e
+            )
+        })?;
+        files.add(glob);
+
+        if let Some(prefix) = pattern.strip_suffix("/**")
+            && let Ok(glob) = Glob::new(prefix)
+        {
+            dirs.add(glob);
+        }
+    }
+
+    Ok((files.build()?, dirs.build()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn extensions() -> Vec<String> {
+        ["jpg", "jpeg", "png", "webp"]
+            .iter()
+            .map(|e| e.to_string())
+            .collect()
+    }
+
+    fn options(extensions: &[String]) -> ScanOptions<'_> {
+        ScanOptions {
+            extensions,
+            exclude: &[],
+            video: false,
+            max_depth: None,
+            follow_symlinks: false,
+            show_hidden: false,
+            min_width: None,
+            min_height: None,
+            aspect_ratio: None,
+            aspect_tolerance: 0.05,
+        }
+    }
+
+    fn write(path: &Path, contents: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    // `count` files spread over nested folders, with other extensions, empty
+    // and hidden files mixed in
+    fn generate_tree(root: &Path, count: usize) {
+        for i in 0..count {
+            let dir = root
+                .join(format!("d{}", i % 37))
+                .join(format!("e{}", i % 5));
+            let extension = ["png", "JPG", "webp", "txt", "jpeg"][i % 5];
+            let name = match i % 11 {
+                0 => format!(".hidden{}.png", i),
+                _ => format!("wall{}.{}", i % 97, extension),
+            };
+            write(&dir.join(name), if i % 13 == 0 { b"" } else { b"x" });
+        }
+    }
+
+    // One folder at a time, the way the scan worked before it went parallel
+    fn sequential_scan(root: &Path, extensions: &[String]) -> Vec<PathBuf> {
+        let mut found = Vec::new();
+        let mut dirs = vec![root.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(&dir).unwrap().flatten() {
+                let path = entry.path();
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| extensions.iter().any(|x| x.eq_ignore_ascii_case(e)))
+                    && fs::metadata(&path).unwrap().len() > 0
+                {
+                    found.push(path);
+                }
+            }
+        }
+        let mut wallpapers: Vec<Wallpaper> = found.into_iter().map(Wallpaper::from_path).collect();
+        wallpapers.sort_by_cached_key(sort_key);
+        paths_of(&wallpapers)
+    }
+
+    fn paths_of(wallpapers: &[Wallpaper]) -> Vec<PathBuf> {
+        wallpapers.iter().map(|w| w.path.clone()).collect()
+    }
+
+    #[test]
+    fn parallel_scan_matches_sequential_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        generate_tree(dir.path(), 2000);
+        let extensions = extensions();
+
+        let (wallpapers, _) =
+            load_wallpapers(&[dir.path().to_path_buf()], &options(&extensions)).unwrap();
+        let expected = sequential_scan(dir.path(), &extensions);
+        assert!(!expected.is_empty());
+        assert_eq!(paths_of(&wallpapers), expected);
+    }
+
+    fn names(wallpapers: &[Wallpaper]) -> Vec<String> {
+        wallpapers
+            .iter()
+            .map(|w| w.file_name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn mixed_extensions_yield_the_configured_set() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "a.jpg", "b.JPEG", "c.png", "d.webp", "e.gif", "f.bmp", "g.tiff", "h.txt", "i.mp4", "j",
+        ] {
+            write(&dir.path().join(name), b"x");
+        }
+        let roots = [dir.path().to_path_buf()];
+
+        let defaults = Config::default().extensions;
+        let (wallpapers, _) = load_wallpapers(&roots, &options(&defaults)).unwrap();
+        assert_eq!(
+            names(&wallpapers),
+            [
+                "a.jpg", "b.JPEG", "c.png", "d.webp", "e.gif", "f.bmp", "g.tiff"
+            ]
+        );
+
+        // A trimmed list only keeps those, video adds mp4 on top
+        let trimmed = vec!["png".to_string(), "gif".to_string()];
+        let mut options = options(&trimmed);
+        let (wallpapers, _) = load_wallpapers(&roots, &options).unwrap();
+        assert_eq!(names(&wallpapers), ["c.png", "e.gif"]);
+        options.video = true;
+        let (wallpapers, _) = load_wallpapers(&roots, &options).unwrap();
+        assert_eq!(names(&wallpapers), ["c.png", "e.gif", "i.mp4"]);
+    }
+
+    // The tree from the request: a dotfile, a dot-directory and an empty file
+    #[test]
+    fn exclude_prunes_folders_and_drops_matching_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "beach.png",
+            "beach_edit.png",
+            "city/night.jpg",
+            "city/night_edit.jpg",
+            "thumbnails/beach.png",
+            "thumbnails/small/beach.png",
+        ] {
+            write(&dir.path().join(name), b"x");
+        }
+        let extensions = extensions();
+        let exclude = vec!["thumbnails/**".to_string(), "*_edit.*".to_string()];
+        let mut options = options(&extensions);
+        options.exclude = &exclude;
+
+        let (wallpapers, _) = load_wallpapers(&[dir.path().to_path_buf()], &options).unwrap();
+        assert_eq!(names(&wallpapers), ["beach.png", "night.jpg"]);
+
+        // The folder itself is dropped while reading its parent, so the walk
+        // never enters it
+        let (files, dirs) = build_exclude_sets(&exclude).unwrap();
+        let walked: Vec<PathBuf> = walk_root(dir.path(), &options, files, dirs, Arc::default())
+            .into_iter()
+            .flatten()
+            .map(|e| e.path().strip_prefix(dir.path()).unwrap().to_path_buf())
+            .collect();
+        assert!(walked.iter().all(|p| !p.starts_with("thumbnails")));
+        assert!(walked.contains(&PathBuf::from("city")));
+
+        let error = build_exclude_sets(&["[thumbs".to_string()]).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Invalid exclude pattern '[thumbs'")
+        );
+    }
+
+    fn hidden_tree(root: &Path) {
+        write(&root.join("wall.png"), b"x");
+        write(&root.join(".hidden.png"), b"x");
+        write(&root.join(".cache/foo.jpg"), b"x");
+        write(&root.join("empty.png"), b"");
+    }
+
+    #[test]
+    fn hidden_and_empty_files_are_skipped_and_counted() {
+        let dir = tempfile::tempdir().unwrap();
+        hidden_tree(dir.path());
+        let extensions = extensions();
+
+        let (wallpapers, stats) =
+            load_wallpapers(&[dir.path().to_path_buf()], &options(&extensions)).unwrap();
+        assert_eq!(names(&wallpapers), ["wall.png"]);
+        // `.cache` counts once, its contents are never read
+        assert_eq!(stats.hidden, 2);
+        assert_eq!(stats.empty, 1);
+    }
+
+    #[test]
+    fn show_hidden_keeps_dotfiles_but_not_empty_files() {
+        let dir = tempfile::tempdir().unwrap();
+        hidden_tree(dir.path());
+        let extensions = extensions();
+
+        let mut options = options(&extensions);
+        options.show_hidden = true;
+        let (wallpapers, stats) = load_wallpapers(&[dir.path().to_path_buf()], &options).unwrap();
+        assert_eq!(names(&wallpapers), [".hidden.png", "foo.jpg", "wall.png"]);
+        assert_eq!(stats.hidden, 0);
+        assert_eq!(stats.empty, 1);
+    }
+
+    // root/top.png, root/a/mid.png, root/a/b/deep.png, plus root/a/b/loop -> root
+    // and root/link -> root/a
+    #[cfg(unix)]
+    fn linked_tree(root: &Path) {
+        write(&root.join("top.png"), b"x");
+        write(&root.join("a/mid.png"), b"x");
+        write(&root.join("a/b/deep.png"), b"x");
+        std::os::unix::fs::symlink(root, root.join("a/b/loop")).unwrap();
+        std::os::unix::fs::symlink(root.join("a"), root.join("link")).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn max_depth_limits_the_walk() {
+        let dir = tempfile::tempdir().unwrap();
+        linked_tree(dir.path());
+        let extensions = extensions();
+        let roots = [dir.path().to_path_buf()];
+
+        let mut options = options(&extensions);
+        options.max_depth = Some(1);
+        let (wallpapers, _) = load_wallpapers(&roots, &options).unwrap();
+        assert_eq!(names(&wallpapers), ["top.png"]);
+
+        options.max_depth = Some(2);
+        let (wallpapers, _) = load_wallpapers(&roots, &options).unwrap();
+        assert_eq!(names(&wallpapers), ["mid.png", "top.png"]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlinks_are_skipped_by_default() {
+        let dir = tempfile::tempdir().unwrap();
+        linked_tree(dir.path());
+        let extensions = extensions();
+
+        let (wallpapers, _) =
+            load_wallpapers(&[dir.path().to_path_buf()], &options(&extensions)).unwrap();
+        assert_eq!(names(&wallpapers), ["deep.png", "mid.png", "top.png"]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn followed_symlink_loops_end_and_list_each_file_once() {
+        let dir = tempfile::tempdir().unwrap();
+        linked_tree(dir.path());
+        let extensions = extensions();
+
+        let mut options = options(&extensions);
+        options.follow_symlinks = true;
+        let (wallpapers, _) = load_wallpapers(&[dir.path().to_path_buf()], &options).unwrap();
+        assert_eq!(names(&wallpapers), ["deep.png", "mid.png", "top.png"]);
+    }
+
+    // Run with `cargo test --release -- --ignored --nocapture` to see the timings
+    #[test]
+    #[ignore]
+    fn bench_scan_50k_files() {
+        let dir = tempfile::tempdir().unwrap();
+        generate_tree(dir.path(), 50_000);
+        let extensions = extensions();
+
+        let start = Instant::now();
+        let expected = sequential_scan(dir.path(), &extensions);
+        let sequential = start.elapsed();
+
+        let start = Instant::now();
+        let (wallpapers, _) =
+            load_wallpapers(&[dir.path().to_path_buf()], &options(&extensions)).unwrap();
+        let parallel = start.elapsed();
 
-    Ok(wallpapers)
+        eprintln!(
+            "{} wallpapers, sequential: {:?}, parallel: {:?}",
+            expected.len(),
+            sequential,
+            parallel
+        );
+        assert_eq!(paths_of(&wallpapers), expected);
+    }
 }